@@ -0,0 +1,508 @@
+pub mod webhook;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commons::{blockchain::Reorg, status::ProviderStatus},
+    conf::{get_configuration, AlertRules, Network, Protocol},
+    db::DATABASE,
+};
+
+/**
+ * AlertRule is the condition which raised an alert, provider rules raise an alert per provider
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertRule {
+    NoNewBlock,
+    ProviderLag,
+    EndpointDown,
+    Reorg,
+    ChainMismatch,
+}
+impl std::fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            AlertRule::NoNewBlock => "no_new_block",
+            AlertRule::ProviderLag => "provider_lag",
+            AlertRule::EndpointDown => "endpoint_down",
+            AlertRule::Reorg => "reorg",
+            AlertRule::ChainMismatch => "chain_mismatch",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+/**
+ * Alert is a rule condition observed on a network, provider is set for provider rules
+ * id is unique per network, an alert already firing with the same id is deduplicated
+ * value is the last observed value (seconds, blocks), threshold the rule one
+ * started_at, resolved_at and notified_at are unix time in seconds
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub id: String,
+    pub rule: AlertRule,
+    pub status: AlertStatus,
+    pub protocol: Protocol,
+    pub network: Network,
+    pub provider: Option<String>,
+    pub value: u64,
+    pub threshold: u64,
+    pub summary: String,
+    pub started_at: u64,
+    pub resolved_at: Option<u64>,
+    pub notified_at: u64,
+}
+
+// Condition is a rule observed during an evaluation: rule, provider, value, threshold and summary
+type Condition = (AlertRule, Option<String>, u64, u64, String);
+
+/**
+ * AlertEngine evaluate network alert rules, it is owned by the network collector
+ * Firing alerts are resumed from database, a restarted collector doesn't notify them again
+ * repeat_interval is the delay between notifications of a still firing alert, 0 notify it only once
+ * tick_rate is the network polling interval in seconds, an endpoint is requested at most every rate + tick_rate
+ */
+pub struct AlertEngine {
+    protocol: Protocol,
+    network: Network,
+    rules: AlertRules,
+    repeat_interval: u64,
+    tick_rate: u64,
+    firing: HashMap<String, Alert>,
+    started_at: u64,
+    // canonical head height, and the time it was first seen
+    head: Option<(u64, u64)>,
+    // providers chain identity mismatch, last identity check error
+    mismatches: HashMap<String, String>,
+    // last deep reorg, and canonical head height when it was evaluated
+    reorg: Option<(Reorg, Option<u64>)>,
+}
+
+impl AlertEngine {
+    pub fn new(
+        protocol: Protocol,
+        network: Network,
+        rules: AlertRules,
+        repeat_interval: u64,
+        tick_rate: u64,
+        firing: Vec<Alert>,
+        now: u64,
+    ) -> AlertEngine {
+        AlertEngine {
+            protocol,
+            network,
+            rules,
+            repeat_interval,
+            tick_rate,
+            firing: firing.into_iter().map(|a| (a.id.clone(), a)).collect(),
+            started_at: now,
+            head: None,
+            mismatches: HashMap::new(),
+            reorg: None,
+        }
+    }
+    // observe_chain_identity record provider identity check, mismatch is the check error
+    pub fn observe_chain_identity(&mut self, provider: &str, mismatch: Option<String>) {
+        match mismatch {
+            Some(mismatch) => self.mismatches.insert(provider.to_string(), mismatch),
+            None => self.mismatches.remove(provider),
+        };
+    }
+    // observe_reorg record reorgs deeper than reorg_depth rule
+    pub fn observe_reorg(&mut self, reorg: &Reorg) {
+        if self
+            .rules
+            .reorg_depth
+            .is_some_and(|depth| reorg.depth > depth)
+        {
+            self.reorg = Some((reorg.clone(), None));
+        }
+    }
+    // conditions return rules observed on network providers and canonical head
+    fn conditions(
+        &mut self,
+        statuses: &[ProviderStatus],
+        head: Option<u64>,
+        now: u64,
+    ) -> Vec<Condition> {
+        let mut conditions = Vec::new();
+        if let Some(height) = head {
+            if self.head.map_or(true, |(h, _)| h != height) {
+                self.head = Some((height, now));
+            }
+        }
+        if let Some(threshold) = self.rules.no_new_block {
+            let since = self.head.map(|(_, at)| at).unwrap_or(self.started_at);
+            let value = now.saturating_sub(since);
+            if value > threshold {
+                conditions.push((
+                    AlertRule::NoNewBlock,
+                    None,
+                    value,
+                    threshold,
+                    format!("no new block for {}s", value),
+                ));
+            }
+        }
+        if let Some((reorg, at)) = &mut self.reorg {
+            let height = head.unwrap_or_default();
+            if *at.get_or_insert(height) == height {
+                conditions.push((
+                    AlertRule::Reorg,
+                    None,
+                    reorg.depth,
+                    self.rules.reorg_depth.unwrap_or_default(),
+                    format!(
+                        "reorg of {} blocks at height {}, {} replaced by {}",
+                        reorg.depth, reorg.height, reorg.old_hash, reorg.new_hash
+                    ),
+                ));
+            } else {
+                self.reorg = None;
+            }
+        }
+        for status in statuses {
+            let provider = Some(status.name.clone());
+            // endpoints skipped until their scrapping rate elapse are not up, down time is the last success age
+            // threshold is at least the delay between two requests, an endpoint slower than the rule doesn't flap
+            if let Some(threshold) = self.rules.endpoint_down {
+                let threshold = threshold.max(status.options.rate as u64 + self.tick_rate);
+                let value = now.saturating_sub(status.last_success.unwrap_or(self.started_at));
+                if value > threshold {
+                    conditions.push((
+                        AlertRule::EndpointDown,
+                        provider.clone(),
+                        value,
+                        threshold,
+                        format!(
+                            "{} is down for {}s: {}",
+                            status.name,
+                            value,
+                            status.last_error.as_deref().unwrap_or("no response")
+                        ),
+                    ));
+                }
+            }
+            // lag is the last one observed while provider was returning blocks
+            if let Some(threshold) = self.rules.provider_lag {
+                if status.lag > threshold as i64 {
+                    conditions.push((
+                        AlertRule::ProviderLag,
+                        provider.clone(),
+                        status.lag as u64,
+                        threshold,
+                        format!(
+                            "{} is {} blocks behind canonical head",
+                            status.name, status.lag
+                        ),
+                    ));
+                }
+            }
+            if self.rules.chain_mismatch == Some(true) {
+                if let Some(mismatch) = self.mismatches.get(&status.name) {
+                    conditions.push((
+                        AlertRule::ChainMismatch,
+                        provider,
+                        1,
+                        0,
+                        format!("{} {}", status.name, mismatch),
+                    ));
+                }
+            }
+        }
+        conditions
+    }
+    // evaluate compare observed conditions with firing alerts, and return alerts to notify
+    // new alerts are firing, firing alerts no longer observed are resolved, still firing alerts are repeated every repeat_interval
+    pub fn evaluate(
+        &mut self,
+        statuses: &[ProviderStatus],
+        head: Option<u64>,
+        now: u64,
+    ) -> Vec<Alert> {
+        let mut notify = Vec::new();
+        let mut observed = Vec::new();
+        for (rule, provider, value, threshold, summary) in self.conditions(statuses, head, now) {
+            let id = match &provider {
+                Some(provider) => format!("{}:{}", rule, provider),
+                None => rule.to_string(),
+            };
+            match self.firing.get_mut(&id) {
+                Some(alert) => {
+                    alert.value = value;
+                    alert.summary = summary;
+                    if self.repeat_interval > 0
+                        && now.saturating_sub(alert.notified_at) >= self.repeat_interval
+                    {
+                        alert.notified_at = now;
+                        notify.push(alert.clone());
+                    }
+                }
+                None => {
+                    let alert = Alert {
+                        id: id.clone(),
+                        rule,
+                        status: AlertStatus::Firing,
+                        protocol: self.protocol,
+                        network: self.network.clone(),
+                        provider,
+                        value,
+                        threshold,
+                        summary,
+                        started_at: now,
+                        resolved_at: None,
+                        notified_at: now,
+                    };
+                    self.firing.insert(id.clone(), alert.clone());
+                    notify.push(alert);
+                }
+            }
+            observed.push(id);
+        }
+        let resolved = self
+            .firing
+            .keys()
+            .filter(|id| !observed.contains(id))
+            .cloned()
+            .collect::<Vec<_>>();
+        for id in resolved {
+            if let Some(mut alert) = self.firing.remove(&id) {
+                alert.status = AlertStatus::Resolved;
+                alert.resolved_at = Some(now);
+                alert.notified_at = now;
+                notify.push(alert);
+            }
+        }
+        notify
+    }
+}
+
+// repeat_interval return configured delay between notifications of a still firing alert
+pub fn repeat_interval() -> u64 {
+    get_configuration()
+        .and_then(|c| c.global.alerts)
+        .map(|a| a.repeat_interval)
+        .unwrap_or(crate::conf::DEFAULT_ALERTS_REPEAT_INTERVAL)
+}
+
+// notify store alerts state and post them to configured webhooks, webhooks are called in background
+pub fn notify(alerts: Vec<Alert>) {
+    if alerts.is_empty() {
+        return;
+    }
+    let db = DATABASE.get().unwrap();
+    for alert in alerts.iter() {
+        let r = match alert.status {
+            AlertStatus::Firing => {
+                warn!(
+                    "Alert {} firing on {} {}: {}",
+                    alert.id, alert.protocol, alert.network, alert.summary
+                );
+                db.set_alert(alert)
+            }
+            AlertStatus::Resolved => {
+                info!(
+                    "Alert {} resolved on {} {}",
+                    alert.id, alert.protocol, alert.network
+                );
+                db.remove_alert(&alert.protocol, &alert.network, &alert.id)
+            }
+        };
+        if let Err(e) = r {
+            error!(
+                "Error saving alert {} {} {}: {}",
+                alert.protocol, alert.network, alert.id, e
+            );
+        }
+    }
+    let webhooks = match get_configuration().and_then(|c| c.global.alerts) {
+        Some(config) => config.webhooks,
+        None => return,
+    };
+    tokio::spawn(async move {
+        for alert in alerts.iter() {
+            for hook in webhooks.iter() {
+                if let Err(e) = webhook::post(hook, alert).await {
+                    error!("Error posting alert {} to webhook: {}", alert.id, e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conf::EndpointOptions;
+
+    fn provider(name: &str, up: bool, lag: i64, last_success: Option<u64>) -> ProviderStatus {
+        let mut status = ProviderStatus::new(
+            name.to_string(),
+            "bitcoin_node",
+            true,
+            EndpointOptions::default(),
+        );
+        status.up = up;
+        status.lag = lag;
+        status.last_success = last_success;
+        status
+    }
+
+    fn engine(rules: AlertRules, firing: Vec<Alert>) -> AlertEngine {
+        AlertEngine::new(
+            Protocol::Bitcoin,
+            "mainnet".to_string(),
+            rules,
+            60,
+            5,
+            firing,
+            1000,
+        )
+    }
+
+    #[test]
+    fn alerts_no_new_block_dedup_and_resolve() {
+        let mut engine = engine(
+            AlertRules {
+                no_new_block: Some(30),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+        assert!(engine.evaluate(&[], Some(10), 1000).is_empty());
+        assert!(engine.evaluate(&[], Some(10), 1030).is_empty());
+        let alerts = engine.evaluate(&[], Some(10), 1031);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].id, "no_new_block");
+        assert_eq!(alerts[0].status, AlertStatus::Firing);
+        assert_eq!(alerts[0].value, 31);
+        // still firing alert is deduplicated until repeat interval
+        assert!(engine.evaluate(&[], Some(10), 1090).is_empty());
+        let alerts = engine.evaluate(&[], Some(10), 1091);
+        assert_eq!(alerts[0].status, AlertStatus::Firing);
+        assert_eq!(alerts[0].started_at, 1031);
+        let alerts = engine.evaluate(&[], Some(11), 1100);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].status, AlertStatus::Resolved);
+        assert_eq!(alerts[0].resolved_at, Some(1100));
+        assert!(engine.evaluate(&[], Some(11), 1101).is_empty());
+    }
+
+    #[test]
+    fn alerts_providers_rules() {
+        let mut engine = engine(
+            AlertRules {
+                provider_lag: Some(2),
+                endpoint_down: Some(20),
+                chain_mismatch: Some(true),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+        let statuses = vec![
+            provider("node1", true, 3, Some(1000)),
+            provider("node2", false, 0, Some(989)),
+            provider("node3", false, 2, Some(1009)),
+        ];
+        engine.observe_chain_identity("node3", Some("chain mismatch".to_string()));
+        let alerts = engine.evaluate(&statuses, Some(10), 1010);
+        let mut ids = alerts.iter().map(|a| a.id.as_str()).collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                "chain_mismatch:node3",
+                "endpoint_down:node2",
+                "provider_lag:node1"
+            ]
+        );
+        assert_eq!(alerts[0].provider.as_deref(), Some("node1"));
+        engine.observe_chain_identity("node3", None);
+        let statuses = vec![
+            provider("node1", true, 0, Some(1000)),
+            provider("node2", true, 0, Some(1011)),
+        ];
+        let alerts = engine.evaluate(&statuses, Some(10), 1011);
+        assert_eq!(alerts.len(), 3);
+        assert!(alerts.iter().all(|a| a.status == AlertStatus::Resolved));
+    }
+
+    #[test]
+    fn alerts_endpoint_down_above_request_rate() {
+        let mut engine = engine(
+            AlertRules {
+                endpoint_down: Some(30),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+        // endpoint requested every 60s plus a 5s tick is not down between two requests
+        let mut status = provider("node1", false, 0, Some(1000));
+        status.options.rate = 60;
+        assert!(engine.evaluate(&[status.clone()], None, 1040).is_empty());
+        assert!(engine.evaluate(&[status.clone()], None, 1065).is_empty());
+        let alerts = engine.evaluate(&[status], None, 1066);
+        assert_eq!(alerts[0].id, "endpoint_down:node1");
+        assert_eq!((alerts[0].value, alerts[0].threshold), (66, 65));
+    }
+
+    #[test]
+    fn alerts_reorg_resolved_by_next_head() {
+        let mut engine = engine(
+            AlertRules {
+                reorg_depth: Some(1),
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+        let reorg = Reorg {
+            height: 9,
+            depth: 1,
+            old_hash: "a9".to_string(),
+            new_hash: "b9".to_string(),
+            provider: "node1".to_string(),
+            detected_at: 1000,
+        };
+        engine.observe_reorg(&reorg);
+        assert!(engine.evaluate(&[], Some(10), 1000).is_empty());
+        engine.observe_reorg(&Reorg { depth: 2, ..reorg });
+        let alerts = engine.evaluate(&[], Some(10), 1001);
+        assert_eq!(alerts[0].rule, AlertRule::Reorg);
+        assert_eq!(alerts[0].value, 2);
+        assert!(engine.evaluate(&[], Some(10), 1002).is_empty());
+        let alerts = engine.evaluate(&[], Some(11), 1003);
+        assert_eq!(alerts[0].status, AlertStatus::Resolved);
+    }
+
+    #[test]
+    fn alerts_resume_firing_alerts() {
+        let rules = AlertRules {
+            no_new_block: Some(30),
+            ..Default::default()
+        };
+        let mut first = engine(rules.clone(), Vec::new());
+        let firing = first.evaluate(&[], None, 1031);
+        assert_eq!(firing.len(), 1);
+        // restarted engine doesn't notify stored alerts again
+        let mut restarted = engine(rules, firing);
+        assert!(restarted.evaluate(&[], None, 1040).is_empty());
+        // alerts of disabled rules are resolved
+        let mut disabled = engine(
+            AlertRules::default(),
+            restarted.firing.into_values().collect(),
+        );
+        let alerts = disabled.evaluate(&[], None, 1041);
+        assert_eq!(alerts[0].status, AlertStatus::Resolved);
+    }
+}
@@ -0,0 +1,122 @@
+use std::{error::Error, time::Duration};
+
+use once_cell::sync::Lazy;
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde_json::{Map, Value};
+
+use super::Alert;
+use crate::conf::Webhook;
+
+// WEBHOOK_TIMEOUT is the max duration of a webhook request
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+static CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .expect("webhook client can be built")
+});
+
+// post send alert to webhook, payload is rendered from webhook template
+pub async fn post(webhook: &Webhook, alert: &Alert) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let alert = serde_json::to_value(alert)?;
+    let payload = match &webhook.template {
+        Some(template) => render(template, &alert),
+        None => alert,
+    };
+    let mut request = CLIENT
+        .post(&webhook.url)
+        .header(CONTENT_TYPE, "application/json")
+        .body(payload.to_string());
+    for (key, value) in webhook.headers.iter().flatten() {
+        request = request.header(key, value);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(format!("webhook responded with status {}", response.status()).into());
+    }
+    Ok(())
+}
+
+// render replace `{{field}}` placeholders of template strings by alert fields
+// a string holding only a placeholder is replaced by the field json value
+pub fn render(template: &Value, alert: &Value) -> Value {
+    match template {
+        Value::String(s) => {
+            let trimmed = s.trim();
+            if let Some(field) = trimmed
+                .strip_prefix("{{")
+                .and_then(|f| f.strip_suffix("}}"))
+                .filter(|f| !f.contains("{{"))
+            {
+                return alert.get(field.trim()).cloned().unwrap_or(Value::Null);
+            }
+            Value::String(render_str(s, alert))
+        }
+        Value::Array(values) => Value::Array(values.iter().map(|v| render(v, alert)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), render(v, alert)))
+                .collect::<Map<_, _>>(),
+        ),
+        v => v.clone(),
+    }
+}
+
+fn render_str(s: &str, alert: &Value) -> String {
+    let mut res = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        res.push_str(&rest[..start]);
+        match alert.get(rest[start + 2..end].trim()) {
+            Some(Value::String(v)) => res.push_str(v),
+            Some(Value::Null) | None => {}
+            Some(v) => res.push_str(&v.to_string()),
+        }
+        rest = &rest[end + 2..];
+    }
+    res.push_str(rest);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn webhook_render_template() {
+        let alert = json!({
+            "id": "provider_lag:node1",
+            "status": "firing",
+            "protocol": "bitcoin",
+            "network": "mainnet",
+            "provider": "node1",
+            "value": 3,
+            "resolved_at": null,
+        });
+        let template = json!({
+            "text": "[{{status}}] {{ protocol }} {{network}} {{id}}: lag {{value}}{{resolved_at}}",
+            "lag": "{{value}}",
+            "tags": ["{{provider}}", "blockhead"],
+            "unknown": "{{missing}}",
+            "unclosed": "{{status",
+            "count": 1,
+        });
+        assert_eq!(
+            render(&template, &alert),
+            json!({
+                "text": "[firing] bitcoin mainnet provider_lag:node1: lag 3",
+                "lag": 3,
+                "tags": ["node1", "blockhead"],
+                "unknown": null,
+                "unclosed": "{{status",
+                "count": 1,
+            })
+        );
+    }
+}
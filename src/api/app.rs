@@ -1,16 +1,121 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    time::SystemTime,
+};
 
-use actix_web::{get, web, HttpResponse};
+use actix_web::{get, http::header, web, HttpResponse};
+use serde::{Deserialize, Serialize};
 use serde_json::to_string;
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::{
-    commons::blockchain::Blockchain,
-    conf::{get_enabled_protocol_network, Network, Protocol},
-    db::DATABASE,
+    commons::{
+        blockchain::{Block, Blockchain},
+        events,
+        health::ChainHealth,
+        stats::BlockStats,
+        status::{get_providers_status, ProviderHead},
+    },
+    conf::{
+        get_configuration, get_enabled_protocol_network, Network, Protocol, DEFAULT_STALL_FACTOR,
+    },
+    db::{Redb, DATABASE},
 };
 
 type BlockchainRes = HashMap<Protocol, HashMap<Network, Blockchain>>;
 
+const DEFAULT_BLOCKS_LIMIT: usize = 100;
+const MAX_BLOCKS_LIMIT: usize = 1000;
+const DEFAULT_STATS_LIMIT: usize = 1000;
+const MAX_STATS_LIMIT: usize = 10000;
+
+// RangeQuery from and to are heights for blocks routes, unix time in milliseconds for history routes
+#[derive(Deserialize, Debug)]
+struct RangeQuery {
+    from: Option<u64>,
+    to: Option<u64>,
+    limit: Option<usize>,
+}
+impl RangeQuery {
+    fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_BLOCKS_LIMIT)
+            .clamp(1, MAX_BLOCKS_LIMIT)
+    }
+}
+// StatsQuery limit is the number of latest stored blocks aggregated
+#[derive(Deserialize, Debug)]
+struct StatsQuery {
+    limit: Option<usize>,
+}
+impl StatsQuery {
+    fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_STATS_LIMIT)
+            .clamp(2, MAX_STATS_LIMIT)
+    }
+}
+/**
+ * BlocksPage is a page of blocks sorted by height
+ * next is the from height of next page, None if there is no more blocks in range
+ */
+#[derive(Serialize, Debug)]
+struct BlocksPage {
+    blocks: Vec<Block>,
+    next: Option<u64>,
+}
+#[derive(Serialize, Debug)]
+struct HistoryPage {
+    heads: Vec<ProviderHead>,
+    next: Option<u64>,
+}
+
+/**
+ * Health is the health of every enabled network, status is stalled if a network is stalled
+ * networks without stored head have no health
+ */
+#[derive(Serialize, Debug)]
+struct Health {
+    status: &'static str,
+    networks: BTreeMap<String, BTreeMap<Network, Option<ChainHealth>>>,
+}
+
+// network_health compute network health from its stored head, None if nothing is stored yet
+fn network_health(
+    db: &Redb,
+    protocol: &Protocol,
+    network: &Network,
+) -> Result<Option<ChainHealth>, Box<dyn Error + Send + Sync>> {
+    let metadata = match db.get_metadata(protocol, network)? {
+        Some(metadata) => metadata,
+        None => return Ok(None),
+    };
+    let head = match db.get_block(protocol, network, metadata.height)? {
+        Some(head) => head,
+        None => return Ok(None),
+    };
+    let (block_time, stall_factor) =
+        match get_configuration().and_then(|c| c.get_network_options(protocol, network).cloned()) {
+            Some(net_opts) => (
+                net_opts.expected_block_time(protocol),
+                net_opts.stall_factor,
+            ),
+            None => (protocol.block_time(), DEFAULT_STALL_FACTOR),
+        };
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    Ok(Some(ChainHealth::new(
+        head.height,
+        head.time,
+        block_time,
+        stall_factor,
+        now,
+    )))
+}
+
 #[get("/ping")]
 async fn ping_handler() -> HttpResponse {
     HttpResponse::Ok().body("pong")
@@ -27,6 +132,309 @@ async fn protocol_net_handler(params: web::Path<(Protocol, Network)>) -> HttpRes
         .content_type("application/json")
         .body(to_string(&response).unwrap())
 }
+#[get("/protocols/{protocol}/{network}/reorgs")]
+async fn protocol_net_reorgs_handler(params: web::Path<(Protocol, Network)>) -> HttpResponse {
+    let db = match DATABASE.get() {
+        Some(db) => db,
+        None => return HttpResponse::InternalServerError().body("Database not initialized"),
+    };
+    let (protocol, network) = params.into_inner();
+    match db.get_reorgs(&protocol, &network) {
+        Ok(reorgs) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(to_string(&reorgs).unwrap()),
+        Err(e) => {
+            error!(
+                "Database req reorgs {} {} return error: {}",
+                &protocol.to_string(),
+                &network.to_string(),
+                e
+            );
+            HttpResponse::InternalServerError().body(format!(
+                "No reorg data found for {:?} protocol and {:?} network",
+                protocol, network
+            ))
+        }
+    }
+}
+// protocol_net_stream_handler push network events as Server-Sent Events, until client disconnect
+#[get("/protocols/{protocol}/{network}/stream")]
+async fn protocol_net_stream_handler(params: web::Path<(Protocol, Network)>) -> HttpResponse {
+    let (protocol, network) = params.into_inner();
+    let rx = events::subscribe();
+    let stream = futures::stream::unfold(rx, move |mut rx| {
+        let network = network.clone();
+        async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) if event.protocol == protocol && event.network == network => {
+                        let data = format!(
+                            "event: {}\ndata: {}\n\n",
+                            event.name(),
+                            to_string(&event).unwrap()
+                        );
+                        return Some((Ok::<_, actix_web::Error>(web::Bytes::from(data)), rx));
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(n)) => {
+                        warn!(
+                            "Stream {} {} client lagged, {} events skipped",
+                            protocol, network, n
+                        );
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        // events must be flushed as they come, do not let compress middleware buffer them
+        .insert_header((header::CONTENT_ENCODING, "identity"))
+        .streaming(stream)
+}
+// protocol_net_blocks_handler return stored blocks between from and to heights, paginated by limit
+#[get("/protocols/{protocol}/{network}/blocks")]
+async fn protocol_net_blocks_handler(
+    params: web::Path<(Protocol, Network)>,
+    query: web::Query<RangeQuery>,
+) -> HttpResponse {
+    let db = match DATABASE.get() {
+        Some(db) => db,
+        None => return HttpResponse::InternalServerError().body("Database not initialized"),
+    };
+    let (protocol, network) = params.into_inner();
+    let limit = query.limit();
+    let from = query.from.unwrap_or(0);
+    let to = query.to.unwrap_or(u64::MAX);
+    // fetch one more block to know if there is a next page
+    match db.get_blocks(&protocol, &network, from, to, limit + 1, false) {
+        Ok(blocks) if blocks.is_empty() => HttpResponse::NotFound().body(format!(
+            "No block found between {} and {} in {} {} history",
+            from, to, protocol, network
+        )),
+        Ok(mut blocks) => {
+            let next = blocks.get(limit).map(|b| b.height);
+            blocks.truncate(limit);
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(to_string(&BlocksPage { blocks, next }).unwrap())
+        }
+        Err(e) => {
+            error!(
+                "Database req blocks {} {} return error: {}",
+                &protocol.to_string(),
+                &network.to_string(),
+                e
+            );
+            HttpResponse::InternalServerError().body(format!(
+                "No block data found for {:?} protocol and {:?} network",
+                protocol, network
+            ))
+        }
+    }
+}
+// protocol_net_latest_blocks_handler return the last limit stored blocks, highest first
+#[get("/protocols/{protocol}/{network}/blocks/latest")]
+async fn protocol_net_latest_blocks_handler(
+    params: web::Path<(Protocol, Network)>,
+    query: web::Query<RangeQuery>,
+) -> HttpResponse {
+    let db = match DATABASE.get() {
+        Some(db) => db,
+        None => return HttpResponse::InternalServerError().body("Database not initialized"),
+    };
+    let (protocol, network) = params.into_inner();
+    match db.get_blocks(&protocol, &network, 0, u64::MAX, query.limit(), true) {
+        Ok(blocks) if blocks.is_empty() => HttpResponse::NotFound().body(format!(
+            "No block found in {} {} history",
+            protocol, network
+        )),
+        Ok(blocks) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(to_string(&blocks).unwrap()),
+        Err(e) => {
+            error!(
+                "Database req latest blocks {} {} return error: {}",
+                &protocol.to_string(),
+                &network.to_string(),
+                e
+            );
+            HttpResponse::InternalServerError().body(format!(
+                "No block data found for {:?} protocol and {:?} network",
+                protocol, network
+            ))
+        }
+    }
+}
+// protocol_net_stats_handler return block interval and throughput stats of the last limit stored blocks
+#[get("/protocols/{protocol}/{network}/stats")]
+async fn protocol_net_stats_handler(
+    params: web::Path<(Protocol, Network)>,
+    query: web::Query<StatsQuery>,
+) -> HttpResponse {
+    let db = match DATABASE.get() {
+        Some(db) => db,
+        None => return HttpResponse::InternalServerError().body("Database not initialized"),
+    };
+    let (protocol, network) = params.into_inner();
+    match db.get_blocks(&protocol, &network, 0, u64::MAX, query.limit(), true) {
+        Ok(blocks) => match BlockStats::new(&blocks) {
+            Some(stats) => HttpResponse::Ok()
+                .content_type("application/json")
+                .body(to_string(&stats).unwrap()),
+            None => HttpResponse::NotFound().body(format!(
+                "No timed block found in {} {} history",
+                protocol, network
+            )),
+        },
+        Err(e) => {
+            error!(
+                "Database req stats {} {} return error: {}",
+                protocol, network, e
+            );
+            HttpResponse::InternalServerError().body(format!(
+                "No block data found for {:?} protocol and {:?} network",
+                protocol, network
+            ))
+        }
+    }
+}
+#[get("/protocols/{protocol}/{network}/blocks/hash/{hash}")]
+async fn protocol_net_block_hash_handler(
+    params: web::Path<(Protocol, Network, String)>,
+) -> HttpResponse {
+    let db = match DATABASE.get() {
+        Some(db) => db,
+        None => return HttpResponse::InternalServerError().body("Database not initialized"),
+    };
+    let (protocol, network, hash) = params.into_inner();
+    block_response(
+        db.get_block_by_hash(&protocol, &network, &hash),
+        &protocol,
+        &network,
+        &hash,
+    )
+}
+#[get("/protocols/{protocol}/{network}/blocks/{height}")]
+async fn protocol_net_block_handler(params: web::Path<(Protocol, Network, u64)>) -> HttpResponse {
+    let db = match DATABASE.get() {
+        Some(db) => db,
+        None => return HttpResponse::InternalServerError().body("Database not initialized"),
+    };
+    let (protocol, network, height) = params.into_inner();
+    block_response(
+        db.get_block(&protocol, &network, height),
+        &protocol,
+        &network,
+        &height.to_string(),
+    )
+}
+// block_response return 404 if block is not in stored history
+fn block_response(
+    block: Result<Option<Block>, Box<dyn std::error::Error + Send + Sync>>,
+    protocol: &Protocol,
+    network: &Network,
+    id: &str,
+) -> HttpResponse {
+    match block {
+        Ok(Some(block)) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(to_string(&block).unwrap()),
+        Ok(None) => HttpResponse::NotFound().body(format!(
+            "Block {} not found in {} {} history",
+            id, protocol, network
+        )),
+        Err(e) => {
+            error!(
+                "Database req block {} {} {} return error: {}",
+                protocol, network, id, e
+            );
+            HttpResponse::InternalServerError().body(format!(
+                "No block data found for {:?} protocol and {:?} network",
+                protocol, network
+            ))
+        }
+    }
+}
+// protocol_net_providers_handler return last known status of every provider of a network
+#[get("/protocols/{protocol}/{network}/providers")]
+async fn protocol_net_providers_handler(params: web::Path<(Protocol, Network)>) -> HttpResponse {
+    let (protocol, network) = params.into_inner();
+    let providers = get_providers_status(&protocol, &network);
+    if providers.is_empty() {
+        return HttpResponse::NotFound()
+            .body(format!("No provider found for {} {}", protocol, network));
+    }
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(to_string(&providers).unwrap())
+}
+// protocol_net_health_handler return network head age and stalled state, with a 503 status when chain is stalled
+// health of a head without time is unknown, it is not reported as stalled
+#[get("/protocols/{protocol}/{network}/health")]
+async fn protocol_net_health_handler(params: web::Path<(Protocol, Network)>) -> HttpResponse {
+    let db = match DATABASE.get() {
+        Some(db) => db,
+        None => return HttpResponse::InternalServerError().body("Database not initialized"),
+    };
+    let (protocol, network) = params.into_inner();
+    match network_health(db, &protocol, &network) {
+        Ok(Some(health)) => {
+            let mut res = match health.stalled {
+                Some(true) => HttpResponse::ServiceUnavailable(),
+                _ => HttpResponse::Ok(),
+            };
+            res.content_type("application/json")
+                .body(to_string(&health).unwrap())
+        }
+        Ok(None) => {
+            HttpResponse::NotFound().body(format!("No head stored for {} {}", protocol, network))
+        }
+        Err(e) => {
+            error!(
+                "Database req health {} {} return error: {}",
+                protocol, network, e
+            );
+            HttpResponse::InternalServerError()
+                .body(format!("No data found for {} {}", protocol, network))
+        }
+    }
+}
+// protocol_net_provider_history_handler return heads observed for a provider, oldest first, paginated by limit
+#[get("/protocols/{protocol}/{network}/providers/{alias}/history")]
+async fn protocol_net_provider_history_handler(
+    params: web::Path<(Protocol, Network, String)>,
+    query: web::Query<RangeQuery>,
+) -> HttpResponse {
+    let db = match DATABASE.get() {
+        Some(db) => db,
+        None => return HttpResponse::InternalServerError().body("Database not initialized"),
+    };
+    let (protocol, network, alias) = params.into_inner();
+    let limit = query.limit();
+    let from = query.from.unwrap_or(0);
+    let to = query.to.unwrap_or(u64::MAX);
+    match db.get_provider_heads(&protocol, &network, &alias, from, to, limit + 1) {
+        Ok(mut heads) => {
+            let next = heads.get(limit).map(|h| h.observed_at);
+            heads.truncate(limit);
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(to_string(&HistoryPage { heads, next }).unwrap())
+        }
+        Err(e) => {
+            error!(
+                "Database req provider {} history {} {} return error: {}",
+                alias, protocol, network, e
+            );
+            HttpResponse::InternalServerError()
+                .body(format!("No history data found for {} provider", alias))
+        }
+    }
+}
 #[get("/protocols/{protocol}")]
 async fn protocol_handler(params: web::Path<Protocol>) -> HttpResponse {
     let db = match DATABASE.get() {
@@ -105,3 +513,45 @@ async fn protocols_handler() -> HttpResponse {
         .content_type("application/json")
         .body(to_string(&data).unwrap())
 }
+
+// health_handler return health of every enabled network, with a 503 status when a network is stalled
+#[get("/health")]
+async fn health_handler() -> HttpResponse {
+    let db = match DATABASE.get() {
+        Some(db) => db,
+        None => return HttpResponse::InternalServerError().body("Database not initialized"),
+    };
+    let proto_net = get_enabled_protocol_network().unwrap_or_default();
+    let mut health = Health {
+        status: "ok",
+        networks: BTreeMap::new(),
+    };
+    for (protocol, networks) in proto_net {
+        for network in networks {
+            let network_health = match network_health(db, &protocol, &network) {
+                Ok(network_health) => network_health,
+                Err(e) => {
+                    error!(
+                        "Database req health {} {} return error: {}",
+                        protocol, network, e
+                    );
+                    None
+                }
+            };
+            if network_health.as_ref().is_some_and(|h| h.stalled == Some(true)) {
+                health.status = "stalled";
+            }
+            health
+                .networks
+                .entry(protocol.to_string())
+                .or_default()
+                .insert(network, network_health);
+        }
+    }
+    let mut res = match health.status {
+        "ok" => HttpResponse::Ok(),
+        _ => HttpResponse::ServiceUnavailable(),
+    };
+    res.content_type("application/json")
+        .body(to_string(&health).unwrap())
+}
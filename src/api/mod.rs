@@ -1,2 +1,3 @@
 pub mod metrics;
 pub mod app;
+pub mod proxy;
\ No newline at end of file
@@ -0,0 +1,220 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use actix_web::{post, web, HttpResponse};
+use once_cell::sync::Lazy;
+use serde_json::{to_string, Value};
+
+use crate::{
+    commons::status::{get_providers_status, set_provider_down, ProviderStatus},
+    conf::{get_configuration, Network, Protocol, DEFAULT_PROXY_MAX_LAG},
+    requests::{
+        circuit_breaker::{circuit_state, CircuitState},
+        client::ReqwestClient,
+        rpc::RequestError,
+    },
+};
+
+type ProxyClientKey = (Protocol, Network, String);
+// PROXY_CLIENTS keep one client per proxied endpoint so connections are reused between requests
+static PROXY_CLIENTS: Lazy<Mutex<HashMap<ProxyClientKey, ReqwestClient>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// proxy_client return the pooled client of an endpoint, a new one is built if endpoint url changed
+// clones share connection pool, rate limiter and circuit breaker, they are not stored back after requests
+fn proxy_client(
+    protocol: &Protocol,
+    network: &Network,
+    endpoint: &ProviderStatus,
+) -> ReqwestClient {
+    let mut clients = PROXY_CLIENTS.lock().unwrap();
+    let key = (*protocol, network.clone(), endpoint.name.clone());
+    match clients.get(&key) {
+        Some(client) if client.config.url == endpoint.options.url => client.clone(),
+        _ => {
+            let client = ReqwestClient::new(endpoint.options.clone());
+            clients.insert(key, client.clone());
+            client
+        }
+    }
+}
+
+/**
+ * rpc_proxy_handler forward JSON-RPC request (single or batch) to the most up to date rpc endpoint
+ * Endpoints disagreeing with canonical blockchain are skipped, endpoints ahead of it are not preferred to synced ones
+ * If endpoint fail, request is forwarded to the next one
+ */
+#[post("/rpc/{protocol}/{network}")]
+async fn rpc_proxy_handler(
+    params: web::Path<(Protocol, Network)>,
+    body: web::Json<Value>,
+) -> HttpResponse {
+    let (protocol, network) = params.into_inner();
+    let max_lag = get_configuration()
+        .and_then(|c| c.global.proxy)
+        .map(|p| p.max_lag)
+        .unwrap_or(DEFAULT_PROXY_MAX_LAG);
+    let mut endpoints = get_providers_status(&protocol, &network)
+        .into_iter()
+        .filter(|p| {
+            p.rpc && p.up && p.syncing != Some(true) && !p.divergent && p.lag <= max_lag as i64
+        })
+        // circuit state is read live, it may have opened since collector last tick
+        .filter(|p| {
+            circuit_state(p.options.url.as_deref().unwrap_or_default()) != CircuitState::Open
+        })
+        .collect::<Vec<_>>();
+    if endpoints.is_empty() {
+        return HttpResponse::ServiceUnavailable().body(format!(
+            "No healthy rpc endpoint for {} {}",
+            protocol, network
+        ));
+    }
+    // a negative lag is a head above canonical one, it may be a fork not yet disputed
+    endpoints.sort_by_key(|p| p.lag.max(0));
+    let body = body.into_inner();
+    for endpoint in endpoints {
+        let mut client = proxy_client(&protocol, &network, &endpoint);
+        let res = client.rpc::<Value, Value>(&body, &protocol, &network).await;
+        match res {
+            Ok(res) => {
+                debug!(
+                    "rpc proxy {} {} request served by {}",
+                    protocol, network, endpoint.name
+                );
+                return HttpResponse::Ok()
+                    .content_type("application/json")
+                    .body(to_string(&res).unwrap());
+            }
+            Err(e) => {
+                warn!(
+                    "rpc proxy {} {} endpoint {} fail, try next endpoint: {}",
+                    protocol, network, endpoint.name, e
+                );
+                // local rate limits and open circuits are blockhead own throttling, endpoint may be healthy
+                if matches!(
+                    e,
+                    RequestError::Transport(_)
+                        | RequestError::Timeout(_)
+                        | RequestError::HttpStatus { .. }
+                ) {
+                    set_provider_down(&protocol, &network, &endpoint.name);
+                }
+            }
+        }
+    }
+    HttpResponse::BadGateway().body(format!(
+        "Every rpc endpoint fail for {} {}",
+        protocol, network
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commons::status::set_providers_status,
+        conf::{EndpointOptions, RateLimit, RatePeriod},
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+    };
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn proxy_rpc_failover() {
+        tests::setup();
+        let failing = tests::mock_server(SimApi::EthereumNode);
+        failing.behavior().fail_next = u32::MAX;
+        let healthy = tests::mock_server(SimApi::EthereumNode);
+        let network = "proxy_failover".to_string();
+        let status = |name: &str, url: String, lag: i64| {
+            let mut options = EndpointOptions::test_new(&url, None, None);
+            options.retry = 1;
+            let mut status = ProviderStatus::new(name.to_string(), "ethereum_node", true, options);
+            status.up = true;
+            status.lag = lag;
+            status
+        };
+        // failing endpoint is the most up to date one, it is tried first
+        set_providers_status(
+            &Protocol::Ethereum,
+            &network,
+            vec![
+                status("failing", failing.url(), 0),
+                status("healthy", healthy.url(), 1),
+            ],
+        );
+        let app = test::init_service(App::new().service(rpc_proxy_handler)).await;
+        let req = test::TestRequest::post()
+            .uri("/rpc/ethereum/proxy_failover")
+            .set_json(json!({"jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber", "params": []}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["result"], json!(format!("0x{:x}", MOCK_HEIGHT)));
+        assert_eq!(failing.requests(), 1);
+        assert_eq!(healthy.requests(), 1);
+        let statuses = get_providers_status(&Protocol::Ethereum, &network);
+        assert!(!statuses.iter().find(|s| s.name == "failing").unwrap().up);
+        assert!(statuses.iter().find(|s| s.name == "healthy").unwrap().up);
+        // endpoint throttled by its local rate limit is not set down
+        let mut throttled = status("throttled", healthy.url() + "/throttled", 0);
+        throttled.options.rate_limits = vec![RateLimit {
+            requests: 1,
+            per: RatePeriod::Day,
+        }];
+        set_providers_status(&Protocol::Ethereum, &network, vec![throttled]);
+        let requests = (0..2).map(|_| {
+            test::TestRequest::post()
+                .uri("/rpc/ethereum/proxy_failover")
+                .set_json(
+                    json!({"jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber", "params": []}),
+                )
+                .to_request()
+        });
+        let mut codes = Vec::new();
+        for req in requests {
+            codes.push(test::call_service(&app, req).await.status());
+        }
+        assert_eq!(codes, vec![StatusCode::OK, StatusCode::BAD_GATEWAY]);
+        assert!(get_providers_status(&Protocol::Ethereum, &network)[0].up);
+    }
+
+    #[actix_web::test]
+    async fn proxy_rpc_skip_divergent_and_ahead() {
+        tests::setup();
+        let forked = tests::mock_server(SimApi::EthereumNode);
+        let ahead = tests::mock_server(SimApi::EthereumNode);
+        let synced = tests::mock_server(SimApi::EthereumNode);
+        let network = "proxy_divergent".to_string();
+        let status = |name: &str, url: String, lag: i64, divergent: bool| {
+            let options = EndpointOptions::test_new(&url, None, None);
+            let mut status = ProviderStatus::new(name.to_string(), "ethereum_node", true, options);
+            status.up = true;
+            status.lag = lag;
+            status.divergent = divergent;
+            status
+        };
+        set_providers_status(
+            &Protocol::Ethereum,
+            &network,
+            vec![
+                status("forked", forked.url(), -2, true),
+                status("synced", synced.url(), 0, false),
+                status("ahead", ahead.url(), -1, false),
+            ],
+        );
+        let app = test::init_service(App::new().service(rpc_proxy_handler)).await;
+        let req = test::TestRequest::post()
+            .uri("/rpc/ethereum/proxy_divergent")
+            .set_json(json!({"jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber", "params": []}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        // divergent endpoint is skipped, endpoint ahead of canonical head is not preferred to synced one
+        assert_eq!(forked.requests(), 0);
+        assert_eq!(synced.requests(), 1);
+        assert_eq!(ahead.requests(), 0);
+    }
+}
@@ -0,0 +1,311 @@
+use std::{error::Error, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use blockhead::sim::SimApi;
+
+pub const DEFAULT_SIM_CONFIG_PATH: &str = "sim.yaml";
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_HEIGHT: u64 = 1000;
+const DEFAULT_BLOCK_TIME: u64 = 12;
+const DEFAULT_MAX_REORG_DEPTH: u64 = 1;
+const DEFAULT_FINALITY_DEPTH: u64 = 2;
+// emitted blockhead ports
+const BLOCKHEAD_SERVER_PORT: u16 = 8080;
+const BLOCKHEAD_METRICS_PORT: u16 = 8081;
+
+/**
+ * SimConfig describe simulated networks, endpoints are served on consecutive ports from `port`
+ * With port 0 every endpoint get a random port
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SimConfig {
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default)]
+    pub port: u16,
+    pub networks: Vec<SimNetwork>,
+}
+fn default_host() -> String {
+    DEFAULT_HOST.to_string()
+}
+
+/**
+ * SimNetwork is a simulated chain, a reorg of 1 to max_reorg_depth blocks happen before a new block with fork_probability
+ * block_time 0 freeze the chain at its initial height
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SimNetwork {
+    pub protocol: String,
+    pub network: String,
+    // seed default to network position in configuration
+    pub seed: Option<u64>,
+    #[serde(default = "default_height")]
+    pub height: u64,
+    #[serde(default = "default_block_time")]
+    pub block_time: u64,
+    #[serde(default)]
+    pub fork_probability: f64,
+    #[serde(default = "default_max_reorg_depth")]
+    pub max_reorg_depth: u64,
+    #[serde(default = "default_finality_depth")]
+    pub finality_depth: u64,
+    // chain_id override the chain identity served, ethereum like chain ids are hex (0x89)
+    pub chain_id: Option<String>,
+    pub endpoints: Vec<SimEndpoint>,
+}
+fn default_height() -> u64 {
+    DEFAULT_HEIGHT
+}
+fn default_block_time() -> u64 {
+    DEFAULT_BLOCK_TIME
+}
+fn default_max_reorg_depth() -> u64 {
+    DEFAULT_MAX_REORG_DEPTH
+}
+fn default_finality_depth() -> u64 {
+    DEFAULT_FINALITY_DEPTH
+}
+
+/**
+ * SimEndpoint declare `count` endpoints serving the same api with the same behavior
+ * api default to the protocol rpc node, explorers can only be declared once per network
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SimEndpoint {
+    pub api: Option<SimApi>,
+    #[serde(default = "default_count")]
+    pub count: u32,
+    #[serde(default)]
+    pub lag: u64,
+    #[serde(default)]
+    pub latency_ms: u64,
+    #[serde(default)]
+    pub error_rate: f64,
+    #[serde(default)]
+    pub rate_limit_rate: f64,
+    // retry_after is sent with 429 responses, in seconds
+    pub retry_after: Option<u64>,
+}
+fn default_count() -> u32 {
+    1
+}
+
+impl SimConfig {
+    pub fn from_file(path: &Path) -> Result<SimConfig, Box<dyn Error + Send + Sync>> {
+        let config: SimConfig = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+        config.validate()?;
+        Ok(config)
+    }
+    fn validate(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        for network in &self.networks {
+            let name = format!("{} {}", network.protocol, network.network);
+            if !(0.0..=1.0).contains(&network.fork_probability) {
+                return Err(format!("{}: fork_probability must be in [0, 1]", name).into());
+            }
+            let mut explorers = Vec::new();
+            for endpoint in &network.endpoints {
+                let api = network.endpoint_api(endpoint)?;
+                if provider_key(api) != "rpc" {
+                    if endpoint.count > 1 || explorers.contains(&api) {
+                        return Err(format!(
+                            "{}: {:?} can only be declared once per network",
+                            name, api
+                        )
+                        .into());
+                    }
+                    explorers.push(api);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SimNetwork {
+    // rpc_api return the node api blockhead use for protocol rpc endpoints
+    pub fn rpc_api(&self) -> Option<SimApi> {
+        match self.protocol.as_str() {
+            "bitcoin" => Some(SimApi::BitcoinNode),
+            "ethereum" | "ewf" | "polygon" | "moonbeam" | "avalanche" | "evm" => {
+                Some(SimApi::EthereumNode)
+            }
+            "tezos" => Some(SimApi::TezosNode),
+            "polkadot" => Some(SimApi::PolkadotNode),
+            "starknet" => Some(SimApi::StarknetNode),
+            "solana" => Some(SimApi::SolanaNode),
+            "cosmos" => Some(SimApi::CosmosNode),
+            _ => None,
+        }
+    }
+    pub fn endpoint_api(
+        &self,
+        endpoint: &SimEndpoint,
+    ) -> Result<SimApi, Box<dyn Error + Send + Sync>> {
+        match endpoint.api.or_else(|| self.rpc_api()) {
+            Some(api) => Ok(api),
+            None => Err(format!("protocol {} is not simulated", self.protocol).into()),
+        }
+    }
+}
+
+// provider_key return the blockhead provider declaring api, rpc nodes are declared in `rpc` list
+fn provider_key(api: SimApi) -> &'static str {
+    match api {
+        SimApi::Blockstream => "blockstream",
+        SimApi::Blockcypher => "blockcypher",
+        SimApi::Tzkt => "tzkt",
+        SimApi::TzStats => "tzstats",
+        SimApi::Subscan => "subscan",
+        SimApi::CosmosLcd => "cosmos_lcd",
+        _ => "rpc",
+    }
+}
+
+/**
+ * ServedNetwork is a running simulated network, chain_id is the identity served by its endpoints
+ */
+pub struct ServedNetwork {
+    pub protocol: String,
+    pub network: String,
+    pub chain_id: String,
+    pub block_time: u64,
+    pub endpoints: Vec<(SimApi, String)>,
+}
+
+// blockhead_config return a blockhead configuration collecting every served network
+pub fn blockhead_config(networks: &[ServedNetwork]) -> Value {
+    let mut protocols = Map::new();
+    for served in networks {
+        let mut network = Map::new();
+        // ethereum like identities are compared as decimal
+        let chain_id = match served.chain_id.strip_prefix("0x") {
+            Some(hex)
+                if served
+                    .endpoints
+                    .iter()
+                    .any(|(api, _)| *api == SimApi::EthereumNode) =>
+            {
+                u64::from_str_radix(hex, 16)
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|_| served.chain_id.clone())
+            }
+            _ => served.chain_id.clone(),
+        };
+        if served.protocol == "evm" {
+            network.insert("chain_id".to_string(), json!(chain_id.parse::<u64>().ok()));
+        }
+        network.insert(
+            "network_options".to_string(),
+            json!({ "chain_id": chain_id }),
+        );
+        for (api, url) in &served.endpoints {
+            let endpoint = json!({
+                "url": url,
+                "options": { "rate": served.block_time.max(1) },
+            });
+            match provider_key(*api) {
+                "rpc" => network
+                    .entry("rpc")
+                    .or_insert_with(|| json!([]))
+                    .as_array_mut()
+                    .expect("rpc is a list")
+                    .push(endpoint),
+                key => {
+                    network.insert(key.to_string(), endpoint);
+                }
+            }
+        }
+        protocols
+            .entry(served.protocol.clone())
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .expect("protocol is a map")
+            .insert(served.network.clone(), Value::Object(network));
+    }
+    json!({
+        "global": {
+            "networks_options": { "head_length": 5 },
+            "options": { "retry": 3, "delay": 1, "rate": 4 },
+            "server": { "port": BLOCKHEAD_SERVER_PORT },
+            "metrics": { "port": BLOCKHEAD_METRICS_PORT },
+        },
+        "database": { "keep_history": 100 },
+        "protocols": protocols,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sim_config_emit_blockhead_config() {
+        let config: SimConfig = serde_yaml::from_str(
+            r#"
+port: 9000
+networks:
+  - protocol: ethereum
+    network: mainnet
+    fork_probability: 0.1
+    endpoints:
+      - count: 2
+        lag: 1
+  - protocol: tezos
+    network: mainnet
+    endpoints:
+      - {}
+      - api: tzkt
+      - api: tzstats
+        error_rate: 0.1
+"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.host, DEFAULT_HOST);
+        assert_eq!(config.networks[0].block_time, DEFAULT_BLOCK_TIME);
+        assert_eq!(config.networks[0].endpoints[0].count, 2);
+        assert_eq!(
+            config.networks[1]
+                .endpoint_api(&config.networks[1].endpoints[0])
+                .unwrap(),
+            SimApi::TezosNode
+        );
+
+        let mut duplicated = config.clone();
+        duplicated.networks[1].endpoints[1].count = 2;
+        assert!(duplicated.validate().is_err());
+
+        let emitted = blockhead_config(&[
+            ServedNetwork {
+                protocol: "ethereum".to_string(),
+                network: "mainnet".to_string(),
+                chain_id: "0x1".to_string(),
+                block_time: 12,
+                endpoints: vec![
+                    (SimApi::EthereumNode, "http://127.0.0.1:9000".to_string()),
+                    (SimApi::EthereumNode, "http://127.0.0.1:9001".to_string()),
+                ],
+            },
+            ServedNetwork {
+                protocol: "tezos".to_string(),
+                network: "mainnet".to_string(),
+                chain_id: "NetXdQprcVkpaWU".to_string(),
+                block_time: 12,
+                endpoints: vec![
+                    (SimApi::TezosNode, "http://127.0.0.1:9002".to_string()),
+                    (SimApi::TzStats, "http://127.0.0.1:9003".to_string()),
+                ],
+            },
+        ]);
+        let eth = &emitted["protocols"]["ethereum"]["mainnet"];
+        assert_eq!(eth["network_options"]["chain_id"], "1");
+        assert_eq!(eth["rpc"].as_array().unwrap().len(), 2);
+        assert_eq!(eth["rpc"][1]["url"], "http://127.0.0.1:9001");
+        let tezos = &emitted["protocols"]["tezos"]["mainnet"];
+        assert_eq!(tezos["network_options"]["chain_id"], "NetXdQprcVkpaWU");
+        assert_eq!(tezos["tzstats"]["url"], "http://127.0.0.1:9003");
+        assert_eq!(emitted["global"]["server"]["port"], BLOCKHEAD_SERVER_PORT);
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    error::Error,
+    fs,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+use env_logger::Env;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use config::{blockhead_config, ServedNetwork, SimConfig, SimNetwork, DEFAULT_SIM_CONFIG_PATH};
+
+mod config;
+
+use blockhead::sim::{MockServer, SharedChain, SimChain};
+
+#[macro_use]
+extern crate log;
+
+#[derive(Parser)]
+#[command(
+    name = "blockhead-sim",
+    about = "Serve simulated blockchain networks for blockhead demos and load tests"
+)]
+struct Args {
+    #[arg(short, long, default_value = "info")]
+    log_level: String,
+    #[arg(short, long, default_value = DEFAULT_SIM_CONFIG_PATH)]
+    config: PathBuf,
+    // emit_config write a blockhead configuration using simulated endpoints, `-` write it on stdout
+    #[arg(short, long)]
+    emit_config: Option<PathBuf>,
+}
+
+#[actix_web::main]
+async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let args = Args::parse();
+    env_logger::init_from_env(
+        Env::default().default_filter_or(format!("blockhead_sim={}", args.log_level)),
+    );
+    let config = SimConfig::from_file(&args.config)?;
+    let host: IpAddr = config.host.parse()?;
+
+    let mut servers = Vec::new();
+    let mut served = Vec::new();
+    for (i, network) in config.networks.iter().enumerate() {
+        let chain = new_chain(network, i as u64 + 1);
+        let mut endpoints = Vec::new();
+        for endpoint in &network.endpoints {
+            let api = network.endpoint_api(endpoint)?;
+            for _ in 0..endpoint.count {
+                let port = match config.port {
+                    0 => 0,
+                    port => port
+                        .checked_add(servers.len() as u16)
+                        .ok_or("not enough ports to serve every endpoint")?,
+                };
+                let server = MockServer::start_on(api, chain.clone(), SocketAddr::new(host, port))?;
+                {
+                    let mut behavior = server.behavior();
+                    behavior.lag = endpoint.lag;
+                    behavior.latency = Duration::from_millis(endpoint.latency_ms);
+                    behavior.error_rate = endpoint.error_rate;
+                    behavior.rate_limit_rate = endpoint.rate_limit_rate;
+                    behavior.retry_after = endpoint.retry_after;
+                    behavior.chain_id = network.chain_id.clone();
+                }
+                endpoints.push((api, server.url()));
+                servers.push(server);
+            }
+        }
+        let chain_id = network.chain_id.clone().unwrap_or_else(|| {
+            let api = network.rpc_api().or(endpoints.first().map(|(api, _)| *api));
+            api.map(|api| api.chain_id(&chain.lock().unwrap()))
+                .unwrap_or_default()
+        });
+        info!(
+            "{} {}: {} endpoints, chain id {}, block time {}s",
+            network.protocol,
+            network.network,
+            endpoints.len(),
+            chain_id,
+            network.block_time
+        );
+        for (api, url) in &endpoints {
+            debug!(
+                "{} {} {:?} served on {}",
+                network.protocol, network.network, api, url
+            );
+        }
+        if network.block_time > 0 {
+            tokio::spawn(produce_blocks(network.clone(), chain, i as u64 + 1));
+        }
+        served.push(ServedNetwork {
+            protocol: network.protocol.clone(),
+            network: network.network.clone(),
+            chain_id,
+            block_time: network.block_time,
+            endpoints,
+        });
+    }
+
+    if let Some(path) = args.emit_config {
+        let emitted = serde_yaml::to_string(&blockhead_config(&served))?;
+        if path.as_os_str() == "-" {
+            print!("{}", emitted);
+        } else {
+            fs::write(&path, emitted)?;
+            info!("Blockhead configuration written to {}", path.display());
+        }
+    }
+    info!(
+        "Serving {} simulated endpoints, press ctrl-c to stop",
+        servers.len()
+    );
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+// new_chain build network chain with head produced now, seed default to network position
+fn new_chain(network: &SimNetwork, position: u64) -> SharedChain {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is after unix epoch")
+        .as_secs();
+    let genesis_time = now.saturating_sub(network.height * network.block_time);
+    let chain = SimChain::new(network.seed.unwrap_or(position), network.height)
+        .with_block_time(network.block_time)
+        .with_finality_depth(network.finality_depth)
+        .with_genesis_time(genesis_time);
+    Arc::new(Mutex::new(chain))
+}
+
+// produce_blocks append a block every block time, a reorg can happen first with network fork probability
+async fn produce_blocks(network: SimNetwork, chain: SharedChain, position: u64) {
+    let mut rng = StdRng::seed_from_u64(network.seed.unwrap_or(position));
+    let mut interval = tokio::time::interval(Duration::from_secs(network.block_time));
+    // first tick is immediate
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let mut chain = chain.lock().unwrap();
+        if rng.gen_bool(network.fork_probability) {
+            let depth = rng.gen_range(1..=network.max_reorg_depth.max(1));
+            info!(
+                "{} {}: reorg of {} blocks at height {}",
+                network.protocol,
+                network.network,
+                depth,
+                chain.height()
+            );
+            chain.reorg(depth);
+        }
+        chain.produce(1);
+        debug!(
+            "{} {}: new block {} {}",
+            network.protocol,
+            network.network,
+            chain.height(),
+            chain.head().hex()
+        );
+    }
+}
@@ -1,12 +1,31 @@
-use std::time::{Duration, SystemTime};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime},
+};
 
 use crate::{
-    commons::blockchain,
-    conf::{Network, NetworkAppOptions, Protocol, Provider},
-    db::DATABASE,
-    endpoints::ProviderActions,
+    alerts::{self, AlertEngine},
+    commons::{
+        blockchain,
+        consensus::{self, Consensus, ProviderChain},
+        events::{self, EventKind},
+        health::ChainHealth,
+        status::{self, ProviderHead, ProviderStatus},
+    },
+    conf::{identity, Network, NetworkAppOptions, Protocol, Provider},
+    db::{Redb, DATABASE},
+    endpoints::{ProviderActions, CHAIN_MISMATCH, NO_NEW_BLOCK},
     prom,
+    requests::{
+        circuit_breaker::circuit_state,
+        client::ReqwestClient,
+        subscription::{HeadEvent, Subscriptions},
+    },
 };
+use tokio::sync::mpsc;
+
+// CHAIN_IDENTITY_INTERVAL is the delay between two chain identity checks of a verified endpoint
+const CHAIN_IDENTITY_INTERVAL: Duration = Duration::from_secs(600);
 
 pub async fn runner(
     protocol: Protocol,
@@ -21,57 +40,351 @@ pub async fn runner(
         &providers.len()
     );
     let mut providers = providers;
-    let mut head_hash: Option<String> = None;
+    // providers status, updated and shared at each tick
+    let mut statuses: Vec<ProviderStatus> = providers
+        .iter()
+        .filter_map(|p| {
+            let endpoint = p.get_endpoint()?;
+            Some(ProviderStatus::new(
+                endpoint.name(),
+                p.kind(),
+                p.is_json_rpc(),
+                endpoint.reqwest.config.clone(),
+            ))
+        })
+        .collect();
+    // last blockchain returned by each provider, used to vote for canonical blockchain
+    let mut chains: HashMap<String, ProviderChain> = HashMap::new();
+    // last time each provider head changed
+    let mut head_changed_at: HashMap<String, u64> = HashMap::new();
+    let mut canonical: Option<Consensus> = None;
+    // node providers with a subscription url push their new heads, polling at tick rate stay as fallback
+    let (head_tx, mut head_rx) = mpsc::channel::<HeadEvent>(64);
+    let mut subscriptions = Subscriptions::default();
+    for provider in providers.iter() {
+        let endpoint = match provider.get_endpoint() {
+            Some(endpoint) if endpoint.reqwest.config.subscription_url.is_some() => endpoint,
+            _ => continue,
+        };
+        for subscription in provider.subscriptions() {
+            subscriptions.spawn(
+                subscription,
+                endpoint.name(),
+                endpoint.reqwest.config.clone(),
+                protocol,
+                network.clone(),
+                head_tx.clone(),
+            );
+        }
+    }
+    // endpoints serving another chain than expected_identity are failed and excluded from consensus
+    let expected_identity = identity::expected_chain_identity(&protocol, &network, &net_opts);
+    let mut identity_verified_at: HashMap<String, Instant> = HashMap::new();
+    // firing alerts are resumed from database, they are not notified again
+    let firing = DATABASE
+        .get()
+        .unwrap()
+        .get_alerts(&protocol, &network)
+        .unwrap_or_else(|e| {
+            error!("Error loading alerts {} {}: {}", protocol, network, e);
+            Vec::new()
+        });
+    let mut alert_engine = AlertEngine::new(
+        protocol,
+        network.clone(),
+        net_opts.alerts.clone(),
+        alerts::repeat_interval(),
+        net_opts.tick_rate as u64,
+        firing,
+        now(),
+    );
+    let endpoints: HashMap<String, (String, String)> = providers
+        .iter()
+        .filter_map(|p| p.get_endpoint())
+        .map(|e| (e.name(), (e.url.clone(), e.reqwest.config.alias.clone())))
+        .collect();
+    // first time a block height was pushed, and providers which already pushed it
+    let mut first_seen: HashMap<u64, (f64, Vec<String>)> = HashMap::new();
     let mut interval = tokio::time::interval(Duration::from_secs(net_opts.tick_rate as u64));
     loop {
         // get all providers that implement ProviderActions
-        let mut providers_d: Vec<Box<&mut dyn ProviderActions>> = Vec::new();
+        let mut providers_d: Vec<(String, u32, bool, &mut dyn ProviderActions)> = Vec::new();
+        // endpoint clients share response times of provider requests
+        let mut clients: HashMap<String, ReqwestClient> = HashMap::new();
         for provider in providers.iter_mut() {
-            let r = provider.as_mut_provider_actions();
-            if r.is_some() {
-                providers_d.push(Box::new(r.unwrap()));
+            let (name, weight, available) = match provider.get_endpoint() {
+                Some(endpoint) => {
+                    clients.insert(endpoint.name(), endpoint.reqwest.clone());
+                    (
+                        endpoint.name(),
+                        endpoint.reqwest.config.trust,
+                        endpoint.reqwest.available(),
+                    )
+                }
+                None => continue,
+            };
+            if let Some(p) = provider.as_mut_provider_actions() {
+                providers_d.push((name, weight, available, p));
             }
         }
         // batch all tasks
-        let tasks = providers_d
-            .iter_mut()
-            .map(|p| p.parse_top_blocks(net_opts.head_length, head_hash.clone()));
+        let tasks = providers_d.iter_mut().map(|(name, weight, available, p)| {
+            let name = name.clone();
+            let weight = *weight;
+            let available = *available;
+            let previous_head = chains
+                .get(&name)
+                .and_then(|c| c.blockchain.blocks.first())
+                .map(|b| b.hash.clone());
+            let head_length = net_opts.head_length;
+            // chain identity is verified at startup, then every CHAIN_IDENTITY_INTERVAL
+            // checks are skipped with scrapping task while endpoint is not available
+            let expected = expected_identity.clone().filter(|_| {
+                available
+                    && identity_verified_at
+                        .get(&name)
+                        .map_or(true, |t| t.elapsed() >= CHAIN_IDENTITY_INTERVAL)
+            });
+            let previous_syncing = statuses
+                .iter()
+                .find(|s| s.name == name)
+                .and_then(|s| s.syncing);
+            let client = clients[&name].clone();
+            async move {
+                let (r, verified) =
+                    scrape_blocks(&mut **p, &client, expected, head_length, previous_head).await;
+                // sync status is unknown when request fail, provider is not excluded
+                let syncing = match &verified {
+                    _ if !available => previous_syncing,
+                    Some(Err(_)) => None,
+                    _ => p.sync_status().await.unwrap_or_else(|e| {
+                        debug!("{} sync status request failed: {}", name, e);
+                        None
+                    }),
+                };
+                (name, weight, r, verified, syncing)
+            }
+        });
         let results = futures::future::join_all(tasks).await;
-        // filter out errors
-        let results = results
-            .into_iter()
-            .filter_map(|r| match r {
-                Ok(b) => Some(b),
+        // update providers last blockchain, providers without new block keep their previous one
+        let mut updated = false;
+        let mut heads: Vec<(String, ProviderHead)> = Vec::new();
+        let observed_at = now_ms();
+        for (name, weight, r, verified, syncing) in results {
+            // rate limiter and retry waits are not part of response times
+            let latencies = clients
+                .get(&name)
+                .map(|c| c.take_latencies())
+                .unwrap_or_default();
+            if let Some(verified) = verified {
+                match &verified {
+                    Ok(_) => identity_verified_at.insert(name.clone(), Instant::now()),
+                    Err(_) => identity_verified_at.remove(&name),
+                };
+                // identity requests failure leave mismatch metric unchanged
+                let mismatch = match &verified {
+                    Ok(_) => Some(false),
+                    Err(e) if e.starts_with(CHAIN_MISMATCH) => Some(true),
+                    Err(_) => None,
+                };
+                if let (Some(mismatch), Some((url, alias))) = (mismatch, endpoints.get(&name)) {
+                    prom::registry::set_endpoint_chain_mismatch(
+                        url, alias, &protocol, &network, mismatch,
+                    );
+                }
+                if mismatch.is_some() {
+                    alert_engine.observe_chain_identity(&name, verified.clone().err());
+                }
+            }
+            if let Some(status) = statuses.iter_mut().find(|s| s.name == name) {
+                let error = match &r {
+                    Err(e) if e.to_string() != NO_NEW_BLOCK => Some(e.to_string()),
+                    _ => None,
+                };
+                status.record(&latencies, error);
+                if syncing == Some(true) && status.syncing != Some(true) {
+                    warn!(
+                        "{} {} provider {} is syncing, excluded from consensus",
+                        protocol, network, name
+                    );
+                }
+                status.syncing = syncing;
+                status.circuit = circuit_state(status.options.url.as_deref().unwrap_or_default());
+            }
+            if let (Some(syncing), Some((url, alias))) = (syncing, endpoints.get(&name)) {
+                prom::registry::set_endpoint_syncing(url, alias, &protocol, &network, syncing);
+            }
+            let mut head = ProviderHead {
+                height: 0,
+                hash: String::new(),
+                observed_at,
+                latency: (!latencies.is_empty())
+                    .then(|| latencies.iter().sum::<u64>() / latencies.len() as u64),
+                error: None,
+            };
+            match r {
+                Ok(blockchain) => {
+                    updated = true;
+                    if let Some(block) = blockchain.blocks.first() {
+                        head.height = block.height;
+                        head.hash = block.hash.clone();
+                    }
+                    let previous_head = chains
+                        .get(&name)
+                        .and_then(|c| c.blockchain.blocks.first())
+                        .map(|b| b.hash.clone());
+                    if previous_head.as_ref() != blockchain.blocks.first().map(|b| &b.hash) {
+                        head_changed_at.insert(name.clone(), now());
+                    }
+                    chains.insert(
+                        name.clone(),
+                        ProviderChain {
+                            provider: name.clone(),
+                            weight,
+                            blockchain,
+                        },
+                    );
+                    heads.push((name, head));
+                }
+                Err(e) if e.to_string() == NO_NEW_BLOCK => {
+                    if let Some(block) = chains.get(&name).and_then(|c| c.blockchain.blocks.first())
+                    {
+                        head.height = block.height;
+                        head.hash = block.hash.clone();
+                    }
+                    heads.push((name, head));
+                }
                 Err(e) => {
                     debug!("Scrapping err  : {:?}", e);
-                    None
+                    chains.remove(&name);
+                    head.error = Some(e.to_string());
+                    heads.push((name, head));
                 }
-            })
-            .collect::<Vec<_>>();
-        if results.is_empty() {
+            }
+        }
+        let db = DATABASE.get().unwrap();
+        if let Err(e) = db.add_provider_heads(&protocol, &network, &heads) {
+            error!(
+                "Error saving providers heads {} {}: {}",
+                protocol, network, e
+            );
+        }
+        if !updated {
             debug!(
                 "{:?} collector: no results for network: {:?}",
                 &protocol.to_string(),
                 &network.to_string()
             );
-            interval.tick().await;
+            track_providers(
+                &protocol,
+                &network,
+                &mut statuses,
+                &chains,
+                &head_changed_at,
+                canonical.as_ref(),
+            );
+            evaluate_alerts(&mut alert_engine, &statuses, canonical.as_ref());
+            track_health(&protocol, &network, &net_opts, canonical.as_ref());
+            wait_next_tick(
+                &mut interval,
+                &mut head_rx,
+                &mut first_seen,
+                &protocol,
+                &network,
+                &statuses,
+                canonical.as_ref(),
+            )
+            .await;
             continue;
         }
-        let mut best_chain = blockchain::get_highest_blockchain(results).unwrap();
+        // syncing providers are tracked but don't vote for canonical blockchain
+        let provider_chains = chains
+            .values()
+            .filter(|c| {
+                !statuses
+                    .iter()
+                    .any(|s| s.name == c.provider && s.syncing == Some(true))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let consensus = match consensus::select_canonical(&provider_chains, &net_opts.consensus) {
+            Some(consensus) => consensus,
+            None => {
+                warn!(
+                    "{} {} collector: no consensus reached with policy {:?} between {} providers",
+                    protocol,
+                    network,
+                    net_opts.consensus,
+                    provider_chains.len()
+                );
+                track_providers(
+                    &protocol,
+                    &network,
+                    &mut statuses,
+                    &chains,
+                    &head_changed_at,
+                    canonical.as_ref(),
+                );
+                evaluate_alerts(&mut alert_engine, &statuses, canonical.as_ref());
+                track_health(&protocol, &network, &net_opts, canonical.as_ref());
+                wait_next_tick(
+                    &mut interval,
+                    &mut head_rx,
+                    &mut first_seen,
+                    &protocol,
+                    &network,
+                    &statuses,
+                    canonical.as_ref(),
+                )
+                .await;
+                continue;
+            }
+        };
+        if !consensus.dissenters.is_empty() {
+            warn!(
+                "{} {} collector: providers {:?} disagree with canonical blockchain from {}",
+                protocol, network, consensus.dissenters, consensus.provider
+            );
+        }
+        track_providers(
+            &protocol,
+            &network,
+            &mut statuses,
+            &chains,
+            &head_changed_at,
+            Some(&consensus),
+        );
+        let provider_name = consensus.provider.clone();
+        let mut best_chain = consensus.blockchain.clone();
+        canonical = Some(consensus);
         best_chain.sort();
         debug!("best_chain: {:?}", &best_chain);
         prom::registry::set_blockchain_metrics(
             &protocol,
             &network,
             best_chain.height as i64,
-            best_chain.blocks.last().unwrap().time as i64,
-            best_chain.blocks.last().unwrap().txs as i64,
+            best_chain.blocks.first().unwrap().time as i64,
+            best_chain.blocks.first().unwrap().txs as i64,
         );
-        best_chain.last_scrapping_task = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let db = DATABASE.get().unwrap();
+        if let (Some(finalized), Some(lag)) = (&best_chain.finalized, best_chain.finality_lag()) {
+            prom::registry::set_blockchain_finality_metrics(
+                &protocol,
+                &network,
+                finalized.height as i64,
+                lag as i64,
+            );
+        }
+        best_chain.last_scrapping_task = now();
+        let stored_height = db
+            .get_metadata(&protocol, &network)
+            .ok()
+            .flatten()
+            .map(|m| m.height)
+            .unwrap_or(0);
+        let reorg = track_reorg(db, &protocol, &network, &best_chain, &provider_name);
+        if let Some(reorg) = &reorg {
+            alert_engine.observe_reorg(reorg);
+        }
         let r = db.set_blockchain(&best_chain, &protocol, &network);
         match r {
             Ok(_) => {
@@ -81,6 +394,26 @@ pub async fn runner(
                     &network.to_string(),
                     &best_chain.height
                 );
+                // publish blocks above stored head, and blocks replaced by reorg
+                let mut new_blocks = best_chain
+                    .blocks
+                    .iter()
+                    .filter(|b| {
+                        b.height > stored_height
+                            || reorg.as_ref().is_some_and(|r| b.height >= r.height)
+                    })
+                    .collect::<Vec<_>>();
+                new_blocks.sort_by_key(|b| b.height);
+                track_block_intervals(&protocol, &network, &best_chain, stored_height);
+                for block in new_blocks {
+                    events::publish(
+                        &protocol,
+                        &network,
+                        EventKind::NewBlock {
+                            block: block.clone(),
+                        },
+                    );
+                }
             }
             Err(e) => {
                 error!(
@@ -91,8 +424,465 @@ pub async fn runner(
                 );
             }
         }
-        let x = best_chain.clone().blocks.first().unwrap().hash.clone();
-        head_hash = Some(x);
-        interval.tick().await;
+        evaluate_alerts(&mut alert_engine, &statuses, canonical.as_ref());
+        track_health(&protocol, &network, &net_opts, canonical.as_ref());
+        wait_next_tick(
+            &mut interval,
+            &mut head_rx,
+            &mut first_seen,
+            &protocol,
+            &network,
+            &statuses,
+            canonical.as_ref(),
+        )
+        .await;
+    }
+}
+
+// track_reorg compare stored blockchain with the best chain, and record reorg if a stored block is replaced
+fn track_reorg(
+    db: &Redb,
+    protocol: &Protocol,
+    network: &Network,
+    best_chain: &blockchain::Blockchain,
+    provider: &str,
+) -> Option<blockchain::Reorg> {
+    // only stored blocks in best chain range can be replaced
+    let lowest = best_chain.blocks.iter().map(|b| b.height).min()?;
+    let blocks = db
+        .get_blocks(protocol, network, lowest, u64::MAX, usize::MAX, true)
+        .ok()?;
+    let mut stored = blockchain::Blockchain::new(Some(blocks));
+    stored.sort();
+    // best chain will not replace stored blocks if it is behind
+    if stored.height > best_chain.height {
+        return None;
+    }
+    let reorg = blockchain::detect_reorg(&stored, best_chain, provider)?;
+    warn!(
+        "Reorg detected on {} {} by {}: depth {} at height {} ({} replaced by {})",
+        protocol, network, provider, reorg.depth, reorg.height, reorg.old_hash, reorg.new_hash
+    );
+    prom::registry::track_reorg(protocol, network, provider, reorg.depth);
+    if let Err(e) = db.add_reorg(&reorg, protocol, network) {
+        error!("Error saving reorg {} {}: {}", protocol, network, e);
+    }
+    events::publish(
+        protocol,
+        network,
+        EventKind::Reorg {
+            reorg: reorg.clone(),
+        },
+    );
+    Some(reorg)
+}
+
+// track_providers update providers status, and set per provider lag, head age and divergence metrics against canonical blockchain
+fn track_providers(
+    protocol: &Protocol,
+    network: &Network,
+    statuses: &mut [ProviderStatus],
+    chains: &HashMap<String, ProviderChain>,
+    head_changed_at: &HashMap<String, u64>,
+    canonical: Option<&Consensus>,
+) {
+    let now = now();
+    for status in statuses.iter_mut() {
+        // providers in error are removed from chains
+        let chain = chains.get(&status.name);
+        if status.up != chain.is_some() {
+            events::publish(
+                protocol,
+                network,
+                EventKind::Provider {
+                    name: status.name.clone(),
+                    up: chain.is_some(),
+                },
+            );
+        }
+        status.up = chain.is_some();
+        let chain = match chain {
+            Some(chain) => chain,
+            None => {
+                // stale values of a provider in error would be exported as current ones
+                prom::registry::remove_endpoint_consensus_metrics(
+                    status.options.url.as_deref().unwrap_or_default(),
+                    &status.options.alias,
+                    protocol,
+                    network,
+                );
+                continue;
+            }
+        };
+        status.height = chain.blockchain.height;
+        let canonical = match canonical {
+            Some(canonical) => canonical,
+            None => continue,
+        };
+        status.lag = canonical.blockchain.height as i64 - chain.blockchain.height as i64;
+        let head_age = now - head_changed_at.get(&status.name).copied().unwrap_or(now);
+        status.divergent = canonical.dissenters.contains(&status.name);
+        prom::registry::set_endpoint_consensus_metrics(
+            status.options.url.as_deref().unwrap_or_default(),
+            &status.options.alias,
+            protocol,
+            network,
+            status.lag,
+            head_age,
+            status.divergent,
+        );
+    }
+    status::set_providers_status(protocol, network, statuses.to_vec());
+}
+
+// track_block_intervals observe time between blocks above stored head and their parent
+// blocks without time or older than their parent (non monotonic block time) are skipped
+fn track_block_intervals(
+    protocol: &Protocol,
+    network: &Network,
+    best_chain: &blockchain::Blockchain,
+    stored_height: u64,
+) {
+    for block in best_chain
+        .blocks
+        .iter()
+        .filter(|b| b.height > stored_height)
+    {
+        let parent = match best_chain
+            .blocks
+            .iter()
+            .find(|p| p.height + 1 == block.height)
+        {
+            Some(parent) => parent,
+            None => continue,
+        };
+        if parent.time == 0 || block.time < parent.time {
+            continue;
+        }
+        prom::registry::track_block_interval(protocol, network, (block.time - parent.time) as f64);
+    }
+}
+
+// track_health set canonical head age and stalled state, head age keep growing while no new block is collected
+// metrics are removed while canonical head has no time
+fn track_health(
+    protocol: &Protocol,
+    network: &Network,
+    net_opts: &NetworkAppOptions,
+    canonical: Option<&Consensus>,
+) {
+    let head = match canonical.and_then(|c| c.blockchain.blocks.iter().max_by_key(|b| b.height)) {
+        Some(head) => head,
+        None => return,
+    };
+    let health = ChainHealth::new(
+        head.height,
+        head.time,
+        net_opts.expected_block_time(protocol),
+        net_opts.stall_factor,
+        now(),
+    );
+    match (health.head_age, health.stalled) {
+        (Some(head_age), Some(stalled)) => prom::registry::set_blockchain_health_metrics(
+            protocol,
+            network,
+            head_age as i64,
+            stalled,
+        ),
+        _ => prom::registry::remove_blockchain_health_metrics(protocol, network),
+    }
+}
+
+// evaluate_alerts evaluate network alert rules against providers status and canonical head, changed alerts are notified
+fn evaluate_alerts(
+    engine: &mut AlertEngine,
+    statuses: &[ProviderStatus],
+    canonical: Option<&Consensus>,
+) {
+    let head = canonical.map(|c| c.blockchain.height);
+    alerts::notify(engine.evaluate(statuses, head, now()));
+}
+
+// wait_next_tick wait for next polling tick, or for a pushed head unknown by canonical blockchain
+// a pushed finalized head is unknown when it is above canonical finalized head
+#[allow(clippy::too_many_arguments)]
+async fn wait_next_tick(
+    interval: &mut tokio::time::Interval,
+    head_rx: &mut mpsc::Receiver<HeadEvent>,
+    first_seen: &mut HashMap<u64, (f64, Vec<String>)>,
+    protocol: &Protocol,
+    network: &Network,
+    statuses: &[ProviderStatus],
+    canonical: Option<&Consensus>,
+) {
+    loop {
+        tokio::select! {
+            _ = interval.tick() => return,
+            Some(event) = head_rx.recv() => {
+                if event.finalized {
+                    let known = canonical
+                        .and_then(|c| c.blockchain.finalized.as_ref())
+                        .is_some_and(|f| f.height >= event.height);
+                    if !known {
+                        debug!(
+                            "{} {} finalized head {} pushed by {}, collect now",
+                            protocol, network, event.height, event.provider
+                        );
+                        interval.reset();
+                        return;
+                    }
+                    continue;
+                }
+                track_propagation(protocol, network, statuses, first_seen, &event);
+                let known = canonical
+                    .map(|c| {
+                        c.blockchain.height > event.height
+                            || c.blockchain.blocks.iter().any(|b| {
+                                b.height == event.height
+                                    && event.hash.as_ref().map_or(true, |h| *h == b.hash)
+                            })
+                    })
+                    .unwrap_or(false);
+                if !known {
+                    debug!(
+                        "{} {} new head {} pushed by {}, collect now",
+                        protocol, network, event.height, event.provider
+                    );
+                    interval.reset();
+                    return;
+                }
+            }
+        }
+    }
+}
+
+// track_propagation observe delay between first provider pushing a block height and each other provider pushing it
+fn track_propagation(
+    protocol: &Protocol,
+    network: &Network,
+    statuses: &[ProviderStatus],
+    first_seen: &mut HashMap<u64, (f64, Vec<String>)>,
+    event: &HeadEvent,
+) {
+    let (first, providers) = first_seen
+        .entry(event.height)
+        .or_insert((event.received_at, Vec::new()));
+    if providers.contains(&event.provider) {
+        return;
+    }
+    providers.push(event.provider.clone());
+    let delay = event.received_at - *first;
+    if let Some(status) = statuses.iter().find(|s| s.name == event.provider) {
+        prom::registry::track_block_propagation(
+            status.options.url.as_deref().unwrap_or_default(),
+            &status.options.alias,
+            protocol,
+            network,
+            delay,
+        );
+    }
+    first_seen.retain(|height, _| height + 100 > event.height);
+}
+
+// scrape_blocks verify endpoint chain identity when expected, then parse its top blocks
+// endpoint serving another chain is not scrapped, identity requests don't consume its scrapping rate
+async fn scrape_blocks(
+    provider: &mut dyn ProviderActions,
+    client: &ReqwestClient,
+    expected: Option<String>,
+    head_length: u32,
+    previous_head: Option<String>,
+) -> (
+    Result<blockchain::Blockchain, Box<dyn std::error::Error + Send + Sync>>,
+    Option<Result<(), String>>,
+) {
+    let verified = match expected {
+        Some(expected) => {
+            let last_request = client.last_request();
+            let verified = verify_chain_identity(provider, &expected).await;
+            client.restore_last_request(last_request);
+            Some(verified)
+        }
+        None => None,
+    };
+    let r = match &verified {
+        Some(Err(e)) => Err(e.clone().into()),
+        _ => provider.parse_top_blocks(head_length, previous_head).await,
+    };
+    (r, verified)
+}
+
+// verify_chain_identity fail with CHAIN_MISMATCH if provider serve another chain than expected
+async fn verify_chain_identity(
+    provider: &mut dyn ProviderActions,
+    expected: &str,
+) -> Result<(), String> {
+    match provider.chain_identity().await {
+        Ok(Some(identity)) if !identity::identity_matches(expected, &identity) => Err(format!(
+            "{}: expected {} got {}",
+            CHAIN_MISMATCH, expected, identity
+        )),
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Chain identity request failed: {}", e)),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commons::blockchain::{Block, Blockchain},
+        conf::{ConsensusPolicy, EndpointOptions, DEFAULT_ALIAS},
+        endpoints::ethereum_node::EthereumNode,
+        prom::{
+            metrics::{
+                BLOCKCHAIN_DIVERGENT_ENDPOINT, BLOCKCHAIN_HEIGHT_ENDPOINT, BLOCKCHAIN_LAG_ENDPOINT,
+            },
+            registry::get_base_url,
+        },
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+    };
+
+    fn provider_chain(provider: &str, blocks: &[(u64, &str)]) -> ProviderChain {
+        let mut blockchain = Blockchain::new(None);
+        for (height, hash) in blocks {
+            blockchain.add_block(Block {
+                hash: hash.to_string(),
+                height: *height,
+                time: 0,
+                txs: 0,
+            });
+        }
+        ProviderChain {
+            provider: provider.to_string(),
+            weight: 1,
+            blockchain,
+        }
+    }
+
+    fn labels(name: &str) -> [&str; 4] {
+        [name, DEFAULT_ALIAS, "ethereum", "track_providers"]
+    }
+
+    #[test]
+    fn collector_track_providers_metrics() {
+        let protocol = Protocol::Ethereum;
+        let network = "track_providers".to_string();
+        let mut statuses = ["honest_1", "honest_2", "liar"]
+            .iter()
+            .map(|name| {
+                let url = format!("http://{}", name);
+                let options = EndpointOptions::test_new(&url, None, None);
+                ProviderStatus::new(name.to_string(), "ethereum_node", true, options)
+            })
+            .collect::<Vec<_>>();
+        let mut chains = [
+            provider_chain("honest_1", &[(10, "a10"), (11, "a11"), (12, "a12")]),
+            provider_chain("honest_2", &[(10, "a10"), (11, "a11")]),
+            provider_chain("liar", &[(10, "a10"), (11, "x11")]),
+        ]
+        .into_iter()
+        .map(|c| (c.provider.clone(), c))
+        .collect::<HashMap<_, _>>();
+        let canonical = consensus::select_canonical(
+            &chains.values().cloned().collect::<Vec<_>>(),
+            &ConsensusPolicy::Highest,
+        )
+        .unwrap();
+        track_providers(
+            &protocol,
+            &network,
+            &mut statuses,
+            &chains,
+            &HashMap::new(),
+            Some(&canonical),
+        );
+        assert_eq!(
+            statuses.iter().map(|s| s.lag).collect::<Vec<_>>(),
+            vec![0, 1, 1]
+        );
+        for (name, lag, divergent) in [("honest_1", 0, 0), ("honest_2", 1, 0), ("liar", 1, 1)] {
+            assert_eq!(
+                BLOCKCHAIN_LAG_ENDPOINT
+                    .with_label_values(&labels(name))
+                    .get(),
+                lag
+            );
+            assert_eq!(
+                BLOCKCHAIN_DIVERGENT_ENDPOINT
+                    .with_label_values(&labels(name))
+                    .get(),
+                divergent
+            );
+        }
+        // provider in error is removed from metrics
+        chains.remove("liar");
+        track_providers(
+            &protocol,
+            &network,
+            &mut statuses,
+            &chains,
+            &HashMap::new(),
+            Some(&canonical),
+        );
+        assert!(!statuses[2].up);
+        assert!(BLOCKCHAIN_LAG_ENDPOINT
+            .remove_label_values(&labels("liar"))
+            .is_err());
+        assert!(BLOCKCHAIN_DIVERGENT_ENDPOINT
+            .remove_label_values(&labels("liar"))
+            .is_err());
+        assert!(BLOCKCHAIN_LAG_ENDPOINT
+            .remove_label_values(&labels("honest_1"))
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn collector_scrape_blocks_after_identity() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::EthereumNode);
+        let network = "scrape_blocks".to_string();
+        let mut node = EthereumNode::test_new(&mock.url(), Protocol::Ethereum, network.clone());
+        node.endpoint.reqwest.config.rate = 60;
+        let client = node.endpoint.reqwest.clone();
+        // identity check does not consume scrapping rate, blocks are parsed on the same tick
+        let (r, verified) = scrape_blocks(&mut node, &client, Some("1".to_string()), 3, None).await;
+        assert_eq!(verified, Some(Ok(())));
+        assert_eq!(r.unwrap().height, MOCK_HEIGHT);
+        assert!(!client.available());
+        // endpoint serving another chain is not scrapped
+        let mock = tests::mock_server(SimApi::EthereumNode);
+        mock.behavior().chain_id = Some("0x5".to_string());
+        let mut node = EthereumNode::test_new(&mock.url(), Protocol::Ethereum, network.clone());
+        let client = node.endpoint.reqwest.clone();
+        let (r, verified) = scrape_blocks(&mut node, &client, Some("1".to_string()), 3, None).await;
+        assert!(verified.unwrap().unwrap_err().starts_with(CHAIN_MISMATCH));
+        assert!(r.is_err());
+        assert_eq!(mock.requests(), 1);
+        let labels = [
+            get_base_url(&mock.url()),
+            DEFAULT_ALIAS.to_string(),
+            "ethereum".to_string(),
+            network,
+        ];
+        let labels = labels.iter().map(|l| l.as_str()).collect::<Vec<_>>();
+        assert!(BLOCKCHAIN_HEIGHT_ENDPOINT
+            .remove_label_values(&labels)
+            .is_err());
     }
 }
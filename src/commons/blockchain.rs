@@ -1,3 +1,5 @@
+use std::time::SystemTime;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -7,11 +9,26 @@ pub struct Block {
     pub time: u64,
     pub txs: u64,
 }
+/**
+ * Head is a reference to a block a chain consider safe or finalized
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Head {
+    pub height: u64,
+    pub hash: String,
+}
+/**
+ * Blockchain blocks are the latest ones, safe and finalized heads are set when the protocol expose them
+ */
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Blockchain {
     pub blocks: Vec<Block>,
     pub height: u64,
     pub last_scrapping_task: u64,
+    #[serde(default)]
+    pub safe: Option<Head>,
+    #[serde(default)]
+    pub finalized: Option<Head>,
 }
 impl Blockchain {
     pub fn new(blocks: Option<Vec<Block>>) -> Blockchain {
@@ -23,6 +40,8 @@ impl Blockchain {
             blocks: b,
             height: 0,
             last_scrapping_task: 0,
+            safe: None,
+            finalized: None,
         }
     }
     pub fn add_block(&mut self, block: Block) {
@@ -41,18 +60,99 @@ impl Blockchain {
             self.height = self.blocks.first().unwrap().height;
         }
     }
+    // finality_lag return number of blocks between latest and finalized heads
+    pub fn finality_lag(&self) -> Option<u64> {
+        self.finalized
+            .as_ref()
+            .map(|f| self.height.saturating_sub(f.height))
+    }
+}
+
+/**
+ * Reorg describe a replacement of stored blocks by blocks with different hashes
+ * height is the lowest replaced height, depth the number of orphaned blocks
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Reorg {
+    pub height: u64,
+    pub depth: u64,
+    pub old_hash: String,
+    pub new_hash: String,
+    pub provider: String,
+    pub detected_at: u64,
 }
 
-pub fn get_highest_blockchain(blockchains: Vec<Blockchain>) -> Option<Blockchain> {
-    match blockchains.len() {
-        0 => None,
-        _ => Some(
-            blockchains
+// detect_reorg compare stored blockchain with the new one and return a Reorg if a stored block is replaced by a different hash
+pub fn detect_reorg(stored: &Blockchain, new: &Blockchain, provider: &str) -> Option<Reorg> {
+    let fork = new
+        .blocks
+        .iter()
+        .filter_map(|b| {
+            stored
+                .blocks
                 .iter()
-                .filter(|b| b.height > 0)
-                .max_by(|a, b| a.height.cmp(&b.height))
-                .unwrap()
-                .clone(),
-        ),
+                .find(|s| s.height == b.height && s.hash != b.hash)
+                .map(|s| (s, b))
+        })
+        .min_by_key(|(s, _)| s.height);
+    let (old, new) = fork?;
+    Some(Reorg {
+        height: old.height,
+        depth: stored.height - old.height + 1,
+        old_hash: old.hash.clone(),
+        new_hash: new.hash.clone(),
+        provider: provider.to_string(),
+        detected_at: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    })
+}
+
+pub fn get_highest_blockchain(
+    blockchains: Vec<(String, Blockchain)>,
+) -> Option<(String, Blockchain)> {
+    blockchains
+        .into_iter()
+        .filter(|(_, b)| b.height > 0)
+        .max_by(|(_, a), (_, b)| a.height.cmp(&b.height))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chain(blocks: &[(u64, &str)]) -> Blockchain {
+        let mut b = Blockchain::new(None);
+        for (height, hash) in blocks {
+            b.add_block(Block {
+                hash: hash.to_string(),
+                height: *height,
+                time: 0,
+                txs: 0,
+            });
+        }
+        b
+    }
+
+    #[test]
+    fn blockchain_detect_reorg() {
+        let stored = chain(&[(10, "a10"), (11, "a11"), (12, "a12")]);
+        // new chain extend stored one, no reorg
+        let new = chain(&[(12, "a12"), (13, "a13")]);
+        assert_eq!(detect_reorg(&stored, &new, "p1"), None);
+        // blocks 11 and 12 are replaced
+        let new = chain(&[(11, "b11"), (12, "b12"), (13, "b13")]);
+        let reorg = detect_reorg(&stored, &new, "p1").unwrap();
+        assert_eq!(reorg.height, 11);
+        assert_eq!(reorg.depth, 2);
+        assert_eq!(reorg.old_hash, "a11");
+        assert_eq!(reorg.new_hash, "b11");
+        assert_eq!(reorg.provider, "p1");
+        // only the head is replaced at same height
+        let new = chain(&[(11, "a11"), (12, "b12")]);
+        let reorg = detect_reorg(&stored, &new, "p2").unwrap();
+        assert_eq!(reorg.height, 12);
+        assert_eq!(reorg.depth, 1);
     }
 }
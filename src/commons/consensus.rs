@@ -0,0 +1,212 @@
+use std::cmp::Reverse;
+
+use crate::conf::ConsensusPolicy;
+
+use super::blockchain::{get_highest_blockchain, Blockchain, Head};
+
+/**
+ * ProviderChain is the last blockchain returned by a provider
+ * weight is the provider trust, used by weighted consensus
+ */
+#[derive(Debug, Clone)]
+pub struct ProviderChain {
+    pub provider: String,
+    pub weight: u32,
+    pub blockchain: Blockchain,
+}
+
+/**
+ * Consensus is the canonical blockchain elected between providers
+ * provider is the provider whose blockchain was elected
+ * dissenters are providers with a different block hash at a height shared with the canonical blockchain
+ */
+#[derive(Debug, Clone)]
+pub struct Consensus {
+    pub provider: String,
+    pub blockchain: Blockchain,
+    pub dissenters: Vec<String>,
+}
+
+// select_canonical elect canonical blockchain according to policy, return None if no head reach the policy threshold
+pub fn select_canonical(chains: &[ProviderChain], policy: &ConsensusPolicy) -> Option<Consensus> {
+    let elected = match policy {
+        ConsensusPolicy::Highest => {
+            let highest = get_highest_blockchain(
+                chains
+                    .iter()
+                    .map(|c| (c.provider.clone(), c.blockchain.clone()))
+                    .collect(),
+            )?;
+            chains.iter().find(|c| c.provider == highest.0)?
+        }
+        _ => {
+            let total: u32 = chains.iter().map(|c| vote_weight(c, policy)).sum();
+            let mut candidates: Vec<&ProviderChain> = chains
+                .iter()
+                .filter(|c| !c.blockchain.blocks.is_empty())
+                .collect();
+            candidates.sort_by_key(|c| Reverse(c.blockchain.height));
+            candidates.into_iter().find(|candidate| {
+                let head = &candidate.blockchain.blocks[0];
+                let support: u32 = chains
+                    .iter()
+                    .filter(|c| {
+                        c.blockchain
+                            .blocks
+                            .iter()
+                            .any(|b| b.height == head.height && b.hash == head.hash)
+                    })
+                    .map(|c| vote_weight(c, policy))
+                    .sum();
+                trace!(
+                    "consensus candidate {} head {} support {}/{}",
+                    candidate.provider,
+                    head.height,
+                    support,
+                    total
+                );
+                match policy {
+                    ConsensusPolicy::Quorum { min_providers } => support >= *min_providers,
+                    _ => support * 2 > total,
+                }
+            })?
+        }
+    };
+    let dissenters: Vec<String> = chains
+        .iter()
+        .filter(|c| disagree(&c.blockchain, &elected.blockchain))
+        .map(|c| c.provider.clone())
+        .collect();
+    let mut blockchain = elected.blockchain.clone();
+    let agreeing: Vec<&Blockchain> = chains
+        .iter()
+        .filter(|c| !dissenters.contains(&c.provider))
+        .map(|c| &c.blockchain)
+        .collect();
+    blockchain.safe = highest_head(&agreeing, &elected.blockchain, |b| b.safe.as_ref());
+    blockchain.finalized = highest_head(&agreeing, &elected.blockchain, |b| b.finalized.as_ref());
+    Some(Consensus {
+        provider: elected.provider.clone(),
+        blockchain,
+        dissenters,
+    })
+}
+
+// highest_head return the highest head reported by agreeing providers which doesn't conflict with canonical blocks
+fn highest_head(
+    chains: &[&Blockchain],
+    canonical: &Blockchain,
+    head: impl Fn(&Blockchain) -> Option<&Head>,
+) -> Option<Head> {
+    chains
+        .iter()
+        .filter_map(|c| head(c))
+        .filter(|h| h.height <= canonical.height)
+        .filter(|h| {
+            !canonical
+                .blocks
+                .iter()
+                .any(|b| b.height == h.height && b.hash != h.hash)
+        })
+        .max_by_key(|h| h.height)
+        .cloned()
+}
+
+fn vote_weight(chain: &ProviderChain, policy: &ConsensusPolicy) -> u32 {
+    match policy {
+        ConsensusPolicy::Weighted => chain.weight,
+        _ => 1,
+    }
+}
+
+// disagree return true if blockchain have a block with different hash at a height known by canonical
+fn disagree(blockchain: &Blockchain, canonical: &Blockchain) -> bool {
+    blockchain.blocks.iter().any(|b| {
+        canonical
+            .blocks
+            .iter()
+            .any(|c| c.height == b.height && c.hash != b.hash)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commons::blockchain::Block;
+
+    fn provider_chain(provider: &str, weight: u32, blocks: &[(u64, &str)]) -> ProviderChain {
+        let mut blockchain = Blockchain::new(None);
+        for (height, hash) in blocks {
+            blockchain.add_block(Block {
+                hash: hash.to_string(),
+                height: *height,
+                time: 0,
+                txs: 0,
+            });
+        }
+        ProviderChain {
+            provider: provider.to_string(),
+            weight,
+            blockchain,
+        }
+    }
+
+    #[test]
+    fn consensus_select_canonical() {
+        let chains = vec![
+            provider_chain("honest_1", 1, &[(10, "a10"), (11, "a11")]),
+            provider_chain("honest_2", 1, &[(10, "a10"), (11, "a11"), (12, "a12")]),
+            provider_chain(
+                "liar",
+                5,
+                &[(10, "a10"), (11, "x11"), (12, "x12"), (13, "x13")],
+            ),
+        ];
+        // highest follow the liar
+        let c = select_canonical(&chains, &ConsensusPolicy::Highest).unwrap();
+        assert_eq!(c.provider, "liar");
+        assert_eq!(c.blockchain.height, 13);
+        // majority elect highest head known by 2 providers
+        let c = select_canonical(&chains, &ConsensusPolicy::Majority).unwrap();
+        assert_eq!(c.provider, "honest_1");
+        assert_eq!(c.blockchain.height, 11);
+        assert_eq!(c.dissenters, vec!["liar".to_string()]);
+        // quorum of 1 elect highest head
+        let c = select_canonical(&chains, &ConsensusPolicy::Quorum { min_providers: 1 }).unwrap();
+        assert_eq!(c.provider, "liar");
+        assert_eq!(
+            c.dissenters,
+            vec!["honest_1".to_string(), "honest_2".to_string()]
+        );
+        // quorum of 3 only agree on block 10, which is not a provider head
+        assert!(select_canonical(&chains, &ConsensusPolicy::Quorum { min_providers: 3 }).is_none());
+        // weighted follow the most trusted provider
+        let c = select_canonical(&chains, &ConsensusPolicy::Weighted).unwrap();
+        assert_eq!(c.provider, "liar");
+    }
+
+    #[test]
+    fn consensus_finality_heads() {
+        let head = |height: u64, hash: &str| {
+            Some(Head {
+                height,
+                hash: hash.to_string(),
+            })
+        };
+        let mut chains = vec![
+            provider_chain("honest_1", 1, &[(10, "a10"), (11, "a11")]),
+            provider_chain("honest_2", 1, &[(10, "a10"), (11, "a11"), (12, "a12")]),
+            provider_chain("liar", 1, &[(10, "a10"), (11, "x11")]),
+        ];
+        chains[0].blockchain.finalized = head(9, "a9");
+        chains[1].blockchain.finalized = head(10, "a10");
+        chains[1].blockchain.safe = head(11, "a11");
+        chains[2].blockchain.finalized = head(11, "x11");
+        let c = select_canonical(&chains, &ConsensusPolicy::Majority).unwrap();
+        assert_eq!(c.blockchain.height, 11);
+        // liar finalized head is ignored, honest_2 one is the highest
+        assert_eq!(c.blockchain.finalized, head(10, "a10"));
+        assert_eq!(c.blockchain.safe, head(11, "a11"));
+        assert_eq!(c.blockchain.finality_lag(), Some(1));
+    }
+}
@@ -0,0 +1,80 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use super::blockchain::{Block, Reorg};
+use crate::conf::{Network, Protocol};
+
+// EVENTS_CAPACITY is the number of events kept for slow subscribers before they lag
+const EVENTS_CAPACITY: usize = 1024;
+
+/**
+ * Event is pushed by collectors when canonical blockchain or providers change
+ * new_block: a canonical block has been persisted
+ * reorg: a persisted block has been replaced
+ * provider: a provider went up or down
+ */
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    NewBlock { block: Block },
+    Reorg { reorg: Reorg },
+    Provider { name: String, up: bool },
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Event {
+    pub protocol: Protocol,
+    pub network: Network,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl Event {
+    pub fn name(&self) -> &str {
+        match self.kind {
+            EventKind::NewBlock { .. } => "new_block",
+            EventKind::Reorg { .. } => "reorg",
+            EventKind::Provider { .. } => "provider",
+        }
+    }
+}
+
+static EVENTS: Lazy<broadcast::Sender<Event>> = Lazy::new(|| broadcast::channel(EVENTS_CAPACITY).0);
+
+// publish send event to every subscriber, event is dropped if nobody listen
+pub fn publish(protocol: &Protocol, network: &Network, kind: EventKind) {
+    let _ = EVENTS.send(Event {
+        protocol: *protocol,
+        network: network.clone(),
+        kind,
+    });
+}
+
+pub fn subscribe() -> broadcast::Receiver<Event> {
+    EVENTS.subscribe()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn events_publish_subscribe() {
+        let mut rx = subscribe();
+        publish(
+            &Protocol::Bitcoin,
+            &"mainnet".to_string(),
+            EventKind::Provider {
+                name: "node".to_string(),
+                up: true,
+            },
+        );
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.name(), "provider");
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"protocol":"bitcoin","network":"mainnet","type":"provider","name":"node","up":true}"#
+        );
+    }
+}
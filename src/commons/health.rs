@@ -0,0 +1,74 @@
+use serde::Serialize;
+
+// MIN_STALLED_AFTER is the lowest head age of a stalled chain in seconds, younger heads are within collector polling delays
+const MIN_STALLED_AFTER: u64 = 60;
+
+/**
+ * ChainHealth is the freshness of a network canonical head
+ * head_time is the head block unix time and head_age its age in seconds
+ * chain is stalled when head is older than stalled_after, stall_factor expected block times
+ * head time, age and stalled state are unknown (None) when head block has no time
+ */
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChainHealth {
+    pub height: u64,
+    pub head_time: Option<u64>,
+    pub head_age: Option<u64>,
+    pub block_time: f64,
+    pub stalled_after: u64,
+    pub stalled: Option<bool>,
+}
+
+impl ChainHealth {
+    pub fn new(
+        height: u64,
+        head_time: u64,
+        block_time: f64,
+        stall_factor: u32,
+        now: u64,
+    ) -> ChainHealth {
+        let head_time = Some(head_time).filter(|t| *t > 0);
+        let head_age = head_time.map(|t| now.saturating_sub(t));
+        let stalled_after =
+            ((block_time * stall_factor as f64).ceil() as u64).max(MIN_STALLED_AFTER);
+        ChainHealth {
+            height,
+            head_time,
+            head_age,
+            block_time,
+            stalled_after,
+            stalled: head_age.map(|age| age > stalled_after),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_stalled_after_stall_factor_block_times() {
+        let health = ChainHealth::new(100, 1000, 12.0, 10, 1120);
+        assert_eq!(health.head_age, Some(120));
+        assert_eq!(health.stalled_after, 120);
+        assert_eq!(health.stalled, Some(false));
+        assert_eq!(
+            ChainHealth::new(100, 1000, 12.0, 10, 1121).stalled,
+            Some(true)
+        );
+        // fast chains are not stalled within polling delays
+        let health = ChainHealth::new(100, 1000, 0.4, 10, 1030);
+        assert_eq!(health.stalled_after, MIN_STALLED_AFTER);
+        assert_eq!(health.stalled, Some(false));
+        // head time ahead of local clock
+        assert_eq!(
+            ChainHealth::new(100, 1010, 12.0, 10, 1000).head_age,
+            Some(0)
+        );
+        // head without time has unknown health
+        let health = ChainHealth::new(100, 0, 12.0, 10, 1000);
+        assert_eq!(health.head_time, None);
+        assert_eq!(health.head_age, None);
+        assert_eq!(health.stalled, None);
+    }
+}
@@ -1 +1,6 @@
-pub mod blockchain;
\ No newline at end of file
+pub mod blockchain;
+pub mod consensus;
+pub mod events;
+pub mod health;
+pub mod stats;
+pub mod status;
@@ -0,0 +1,144 @@
+use serde::Serialize;
+
+use super::blockchain::Block;
+
+/**
+ * IntervalStats are seconds between consecutive blocks
+ * intervals can be negative on protocols where block time is not monotonic (bitcoin)
+ */
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct IntervalStats {
+    pub samples: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub min: i64,
+    pub max: i64,
+}
+
+/**
+ * BlockStats aggregate stored blocks between from_height and to_height
+ * missing_blocks are heights of the range without stored block, they are left out of intervals and txs
+ * tps and blocks_per_hour are computed over the range duration, None when it is empty
+ * block without time (0) are ignored
+ */
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BlockStats {
+    pub blocks: usize,
+    pub missing_blocks: u64,
+    pub from_height: u64,
+    pub to_height: u64,
+    pub from_time: u64,
+    pub to_time: u64,
+    pub duration: u64,
+    pub block_interval: Option<IntervalStats>,
+    pub txs: u64,
+    pub tps: Option<f64>,
+    pub blocks_per_hour: Option<f64>,
+}
+
+impl BlockStats {
+    // new compute stats of blocks in any order, None if no block has a time
+    pub fn new(blocks: &[Block]) -> Option<BlockStats> {
+        let mut blocks = blocks.iter().filter(|b| b.time > 0).collect::<Vec<_>>();
+        blocks.sort_by_key(|b| b.height);
+        blocks.dedup_by_key(|b| b.height);
+        let (first, last) = (*blocks.first()?, *blocks.last()?);
+        let intervals = blocks
+            .windows(2)
+            .filter(|w| w[1].height == w[0].height + 1)
+            .map(|w| w[1].time as i64 - w[0].time as i64)
+            .collect::<Vec<_>>();
+        // first block txs were produced before the range start
+        let txs = blocks.iter().skip(1).map(|b| b.txs).sum();
+        let duration = last.time.saturating_sub(first.time);
+        let per_second = |count: f64| (duration > 0).then(|| count / duration as f64);
+        Some(BlockStats {
+            blocks: blocks.len(),
+            missing_blocks: last.height - first.height + 1 - blocks.len() as u64,
+            from_height: first.height,
+            to_height: last.height,
+            from_time: first.time,
+            to_time: last.time,
+            duration,
+            block_interval: IntervalStats::new(intervals),
+            txs,
+            tps: per_second(txs as f64),
+            blocks_per_hour: per_second((last.height - first.height) as f64 * 3600.0),
+        })
+    }
+}
+
+impl IntervalStats {
+    fn new(mut intervals: Vec<i64>) -> Option<IntervalStats> {
+        if intervals.is_empty() {
+            return None;
+        }
+        intervals.sort_unstable();
+        let n = intervals.len();
+        let median = match n % 2 {
+            0 => (intervals[n / 2 - 1] + intervals[n / 2]) as f64 / 2.0,
+            _ => intervals[n / 2] as f64,
+        };
+        // nearest rank percentile
+        let p95 = intervals[((n as f64 * 0.95).ceil() as usize).max(1) - 1] as f64;
+        Some(IntervalStats {
+            samples: n,
+            mean: intervals.iter().sum::<i64>() as f64 / n as f64,
+            median,
+            p95,
+            min: intervals[0],
+            max: intervals[n - 1],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(height: u64, time: u64, txs: u64) -> Block {
+        Block {
+            hash: format!("0x{}", height),
+            height,
+            time,
+            txs,
+        }
+    }
+
+    #[test]
+    fn stats_block_intervals_and_throughput() {
+        let mut blocks = (0..20)
+            .map(|i| block(100 + i, 1000 + i * 12, 10))
+            .collect::<Vec<_>>();
+        // one slow block, a missing height, and a block without time
+        blocks[19].time = blocks[18].time + 60;
+        blocks.remove(5);
+        blocks.push(block(120, 0, 10));
+        blocks.reverse();
+        let stats = BlockStats::new(&blocks).unwrap();
+        assert_eq!(stats.blocks, 19);
+        assert_eq!(stats.missing_blocks, 1);
+        assert_eq!((stats.from_height, stats.to_height), (100, 119));
+        assert_eq!(stats.duration, 18 * 12 + 60);
+        assert_eq!(stats.txs, 180);
+        assert_eq!(stats.tps, Some(180.0 / 276.0));
+        assert_eq!(stats.blocks_per_hour, Some(19.0 * 3600.0 / 276.0));
+        assert_eq!(
+            stats.block_interval,
+            Some(IntervalStats {
+                samples: 17,
+                mean: (16.0 * 12.0 + 60.0) / 17.0,
+                median: 12.0,
+                p95: 60.0,
+                min: 12,
+                max: 60,
+            })
+        );
+        // single block has no interval nor throughput
+        let stats = BlockStats::new(&blocks[1..2]).unwrap();
+        assert_eq!(stats.block_interval, None);
+        assert_eq!(stats.tps, None);
+        assert!(BlockStats::new(&[block(1, 0, 0)]).is_none());
+    }
+}
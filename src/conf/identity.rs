@@ -0,0 +1,121 @@
+use super::{Network, NetworkAppOptions, Protocol};
+
+/**
+ * Built-in chain identities of known networks, compared with provider chain_identity()
+ * ethereum like: eth_chainId as decimal
+ * bitcoin: getblockchaininfo chain
+ * tezos: chain_id
+ * polkadot: genesis block hash
+ * starknet: starknet_chainId
+ * solana: genesis hash
+ */
+fn default_chain_identity(protocol: &Protocol, network: &str) -> Option<&'static str> {
+    let identity = match (protocol, network) {
+        (Protocol::Bitcoin, "mainnet") => "main",
+        (Protocol::Bitcoin, "testnet") => "test",
+        (Protocol::Bitcoin, "signet") => "signet",
+        (Protocol::Bitcoin, "regtest") => "regtest",
+        (Protocol::Ethereum, "mainnet") => "1",
+        (Protocol::Ethereum, "goerli") => "5",
+        (Protocol::Ethereum, "sepolia") => "11155111",
+        (Protocol::Ethereum, "holesky") => "17000",
+        (Protocol::Ewf, "mainnet") => "246",
+        (Protocol::Ewf, "volta") => "73799",
+        (Protocol::Polygon, "mainnet") => "137",
+        (Protocol::Polygon, "mumbai") => "80001",
+        (Protocol::Polygon, "amoy") => "80002",
+        (Protocol::Moonbeam, "mainnet") => "1284",
+        (Protocol::Moonbeam, "moonriver") => "1285",
+        (Protocol::Moonbeam, "moonbase") => "1287",
+        (Protocol::Avalanche, "mainnet") => "43114",
+        (Protocol::Avalanche, "fuji") => "43113",
+        (Protocol::Tezos, "mainnet") => "NetXdQprcVkpaWU",
+        (Protocol::Tezos, "ghostnet") => "NetXnHfVqm9iesp",
+        (Protocol::Polkadot, "mainnet") => {
+            "0x91b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c3"
+        }
+        (Protocol::Polkadot, "kusama") => {
+            "0xb0a8d493285c2df73290dfb7e61f870f17b41801197a149ca93654499ea3dafe"
+        }
+        (Protocol::Polkadot, "westend") => {
+            "0xe143f23803ac50e8f6f8e62695d1ce9e4e1d68aa36c1cd2cfd15340213f3423e"
+        }
+        (Protocol::Starknet, "mainnet") => "0x534e5f4d41494e",
+        (Protocol::Starknet, "testnet") => "0x534e5f474f45524c49",
+        (Protocol::Starknet, "testnet2") => "0x534e5f474f45524c4932",
+        (Protocol::Starknet, "sepolia") => "0x534e5f5345504f4c4941",
+        (Protocol::Solana, "mainnet") => "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dktq1d",
+        (Protocol::Solana, "devnet") => "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG",
+        (Protocol::Solana, "testnet") => "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY",
+        _ => return None,
+    };
+    Some(identity)
+}
+
+// expected_chain_identity return chain identity endpoints must serve, None if unknown
+// network options override built-in defaults, cosmos networks are named by chain id
+pub fn expected_chain_identity(
+    protocol: &Protocol,
+    network: &Network,
+    net_opts: &NetworkAppOptions,
+) -> Option<String> {
+    if let Some(chain_id) = &net_opts.chain_id {
+        return Some(chain_id.clone());
+    }
+    match protocol {
+        Protocol::Cosmos => Some(network.clone()),
+        _ => default_chain_identity(protocol, network).map(|i| i.to_string()),
+    }
+}
+
+// identity_matches compare identities, hex values are case insensitive
+pub fn identity_matches(expected: &str, identity: &str) -> bool {
+    if expected.starts_with("0x") {
+        return expected.eq_ignore_ascii_case(identity);
+    }
+    expected == identity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_expected_chain_identity() {
+        let mut net_opts = NetworkAppOptions {
+            head_length: 1,
+            tick_rate: 1,
+            consensus: Default::default(),
+            chain_id: None,
+            alerts: Default::default(),
+            block_time: None,
+            stall_factor: 10,
+        };
+        let mainnet = "mainnet".to_string();
+        assert_eq!(
+            expected_chain_identity(&Protocol::Ethereum, &mainnet, &net_opts),
+            Some("1".to_string())
+        );
+        assert_eq!(
+            expected_chain_identity(&Protocol::Ethereum, &"devnet-42".to_string(), &net_opts),
+            None
+        );
+        assert_eq!(
+            expected_chain_identity(&Protocol::Cosmos, &"osmosis-1".to_string(), &net_opts),
+            Some("osmosis-1".to_string())
+        );
+        net_opts.chain_id = Some("11155111".to_string());
+        assert_eq!(
+            expected_chain_identity(&Protocol::Ethereum, &mainnet, &net_opts),
+            Some("11155111".to_string())
+        );
+    }
+
+    #[test]
+    fn identity_matches_hex_case() {
+        assert!(identity_matches("0x534e5f4d41494e", "0x534E5F4D41494E"));
+        assert!(identity_matches("main", "main"));
+        assert!(!identity_matches("main", "test"));
+        assert!(!identity_matches("NetXdQprcVkpaWU", "netxdqprcvkpawu"));
+    }
+}
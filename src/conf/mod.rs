@@ -1,10 +1,14 @@
+pub mod identity;
+
 use crate::{
     endpoints::{
         bitcoin_node::BitcoinNode, blockcypher::Blockcypher, blockstream::Blockstream,
-        ethereum_node::EthereumNode, polkadot_node::PolkadotNode, starknet_node::StarknetNode,
+        cosmos_lcd::CosmosLcd, cosmos_node::CosmosNode, ethereum_node::EthereumNode,
+        polkadot_node::PolkadotNode, solana_node::SolanaNode, starknet_node::StarknetNode,
         subscan::Subscan, tezos_node::TezosNode, tzkt::Tzkt, tzstats::TzStats, ProviderActions,
     },
-    requests::client::ReqwestClient,
+    prom::registry::get_base_url,
+    requests::{client::ReqwestClient, subscription::Subscription},
 };
 
 use clap::{Parser, ValueEnum};
@@ -113,7 +117,7 @@ impl<'de> Deserialize<'de> for Configuration {
             v.as_object().unwrap().get("protocols").unwrap(),
             &global,
         )
-        .unwrap();
+        .map_err(serde::de::Error::custom)?;
 
         Ok(Configuration {
             global,
@@ -167,34 +171,44 @@ where
                 debug!("Deserialize network {}", net);
                 let network = net.clone();
                 let o: Value = serde_json::from_str(&opts.to_string()).unwrap();
+                /*
+                 * Generic evm networks declare their chain next to providers
+                 */
+                let evm_chain = match protocol {
+                    Protocol::Evm => match EvmChain::deserialize(&o) {
+                        Ok(chain) => Some(chain),
+                        Err(e) => panic!("Invalid evm chain {} in configuration file: {}", network, e),
+                    },
+                    _ => None,
+                };
                 /*
                  * Deserialize Network options
                  */
                 let network_option_field = o.as_object().unwrap().get("network_options");
-                match network_option_field {
-                    Some(opt) => {
-                        let mut net_opt = global.networks_options.clone();
-                        net_opt
-                            .from_network_option_file(
-                                &NetworkAppOptionsConfigF::deserialize(opt).unwrap(),
-                            )
-                            .unwrap();
-
-                        net_opts.insert(network.clone(), net_opt);
-                    }
-                    None => {
-                        net_opts.insert(network.clone(), global.networks_options.clone());
-                    }
+                let mut net_opt = global.networks_options.clone();
+                if let Some(opt) = network_option_field {
+                    net_opt
+                        .from_network_option_file(
+                            &NetworkAppOptionsConfigF::deserialize(opt).unwrap(),
+                        )
+                        .unwrap();
                 }
+                // evm chains expect their declared chain id unless network options override it
+                if let Some(chain) = &evm_chain {
+                    net_opt
+                        .chain_id
+                        .get_or_insert_with(|| chain.chain_id.to_string());
+                }
+                net_opts.insert(network.clone(), net_opt);
                 /*
                  * Deserialize providers
                  */
                 let mut providers = Vec::new();
-                let mut alias_list = Vec::new();
                 o.as_object().unwrap().iter().for_each(|(provider, opt)| {
                     let endpoints_options: Value = serde_json::from_str(&opt.to_string()).unwrap();
                     match provider.as_str() {
                         "network_options" => {}
+                        "chain_id" | "url_suffix" if evm_chain.is_some() => {}
                         // Rpc is vec of provider declaration,
                         "rpc" => {
                             debug!("Found rpc {}", provider);
@@ -213,19 +227,16 @@ where
                                     );
 
                                     debug!("endpoint_opt: {:?}", endpoint_opts);
-                                    // Alias should be unique per protocol / network
-                                    if !alias_list.contains(&endpoint_opts.alias) {
-                                        if endpoint_opts.alias != default_alias(){
-                                            alias_list.push(endpoint_opts.alias.clone());
+                                    let rpc_provider = match &evm_chain {
+                                        Some(chain) => {
+                                            Provider::from_evm_chain(chain, endpoint_opts, &network)
                                         }
-                                    } else {
-                                        panic!("Found duplicated alias {} in config file at protocol: {} network: {} ", endpoint_opts.alias, protocol, network);
-                                    }
-                                    let rpc_provider = Provider::from_str(
-                                        &format!("{}_node", protocol),
-                                        endpoint_opts,
-                                        &network,
-                                    );
+                                        None => Provider::from_str(
+                                            &format!("{}_node", protocol),
+                                            endpoint_opts,
+                                            &network,
+                                        ),
+                                    };
                                     providers.push(rpc_provider);
                                 })
                         }
@@ -261,11 +272,53 @@ where
             proto_opts.insert(protocol, net_opts);
             proto_providers.insert(protocol, net_providers);
         });
+    // Endpoint name identify provider in consensus, status and metrics, it should be unique per protocol / network
+    for (protocol, net_providers) in proto_providers.iter_mut() {
+        for (network, providers) in net_providers.iter_mut() {
+            if let Err(name) = set_endpoint_names(providers) {
+                return Err(serde::de::Error::custom(format!(
+                    "Found duplicated endpoint name {} in config file at protocol: {} network: {}",
+                    name, protocol, network
+                )));
+            }
+        }
+    }
     Ok(ProtoOptsProvider {
         proto_opts,
         proto_providers,
     })
 }
+// set_endpoint_names name endpoints without alias which share a host by their host and their index in network providers
+// fail with the name of endpoints sharing an alias, or an alias used as another endpoint name
+fn set_endpoint_names(providers: &mut [Provider]) -> Result<(), String> {
+    let hosts: Vec<Option<String>> = providers
+        .iter()
+        .map(|p| {
+            p.get_endpoint()
+                .filter(|e| e.reqwest.alias == DEFAULT_ALIAS)
+                .map(|e| e.name())
+        })
+        .collect();
+    for (i, provider) in providers.iter_mut().enumerate() {
+        if let (Some(host), Some(endpoint)) = (&hosts[i], provider.get_endpoint_mut()) {
+            if hosts.iter().flatten().filter(|h| *h == host).count() > 1 {
+                endpoint.reqwest.config.name = Some(format!("{}-{}", host, i));
+            }
+        }
+    }
+    let mut names = Vec::new();
+    for name in providers
+        .iter()
+        .filter_map(|p| p.get_endpoint())
+        .map(|e| e.name())
+    {
+        if names.contains(&name) {
+            return Err(name);
+        }
+        names.push(name);
+    }
+    Ok(())
+}
 /**
  * Global configuration is used to store application configuration
  */
@@ -277,6 +330,8 @@ pub struct Global {
     pub networks_options: NetworkAppOptions,
     pub metrics: Metrics,
     pub server: Server,
+    pub proxy: Option<Proxy>,
+    pub alerts: Option<Alerts>,
 }
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Metrics {
@@ -286,11 +341,58 @@ pub struct Metrics {
 pub struct Server {
     pub port: u16,
 }
+/**
+ * Proxy server forward JSON-RPC requests to the most up to date rpc endpoint
+ * Endpoints behind canonical head by more than max_lag blocks are not used
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Proxy {
+    pub port: u16,
+    #[serde(default = "default_proxy_max_lag")]
+    pub max_lag: u64,
+}
+
+/**
+ * Alerts post firing and resolved alerts of every network to webhooks
+ * A still firing alert is notified again every repeat_interval seconds, 0 notify it only once
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Alerts {
+    pub webhooks: Vec<Webhook>,
+    #[serde(default = "default_alerts_repeat_interval")]
+    pub repeat_interval: u64,
+}
+/**
+ * Webhook receive alerts as json POST requests
+ * template is the payload, `{{field}}` placeholders are replaced by alert fields, without template the alert itself is sent
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(deserialize_with = "deserialize_string_hashmap")]
+    #[serde(default = "default_headers")]
+    pub headers: Option<HashMap<String, String>>,
+    pub template: Option<Value>,
+}
+
+/**
+ * EvmChain describe an EVM compatible chain declared under the generic evm protocol
+ * chain_id is the value endpoints must return for eth_chainId
+ * url_suffix is appended to every rpc url (ex: /ext/bc/C/rpc for Avalanche C-Chain)
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct EvmChain {
+    pub chain_id: u64,
+    pub url_suffix: Option<String>,
+}
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Database {
     #[serde(default = "default_database_keep_history")]
     pub keep_history: u32,
+    // provider_history_retention is the number of seconds providers observed heads are kept
+    #[serde(default = "default_database_provider_history_retention")]
+    pub provider_history_retention: u64,
     #[serde(default = "default_database_path")]
     #[serde(deserialize_with = "deserialize_path")]
     pub path: PathBuf,
@@ -320,6 +422,10 @@ pub enum Provider {
     StarknetNode(StarknetNode),
     AvalancheNode(EthereumNode),
     PolygonNode(EthereumNode),
+    SolanaNode(SolanaNode),
+    CosmosNode(CosmosNode),
+    CosmosLcd(CosmosLcd),
+    EvmNode(EthereumNode),
     None,
 }
 #[cfg(test)]
@@ -400,6 +506,13 @@ impl Provider {
             "starknet_node" => {
                 Provider::StarknetNode(StarknetNode::new(endpoint_opt, Protocol::Starknet, n))
             }
+            "solana_node" => {
+                Provider::SolanaNode(SolanaNode::new(endpoint_opt, Protocol::Solana, n))
+            }
+            "cosmos_node" => {
+                Provider::CosmosNode(CosmosNode::new(endpoint_opt, Protocol::Cosmos, n))
+            }
+            "cosmos_lcd" => Provider::CosmosLcd(CosmosLcd::new(endpoint_opt, Protocol::Cosmos, n)),
             "avalanche_node" => {
                 let endpoint_opt = EndpointOptions {
                     url: Some(format!("{}{}", endpoint_opt.url.unwrap(), "/ext/bc/C/rpc")),
@@ -410,6 +523,24 @@ impl Provider {
             _ => Provider::None,
         }
     }
+    // from_evm_chain build an rpc provider for a chain declared under evm protocol
+    pub fn from_evm_chain(
+        chain: &EvmChain,
+        endpoint_opt: EndpointOptions,
+        network: &Network,
+    ) -> Provider {
+        let endpoint_opt = EndpointOptions {
+            url: endpoint_opt
+                .url
+                .map(|url| format!("{}{}", url, chain.url_suffix.as_deref().unwrap_or_default())),
+            ..endpoint_opt
+        };
+        Provider::EvmNode(EthereumNode::new(
+            endpoint_opt,
+            Protocol::Evm,
+            network.to_owned(),
+        ))
+    }
     pub fn as_mut_provider_actions(&mut self) -> Option<&mut dyn ProviderActions> {
         match self {
             Provider::Blockstream(provider) => Some(provider),
@@ -426,9 +557,117 @@ impl Provider {
             Provider::MoonbeamNode(provider) => Some(provider),
             Provider::StarknetNode(provider) => Some(provider),
             Provider::AvalancheNode(provider) => Some(provider),
+            Provider::SolanaNode(provider) => Some(provider),
+            Provider::CosmosNode(provider) => Some(provider),
+            Provider::CosmosLcd(provider) => Some(provider),
+            Provider::EvmNode(provider) => Some(provider),
             _ => None,
         }
     }
+    pub fn get_endpoint(&self) -> Option<&Endpoint> {
+        match self {
+            Provider::Blockstream(provider) => Some(&provider.endpoint),
+            Provider::Blockcypher(provider) => Some(&provider.endpoint),
+            Provider::BitcoinNode(provider) => Some(&provider.endpoint),
+            Provider::EthereumNode(provider) => Some(&provider.endpoint),
+            Provider::EwfNode(provider) => Some(&provider.endpoint),
+            Provider::PolygonNode(provider) => Some(&provider.endpoint),
+            Provider::TezosNode(provider) => Some(&provider.endpoint),
+            Provider::Tzkt(provider) => Some(&provider.endpoint),
+            Provider::TzStats(provider) => Some(&provider.endpoint),
+            Provider::PolkadotNode(provider) => Some(&provider.endpoint),
+            Provider::Subscan(provider) => Some(&provider.endpoint),
+            Provider::MoonbeamNode(provider) => Some(&provider.endpoint),
+            Provider::StarknetNode(provider) => Some(&provider.endpoint),
+            Provider::AvalancheNode(provider) => Some(&provider.endpoint),
+            Provider::SolanaNode(provider) => Some(&provider.endpoint),
+            Provider::CosmosNode(provider) => Some(&provider.endpoint),
+            Provider::CosmosLcd(provider) => Some(&provider.endpoint),
+            Provider::EvmNode(provider) => Some(&provider.endpoint),
+            Provider::None => None,
+        }
+    }
+    pub fn get_endpoint_mut(&mut self) -> Option<&mut Endpoint> {
+        match self {
+            Provider::Blockstream(provider) => Some(&mut provider.endpoint),
+            Provider::Blockcypher(provider) => Some(&mut provider.endpoint),
+            Provider::BitcoinNode(provider) => Some(&mut provider.endpoint),
+            Provider::EthereumNode(provider) => Some(&mut provider.endpoint),
+            Provider::EwfNode(provider) => Some(&mut provider.endpoint),
+            Provider::PolygonNode(provider) => Some(&mut provider.endpoint),
+            Provider::TezosNode(provider) => Some(&mut provider.endpoint),
+            Provider::Tzkt(provider) => Some(&mut provider.endpoint),
+            Provider::TzStats(provider) => Some(&mut provider.endpoint),
+            Provider::PolkadotNode(provider) => Some(&mut provider.endpoint),
+            Provider::Subscan(provider) => Some(&mut provider.endpoint),
+            Provider::MoonbeamNode(provider) => Some(&mut provider.endpoint),
+            Provider::StarknetNode(provider) => Some(&mut provider.endpoint),
+            Provider::AvalancheNode(provider) => Some(&mut provider.endpoint),
+            Provider::SolanaNode(provider) => Some(&mut provider.endpoint),
+            Provider::CosmosNode(provider) => Some(&mut provider.endpoint),
+            Provider::CosmosLcd(provider) => Some(&mut provider.endpoint),
+            Provider::EvmNode(provider) => Some(&mut provider.endpoint),
+            Provider::None => None,
+        }
+    }
+    pub fn kind(&self) -> &str {
+        match self {
+            Provider::Blockstream(_) => "blockstream",
+            Provider::Blockcypher(_) => "blockcypher",
+            Provider::BitcoinNode(_) => "bitcoin_node",
+            Provider::EthereumNode(_) => "ethereum_node",
+            Provider::EwfNode(_) => "ewf_node",
+            Provider::PolygonNode(_) => "polygon_node",
+            Provider::TezosNode(_) => "tezos_node",
+            Provider::Tzkt(_) => "tzkt",
+            Provider::TzStats(_) => "tzstats",
+            Provider::PolkadotNode(_) => "polkadot_node",
+            Provider::Subscan(_) => "subscan",
+            Provider::MoonbeamNode(_) => "moonbeam_node",
+            Provider::StarknetNode(_) => "starknet_node",
+            Provider::AvalancheNode(_) => "avalanche_node",
+            Provider::SolanaNode(_) => "solana_node",
+            Provider::CosmosNode(_) => "cosmos_node",
+            Provider::CosmosLcd(_) => "cosmos_lcd",
+            Provider::EvmNode(_) => "evm_node",
+            Provider::None => "None",
+        }
+    }
+    // is_json_rpc return true if provider endpoint speak JSON-RPC and can be used behind rpc proxy
+    pub fn is_json_rpc(&self) -> bool {
+        matches!(
+            self,
+            Provider::BitcoinNode(_)
+                | Provider::EthereumNode(_)
+                | Provider::EwfNode(_)
+                | Provider::PolygonNode(_)
+                | Provider::PolkadotNode(_)
+                | Provider::MoonbeamNode(_)
+                | Provider::StarknetNode(_)
+                | Provider::AvalancheNode(_)
+                | Provider::SolanaNode(_)
+                | Provider::CosmosNode(_)
+                | Provider::EvmNode(_)
+        )
+    }
+    // subscriptions return heads push methods supported by provider
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        match self {
+            Provider::EthereumNode(_)
+            | Provider::EwfNode(_)
+            | Provider::PolygonNode(_)
+            | Provider::MoonbeamNode(_)
+            | Provider::AvalancheNode(_)
+            | Provider::EvmNode(_) => vec![Subscription::EthereumNewHeads],
+            Provider::PolkadotNode(_) => vec![
+                Subscription::PolkadotNewHeads,
+                Subscription::PolkadotFinalizedHeads,
+            ],
+            Provider::StarknetNode(_) => vec![Subscription::StarknetNewHeads],
+            Provider::TezosNode(_) => vec![Subscription::TezosMonitorHeads],
+            _ => Vec::new(),
+        }
+    }
     pub fn is_available(provider: &str) -> bool {
         matches!(
             provider,
@@ -446,6 +685,9 @@ impl Provider {
                 | "moonbeam_node"
                 | "starknet_node"
                 | "avalanche_node"
+                | "solana_node"
+                | "cosmos_node"
+                | "cosmos_lcd"
         )
     }
 }
@@ -458,6 +700,82 @@ pub enum EthereumProviders {
 pub struct NetworkAppOptionsConfigF {
     pub head_length: Option<u32>,
     pub tick_rate: Option<u32>,
+    pub consensus: Option<ConsensusPolicy>,
+    #[serde(default, deserialize_with = "deserialize_chain_id")]
+    pub chain_id: Option<String>,
+    pub alerts: Option<AlertRules>,
+    pub block_time: Option<f64>,
+    pub stall_factor: Option<u32>,
+}
+/**
+ * Consensus policy define how canonical blockchain is choosen between providers results
+ * highest: provider with highest head win
+ * majority: highest head known by more than half of providers
+ * quorum: highest head known by at least min_providers providers
+ * weighted: highest head known by providers holding more than half of total trust
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum ConsensusPolicy {
+    #[default]
+    Highest,
+    Majority,
+    Quorum {
+        min_providers: u32,
+    },
+    Weighted,
+}
+/**
+ * AlertRules are evaluated by collector at each tick, a rule without threshold is disabled
+ * no_new_block: seconds without a new canonical head
+ * provider_lag: blocks a provider is behind canonical head
+ * endpoint_down: seconds since an endpoint last successful request, at least endpoint rate plus tick_rate
+ * reorg_depth: reorgs deeper than reorg_depth blocks, resolved by the next canonical head
+ * chain_mismatch: endpoints serving another chain than expected
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+pub struct AlertRules {
+    pub no_new_block: Option<u64>,
+    pub provider_lag: Option<u64>,
+    pub endpoint_down: Option<u64>,
+    pub reorg_depth: Option<u64>,
+    pub chain_mismatch: Option<bool>,
+}
+impl AlertRules {
+    // merge override rules defined in network options
+    pub fn merge(&mut self, rules: &AlertRules) {
+        self.no_new_block = rules.no_new_block.or(self.no_new_block);
+        self.provider_lag = rules.provider_lag.or(self.provider_lag);
+        self.endpoint_down = rules.endpoint_down.or(self.endpoint_down);
+        self.reorg_depth = rules.reorg_depth.or(self.reorg_depth);
+        self.chain_mismatch = rules.chain_mismatch.or(self.chain_mismatch);
+    }
+}
+/**
+ * RateLimit is an endpoint quota of requests per period, like commercial api plans
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub per: RatePeriod,
+}
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RatePeriod {
+    Second,
+    Minute,
+    Hour,
+    Day,
+}
+impl RatePeriod {
+    pub fn seconds(&self) -> u64 {
+        match self {
+            RatePeriod::Second => 1,
+            RatePeriod::Minute => 60,
+            RatePeriod::Hour => 3600,
+            RatePeriod::Day => 24 * 3600,
+        }
+    }
 }
 /**
  * Network options is used to define network specific options
@@ -469,6 +787,19 @@ pub struct NetworkAppOptions {
     pub head_length: u32,
     #[serde(default = "default_tick_rate")]
     pub tick_rate: u32,
+    #[serde(default)]
+    pub consensus: ConsensusPolicy,
+    // chain_id override built-in expected chain identity, only settable per network
+    #[serde(skip_deserializing)]
+    pub chain_id: Option<String>,
+    #[serde(default)]
+    pub alerts: AlertRules,
+    // block_time override protocol expected seconds between two blocks, only settable per network
+    #[serde(skip_deserializing)]
+    pub block_time: Option<f64>,
+    // chain is stalled when canonical head is older than stall_factor block times
+    #[serde(default = "default_stall_factor")]
+    pub stall_factor: u32,
 }
 impl NetworkAppOptions {
     pub fn from_network_option_file(
@@ -481,8 +812,27 @@ impl NetworkAppOptions {
         if let Some(tick_rate) = network_option_file.tick_rate {
             self.tick_rate = tick_rate;
         }
+        if let Some(consensus) = &network_option_file.consensus {
+            self.consensus = consensus.clone();
+        }
+        if let Some(chain_id) = &network_option_file.chain_id {
+            self.chain_id = Some(chain_id.clone());
+        }
+        if let Some(alerts) = &network_option_file.alerts {
+            self.alerts.merge(alerts);
+        }
+        if let Some(block_time) = network_option_file.block_time {
+            self.block_time = Some(block_time);
+        }
+        if let Some(stall_factor) = network_option_file.stall_factor {
+            self.stall_factor = stall_factor;
+        }
         Ok(())
     }
+    // expected_block_time return network block_time, protocol one if not overridden
+    pub fn expected_block_time(&self, protocol: &Protocol) -> f64 {
+        self.block_time.unwrap_or_else(|| protocol.block_time())
+    }
 }
 /**
  * Endpoint Options Config is config file structure
@@ -492,17 +842,40 @@ pub struct ProviderOptsConfigF {
     pub retry: Option<u32>,
     pub delay: Option<u32>,
     pub rate: Option<u32>,
+    pub trust: Option<u32>,
+    pub subscription_url: Option<String>,
     #[serde(deserialize_with = "deserialize_string_hashmap")]
     #[serde(default = "default_headers")]
     pub headers: Option<HashMap<String, String>>,
     pub basic_auth: Option<BasicAuth>,
     #[serde(default = "default_alias")]
     pub alias: String,
+    pub pool_max_idle: Option<usize>,
+    pub pool_idle_timeout: Option<u32>,
+    pub tcp_keepalive: Option<u32>,
+    pub http2: Option<bool>,
+    pub rate_limits: Option<Vec<RateLimit>>,
+    pub backoff_factor: Option<u32>,
+    pub backoff_max: Option<u32>,
+    pub jitter: Option<bool>,
+    pub breaker_threshold: Option<u32>,
+    pub breaker_cooldown: Option<u32>,
 }
 fn default_headers() -> Option<HashMap<String, String>> {
     None
 }
 // deserialize_string_hashmap is used to deserialize headers from config file, any value should be converted to string
+// deserialize_chain_id accept numeric chain ids (eth_chainId) as well as strings
+fn deserialize_chain_id<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<serde_json::Value> = Option::deserialize(deserializer)?;
+    Ok(value.map(|value| match value {
+        serde_json::Value::String(s) => s,
+        _ => value.to_string(),
+    }))
+}
 fn deserialize_string_hashmap<'de, D>(
     deserializer: D,
 ) -> Result<Option<HashMap<String, String>>, D::Error>
@@ -565,10 +938,47 @@ pub struct EndpointOptions {
     pub rate: u32,
     #[serde(default = "default_endpoint_request_timeout")]
     pub timeout: u32,
+    #[serde(default = "default_endpoint_trust")]
+    pub trust: u32,
+    // subscription_url enable new heads subscription on node providers which support it
+    pub subscription_url: Option<String>,
     pub headers: Option<HashMap<String, String>>,
     pub basic_auth: Option<BasicAuth>,
     #[serde(default = "default_alias")]
     pub alias: String,
+    // name is set at config load to endpoints without alias sharing a host with other endpoints of their network
+    #[serde(skip)]
+    pub name: Option<String>,
+    // pool_max_idle is the number of idle connections kept open per host
+    #[serde(default = "default_endpoint_pool_max_idle")]
+    pub pool_max_idle: usize,
+    // pool_idle_timeout close idle connections after this number of seconds
+    #[serde(default = "default_endpoint_pool_idle_timeout")]
+    pub pool_idle_timeout: u32,
+    // tcp_keepalive is the interval of tcp keepalive probes in seconds
+    #[serde(default = "default_endpoint_tcp_keepalive")]
+    pub tcp_keepalive: u32,
+    // http2 is negotiated with tls endpoints which support it, false force http/1.1
+    #[serde(default = "default_endpoint_http2")]
+    pub http2: bool,
+    // rate_limits are endpoint quotas, a request is sent once every limit allow it
+    #[serde(default)]
+    pub rate_limits: Vec<RateLimit>,
+    // backoff_factor multiply delay between retries at each try, 1 keep a fixed delay
+    #[serde(default = "default_endpoint_backoff_factor")]
+    pub backoff_factor: u32,
+    // backoff_max is the longest delay between retries in seconds
+    #[serde(default = "default_endpoint_backoff_max")]
+    pub backoff_max: u32,
+    // jitter randomize delay between retries in [delay / 2, delay]
+    #[serde(default = "default_endpoint_jitter")]
+    pub jitter: bool,
+    // breaker_threshold is the number of consecutive failures opening endpoint circuit, 0 disable circuit breaker
+    #[serde(default = "default_endpoint_breaker_threshold")]
+    pub breaker_threshold: u32,
+    // breaker_cooldown is the number of seconds an open circuit wait before a probe request
+    #[serde(default = "default_endpoint_breaker_cooldown")]
+    pub breaker_cooldown: u32,
 }
 impl Default for EndpointOptions {
     fn default() -> Self {
@@ -581,9 +991,22 @@ impl Default for EndpointOptions {
                 delay: default_endpoint_delay(),
                 rate: default_endpoint_request_rate(),
                 timeout: default_endpoint_request_timeout(),
+                trust: default_endpoint_trust(),
+                subscription_url: None,
                 headers: None,
                 basic_auth: None,
                 alias: "".to_string(),
+                name: None,
+                pool_max_idle: default_endpoint_pool_max_idle(),
+                pool_idle_timeout: default_endpoint_pool_idle_timeout(),
+                tcp_keepalive: default_endpoint_tcp_keepalive(),
+                http2: default_endpoint_http2(),
+                rate_limits: Vec::new(),
+                backoff_factor: default_endpoint_backoff_factor(),
+                backoff_max: default_endpoint_backoff_max(),
+                jitter: default_endpoint_jitter(),
+                breaker_threshold: default_endpoint_breaker_threshold(),
+                breaker_cooldown: default_endpoint_breaker_cooldown(),
             },
         }
     }
@@ -615,6 +1038,12 @@ impl EndpointOptions {
             if let Some(rate) = options.rate {
                 endpoint_opt.rate = rate;
             }
+            if let Some(trust) = options.trust {
+                endpoint_opt.trust = trust;
+            }
+            if let Some(subscription_url) = options.subscription_url {
+                endpoint_opt.subscription_url = Some(subscription_url);
+            }
             if let Some(headers) = options.headers {
                 endpoint_opt.headers = Some(headers);
             }
@@ -624,6 +1053,36 @@ impl EndpointOptions {
             if options.alias != default_alias() {
                 endpoint_opt.alias = options.alias;
             }
+            if let Some(pool_max_idle) = options.pool_max_idle {
+                endpoint_opt.pool_max_idle = pool_max_idle;
+            }
+            if let Some(pool_idle_timeout) = options.pool_idle_timeout {
+                endpoint_opt.pool_idle_timeout = pool_idle_timeout;
+            }
+            if let Some(tcp_keepalive) = options.tcp_keepalive {
+                endpoint_opt.tcp_keepalive = tcp_keepalive;
+            }
+            if let Some(http2) = options.http2 {
+                endpoint_opt.http2 = http2;
+            }
+            if let Some(rate_limits) = options.rate_limits {
+                endpoint_opt.rate_limits = rate_limits;
+            }
+            if let Some(backoff_factor) = options.backoff_factor {
+                endpoint_opt.backoff_factor = backoff_factor;
+            }
+            if let Some(backoff_max) = options.backoff_max {
+                endpoint_opt.backoff_max = backoff_max;
+            }
+            if let Some(jitter) = options.jitter {
+                endpoint_opt.jitter = jitter;
+            }
+            if let Some(breaker_threshold) = options.breaker_threshold {
+                endpoint_opt.breaker_threshold = breaker_threshold;
+            }
+            if let Some(breaker_cooldown) = options.breaker_cooldown {
+                endpoint_opt.breaker_cooldown = breaker_cooldown;
+            }
         }
         endpoint_opt
     }
@@ -639,9 +1098,22 @@ impl EndpointOptions {
             delay: 1,
             rate: 0,
             timeout: default_endpoint_request_timeout(),
+            trust: default_endpoint_trust(),
+            subscription_url: None,
             headers,
             basic_auth,
             alias: DEFAULT_ALIAS.to_string(),
+            name: None,
+            pool_max_idle: default_endpoint_pool_max_idle(),
+            pool_idle_timeout: default_endpoint_pool_idle_timeout(),
+            tcp_keepalive: default_endpoint_tcp_keepalive(),
+            http2: default_endpoint_http2(),
+            rate_limits: Vec::new(),
+            backoff_factor: default_endpoint_backoff_factor(),
+            backoff_max: default_endpoint_backoff_max(),
+            jitter: default_endpoint_jitter(),
+            breaker_threshold: default_endpoint_breaker_threshold(),
+            breaker_cooldown: default_endpoint_breaker_cooldown(),
         }
     }
 }
@@ -666,6 +1138,14 @@ pub enum Protocol {
     Starknet,
     #[serde(rename = "avalanche")]
     Avalanche,
+    #[serde(rename = "solana")]
+    Solana,
+    // Cosmos SDK chains, networks are chain ids (cosmoshub-4, osmosis-1...)
+    #[serde(rename = "cosmos")]
+    Cosmos,
+    // Generic EVM chains, networks are chain names declared with their chain id
+    #[serde(rename = "evm")]
+    Evm,
     #[serde(rename = "None")]
     None,
 }
@@ -682,9 +1162,30 @@ impl Protocol {
             "moonbeam" => Some(Protocol::Moonbeam),
             "starknet" => Some(Protocol::Starknet),
             "avalanche" => Some(Protocol::Avalanche),
+            "solana" => Some(Protocol::Solana),
+            "cosmos" => Some(Protocol::Cosmos),
+            "evm" => Some(Protocol::Evm),
             _ => None,
         }
     }
+    // block_time return protocol expected seconds between two blocks, generic evm chains should set their own
+    pub fn block_time(&self) -> f64 {
+        match self {
+            Protocol::Bitcoin => 600.0,
+            Protocol::Ethereum => 12.0,
+            Protocol::Ewf => 5.0,
+            Protocol::Polygon => 2.0,
+            Protocol::Tezos => 8.0,
+            Protocol::Polkadot => 6.0,
+            Protocol::Moonbeam => 6.0,
+            Protocol::Starknet => 30.0,
+            Protocol::Avalanche => 2.0,
+            Protocol::Solana => 0.4,
+            Protocol::Cosmos => 6.0,
+            Protocol::Evm => 12.0,
+            Protocol::None => 0.0,
+        }
+    }
 }
 impl std::fmt::Display for Protocol {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -698,6 +1199,9 @@ impl std::fmt::Display for Protocol {
             Protocol::Moonbeam => "moonbeam",
             Protocol::Starknet => "starknet",
             Protocol::Avalanche => "avalanche",
+            Protocol::Solana => "solana",
+            Protocol::Cosmos => "cosmos",
+            Protocol::Evm => "evm",
             Protocol::None => "None",
         };
         write!(f, "{}", s)
@@ -719,6 +1223,19 @@ pub struct Endpoint {
     pub last_request: u64,
 }
 impl Endpoint {
+    // name return endpoint alias, or endpoint host when alias is not set
+    // endpoints sharing a host are named by their host and their index in network providers
+    pub fn name(&self) -> String {
+        if self.reqwest.alias != DEFAULT_ALIAS {
+            return self.reqwest.alias.clone();
+        }
+        if let Some(name) = &self.reqwest.config.name {
+            return name.clone();
+        }
+        // credentials are not part of the name
+        let host = get_base_url(&self.url);
+        host.rsplit('@').next().unwrap_or_default().to_string()
+    }
     #[cfg(test)]
     pub fn test_new(
         url: &str,
@@ -787,6 +1304,10 @@ pub const DEFAULT_METRICS_PORT: u16 = 8081;
 pub fn default_metrics_port() -> u16 {
     DEFAULT_SERVER_PORT
 }
+pub const DEFAULT_PROXY_MAX_LAG: u64 = 1;
+fn default_proxy_max_lag() -> u64 {
+    DEFAULT_PROXY_MAX_LAG
+}
 pub const DEFAULT_HEAD_LENGTH: u32 = 5;
 pub fn default_head_length() -> u32 {
     DEFAULT_HEAD_LENGTH
@@ -795,6 +1316,10 @@ pub const DEFAULT_TICK_RATE: u32 = 5;
 pub fn default_tick_rate() -> u32 {
     DEFAULT_TICK_RATE
 }
+pub const DEFAULT_STALL_FACTOR: u32 = 10;
+pub fn default_stall_factor() -> u32 {
+    DEFAULT_STALL_FACTOR
+}
 pub const DEFAULT_ENDPOINT_RETRY: u32 = 3;
 fn default_endpoint_retry() -> u32 {
     DEFAULT_ENDPOINT_RETRY
@@ -811,10 +1336,58 @@ pub const DEFAULT_ENDPOINT_REQUEST_TIMEOUT: u32 = 10;
 fn default_endpoint_request_timeout() -> u32 {
     DEFAULT_ENDPOINT_REQUEST_TIMEOUT
 }
+pub const DEFAULT_ENDPOINT_TRUST: u32 = 1;
+fn default_endpoint_trust() -> u32 {
+    DEFAULT_ENDPOINT_TRUST
+}
+pub const DEFAULT_ENDPOINT_POOL_MAX_IDLE: usize = 8;
+fn default_endpoint_pool_max_idle() -> usize {
+    DEFAULT_ENDPOINT_POOL_MAX_IDLE
+}
+pub const DEFAULT_ENDPOINT_POOL_IDLE_TIMEOUT: u32 = 90;
+fn default_endpoint_pool_idle_timeout() -> u32 {
+    DEFAULT_ENDPOINT_POOL_IDLE_TIMEOUT
+}
+pub const DEFAULT_ENDPOINT_TCP_KEEPALIVE: u32 = 60;
+fn default_endpoint_tcp_keepalive() -> u32 {
+    DEFAULT_ENDPOINT_TCP_KEEPALIVE
+}
+pub const DEFAULT_ENDPOINT_HTTP2: bool = true;
+fn default_endpoint_http2() -> bool {
+    DEFAULT_ENDPOINT_HTTP2
+}
+pub const DEFAULT_ENDPOINT_BACKOFF_FACTOR: u32 = 2;
+fn default_endpoint_backoff_factor() -> u32 {
+    DEFAULT_ENDPOINT_BACKOFF_FACTOR
+}
+pub const DEFAULT_ENDPOINT_BACKOFF_MAX: u32 = 30;
+fn default_endpoint_backoff_max() -> u32 {
+    DEFAULT_ENDPOINT_BACKOFF_MAX
+}
+pub const DEFAULT_ENDPOINT_JITTER: bool = true;
+fn default_endpoint_jitter() -> bool {
+    DEFAULT_ENDPOINT_JITTER
+}
+pub const DEFAULT_ENDPOINT_BREAKER_THRESHOLD: u32 = 5;
+fn default_endpoint_breaker_threshold() -> u32 {
+    DEFAULT_ENDPOINT_BREAKER_THRESHOLD
+}
+pub const DEFAULT_ENDPOINT_BREAKER_COOLDOWN: u32 = 60;
+fn default_endpoint_breaker_cooldown() -> u32 {
+    DEFAULT_ENDPOINT_BREAKER_COOLDOWN
+}
+pub const DEFAULT_ALERTS_REPEAT_INTERVAL: u64 = 3600;
+fn default_alerts_repeat_interval() -> u64 {
+    DEFAULT_ALERTS_REPEAT_INTERVAL
+}
 pub const DEFAULT_DATABASE_KEEP_HISTORY: u32 = 1000;
 fn default_database_keep_history() -> u32 {
     DEFAULT_DATABASE_KEEP_HISTORY
 }
+pub const DEFAULT_DATABASE_PROVIDER_HISTORY_RETENTION: u64 = 7 * 24 * 3600;
+fn default_database_provider_history_retention() -> u64 {
+    DEFAULT_DATABASE_PROVIDER_HISTORY_RETENTION
+}
 pub const DEFAULT_ALIAS: &str = "";
 fn default_alias() -> String {
     DEFAULT_ALIAS.to_string()
@@ -854,9 +1427,14 @@ impl Configuration {
             .set_default("global.networks_options.head_length", DEFAULT_HEAD_LENGTH)?
             .set_default("global.networks_options.tick_rate", DEFAULT_TICK_RATE)?
             .set_default("database.keep_history", DEFAULT_DATABASE_KEEP_HISTORY)?
+            .set_default(
+                "database.provider_history_retention",
+                DEFAULT_DATABASE_PROVIDER_HISTORY_RETENTION,
+            )?
             .set_default("global.endpoints.retry", DEFAULT_ENDPOINT_RETRY)?
             .set_default("global.endpoints.delay", DEFAULT_ENDPOINT_DELAY)?
             .set_default("global.endpoints.rate", DEFAULT_ENDPOINT_REQUEST_RATE)?
+            .set_default("global.endpoints.trust", DEFAULT_ENDPOINT_TRUST)?
             .set_default("global.endpoint.alias", DEFAULT_ALIAS)?
             .add_source(File::from(conf_path))
             .build()?;
@@ -986,9 +1564,24 @@ mod test {
             retry: Some(40),
             delay: None,
             rate: Some(60),
+            trust: None,
+            subscription_url: None,
             headers: Some(headers),
             basic_auth: Some(basic_auth),
             alias: default_alias(),
+            pool_max_idle: None,
+            pool_idle_timeout: Some(30),
+            tcp_keepalive: None,
+            http2: Some(false),
+            rate_limits: Some(vec![RateLimit {
+                requests: 3,
+                per: RatePeriod::Second,
+            }]),
+            backoff_factor: None,
+            backoff_max: Some(10),
+            jitter: None,
+            breaker_threshold: Some(0),
+            breaker_cooldown: None,
         };
 
         let provider_config_f = ProviderConfigF {
@@ -1008,6 +1601,16 @@ mod test {
             "delay should not change"
         );
         assert_eq!(merge.rate, 60, "rate should match with overriden value");
+        assert_eq!(merge.pool_max_idle, DEFAULT_ENDPOINT_POOL_MAX_IDLE);
+        assert_eq!(merge.pool_idle_timeout, 30);
+        assert!(!merge.http2, "http2 should match with overriden value");
+        assert_eq!(merge.rate_limits[0].per, RatePeriod::Second);
+        assert_eq!(merge.backoff_factor, DEFAULT_ENDPOINT_BACKOFF_FACTOR);
+        assert_eq!(merge.backoff_max, 10);
+        assert_eq!(
+            merge.breaker_threshold, 0,
+            "circuit breaker should be disabled"
+        );
         let merged_header = merge.headers.unwrap();
         assert_eq!(merged_header.contains_key("X-API-KEY"), true);
         assert_eq!(
@@ -1062,12 +1665,27 @@ mod test {
             config.global.metrics.port, 7,
             "metrics port should be set to 7"
         );
+        let proxy = config.global.proxy.as_ref().unwrap();
+        assert_eq!(proxy.port, 9, "proxy port should be set to 9");
+        assert_eq!(proxy.max_lag, 2, "proxy max lag should be set to 2");
+        let alerts = config.global.alerts.as_ref().unwrap();
+        assert_eq!(alerts.repeat_interval, 600);
+        assert_eq!(alerts.webhooks[0].url, "https://hooks.example.com/blockhead");
+        assert_eq!(
+            alerts.webhooks[0].headers.as_ref().unwrap().get("Authorization"),
+            Some(&"Bearer token".to_string())
+        );
+        assert!(alerts.webhooks[0].template.is_some());
 
         // test database options
         assert_eq!(
             config.database.keep_history, 88,
             "keep_history should be set to 88"
         );
+        assert_eq!(
+            config.database.provider_history_retention, 3600,
+            "provider_history_retention should be set to 3600"
+        );
 
         assert_eq!(
             config.database.path,
@@ -1144,6 +1762,10 @@ mod test {
             e.config.delay, 14,
             "First Bitcoin mainnet rpc url should be set"
         );
+        assert_eq!(
+            e.config.trust, 3,
+            "Second Bitcoin mainnet rpc trust should be set to 3"
+        );
 
         let basic_auth = e.config.basic_auth.unwrap();
         assert_eq!(basic_auth.username, "user".to_string());
@@ -1204,6 +1826,31 @@ mod test {
             bitcoin_mainnet_network_options.head_length, 9,
             "Bitcoin mainnet head_length should be set to 9"
         );
+        assert_eq!(
+            bitcoin_mainnet_network_options.consensus,
+            ConsensusPolicy::Quorum { min_providers: 3 },
+            "Bitcoin mainnet consensus should be quorum of 3"
+        );
+        assert_eq!(
+            bitcoin_mainnet_network_options.alerts,
+            AlertRules {
+                no_new_block: Some(120),
+                provider_lag: Some(2),
+                endpoint_down: Some(60),
+                reorg_depth: None,
+                chain_mismatch: None,
+            },
+            "Bitcoin mainnet alert rules should be merged with global ones"
+        );
+        assert_eq!(
+            bitcoin_mainnet_network_options.expected_block_time(&Protocol::Bitcoin),
+            540.0,
+            "Bitcoin mainnet block_time should override protocol default"
+        );
+        assert_eq!(
+            bitcoin_mainnet_network_options.stall_factor, 4,
+            "Bitcoin mainnet stall_factor should be set to 4"
+        );
         // Test ethereum provider
         let ethereum_net_provider = config.proto_providers.get(&Protocol::Ethereum).unwrap();
         let ethereum_mainnet_providers = ethereum_net_provider.get("mainnet").unwrap();
@@ -1237,6 +1884,11 @@ mod test {
             e.config.rate, 24,
             "First Ethereum mainnet rate should be equal to 24"
         );
+        assert_eq!(
+            e.config.subscription_url.as_deref(),
+            Some("wss://ws-ethereum-5.com"),
+            "First Ethereum mainnet subscription url should be set"
+        );
         let ethereum_network_options = config.proto_opts.get(&Protocol::Ethereum).unwrap();
         let ethereum_mainnet_network_options = ethereum_network_options.get("mainnet").unwrap();
         assert_eq!(
@@ -1248,6 +1900,11 @@ mod test {
             ethereum_mainnet_network_options.tick_rate, config.global.networks_options.tick_rate,
             "Ethereum mainnet tick_rate should be eq to global tick_rate"
         );
+        assert_eq!(
+            ethereum_mainnet_network_options.consensus,
+            ConsensusPolicy::Highest,
+            "Ethereum mainnet consensus should be highest per default"
+        );
         // Test Ethereum sepolia
         let ethereum_sepolia_providers = ethereum_net_provider.get("sepolia").unwrap();
         assert_eq!(
@@ -1289,6 +1946,99 @@ mod test {
             ethereum_sepolia_network_options.tick_rate, config.global.networks_options.tick_rate,
             "Ethereum sepolia tick_rate should be set to config.global.networks_options.tick_rate"
         );
+        assert_eq!(
+            ethereum_sepolia_network_options.chain_id.as_deref(),
+            Some("11155111"),
+            "Ethereum sepolia chain id should be overridden"
+        );
+        assert_eq!(
+            ethereum_mainnet_network_options.chain_id, None,
+            "Ethereum mainnet chain id should use built-in default"
+        );
+        // Test generic evm chain
+        let evm_net_provider = config.proto_providers.get(&Protocol::Evm).unwrap();
+        let arbitrum_providers = evm_net_provider.get("arbitrum").unwrap();
+        assert_eq!(
+            arbitrum_providers.len(),
+            1,
+            "should have 1 provider for evm arbitrum"
+        );
+        assert_eq!(arbitrum_providers[0].kind(), "evm_node");
+        match &arbitrum_providers[0] {
+            Provider::EvmNode(node) => {
+                assert_eq!(
+                    node.endpoint.url, "https://rpc-arbitrum-7.com/rpc",
+                    "Arbitrum url suffix should be appended"
+                );
+            }
+            _ => panic!("Arbitrum provider should be an evm node"),
+        }
+        let evm_network_options = config.proto_opts.get(&Protocol::Evm).unwrap();
+        assert_eq!(
+            evm_network_options
+                .get("arbitrum")
+                .unwrap()
+                .chain_id
+                .as_deref(),
+            Some("42161"),
+            "Arbitrum expected chain id should be set from evm chain"
+        );
+    }
+
+    #[test]
+    fn conf_endpoint_names() {
+        let config = r#"
+global:
+  networks_options: {}
+  metrics:
+    port: 8081
+  server:
+    port: 8080
+database:
+  keep_history: 10
+protocols:
+  ethereum:
+    mainnet:
+      rpc:
+      - url: https://mainnet.infura.io/v3/KEY1
+      - url: https://mainnet.infura.io/v3/KEY2
+      - url: https://rpc.ankr.com/eth
+"#;
+        let config: Configuration = serde_yaml::from_str(config).unwrap();
+        let names = config.proto_providers[&Protocol::Ethereum]["mainnet"]
+            .iter()
+            .filter_map(|p| p.get_endpoint())
+            .map(|e| e.name())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["mainnet.infura.io-0", "mainnet.infura.io-1", "rpc.ankr.com"]
+        );
+        // explicit aliases can't be shared
+        let config = r#"
+global:
+  networks_options: {}
+  metrics:
+    port: 8081
+  server:
+    port: 8080
+database:
+  keep_history: 10
+protocols:
+  ethereum:
+    mainnet:
+      rpc:
+      - url: https://mainnet.infura.io/v3/KEY1
+        options:
+          alias: infura
+      - url: https://mainnet.infura.io/v3/KEY2
+        options:
+          alias: infura
+"#;
+        let err = serde_yaml::from_str::<Configuration>(config).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Found duplicated endpoint name infura"));
     }
 
     #[test]
@@ -1319,6 +2069,14 @@ mod test {
             config.global.metrics.port, DEFAULT_METRICS_PORT,
             "metrics port should be set to default value"
         );
+        assert!(
+            config.global.proxy.is_none(),
+            "proxy should be disabled per default"
+        );
+        assert!(
+            config.global.alerts.is_none(),
+            "alerts should be disabled per default"
+        );
         assert_eq!(
             config.global.networks_options.head_length, DEFAULT_HEAD_LENGTH,
             "head_length should be set to default value"
@@ -1331,6 +2089,10 @@ mod test {
             config.database.keep_history, DEFAULT_DATABASE_KEEP_HISTORY,
             "keep_history should be set to default value"
         );
+        assert_eq!(
+            config.database.provider_history_retention, DEFAULT_DATABASE_PROVIDER_HISTORY_RETENTION,
+            "provider_history_retention should be set to default value"
+        );
         assert_eq!(
             config.database.path,
             PathBuf::from(DEFAULT_DATABASE_PATH),
@@ -1,16 +1,66 @@
 use std::error::Error;
 
 use once_cell::sync::OnceCell;
-use redb::{Database, ReadableTable, TableDefinition};
+use redb::{Database, ReadableTable, TableDefinition, WriteTransaction};
+use serde::{Deserialize, Serialize};
 
 use std::io;
 
 use crate::{
-    commons::blockchain::{self, Block},
-    conf::{self, get_configuration, Network, Protocol},
+    alerts::Alert,
+    commons::{
+        blockchain::{self, Block, Reorg},
+        status::ProviderHead,
+    },
+    conf::{
+        self, get_configuration, Network, Protocol, DEFAULT_DATABASE_KEEP_HISTORY,
+        DEFAULT_DATABASE_PROVIDER_HISTORY_RETENTION,
+    },
 };
-const TABLE: TableDefinition<&str, &str> = TableDefinition::new("blockchain");
+// SCHEMA_VERSION is increased on every schema change, older databases are migrated at init
+// version 3 rescale polkadot and tezos block times stored in milliseconds by polkadot node and tzstats parsers
+pub const SCHEMA_VERSION: u64 = 3;
+const SCHEMA_TABLE: TableDefinition<&str, u64> = TableDefinition::new("schema");
+// BLOCKS_TABLE store blocks by (protocol, network, height), BLOCKS_HASH_TABLE index heights by (protocol, network, hash)
+const BLOCKS_TABLE: TableDefinition<(&str, &str, u64), &str> =
+    TableDefinition::new("network_blocks");
+const BLOCKS_HASH_TABLE: TableDefinition<(&str, &str, &str), u64> =
+    TableDefinition::new("network_blocks_hash");
+const METADATA_TABLE: TableDefinition<(&str, &str), &str> =
+    TableDefinition::new("network_metadata");
+const REORG_TABLE: TableDefinition<&str, &str> = TableDefinition::new("reorg");
+// PROVIDER_HEADS_TABLE store providers observed heads by (protocol, network, provider, observed_at)
+const PROVIDER_HEADS_TABLE: TableDefinition<(&str, &str, &str, u64), &str> =
+    TableDefinition::new("provider_heads");
+// ALERTS_TABLE store firing alerts by (protocol, network, alert id)
+const ALERTS_TABLE: TableDefinition<(&str, &str, &str), &str> = TableDefinition::new("alerts");
+// schema version 1 tables, a json blockchain per network and its blocks index
+const LEGACY_TABLE: TableDefinition<&str, &str> = TableDefinition::new("blockchain");
+const LEGACY_BLOCKS_TABLE: TableDefinition<(&str, u64), &str> = TableDefinition::new("blocks");
+const LEGACY_BLOCKS_HASH_TABLE: TableDefinition<(&str, &str), u64> =
+    TableDefinition::new("blocks_hash");
+// MILLIS_BLOCK_TIME is the lowest block time stored in milliseconds, in seconds it is year 5138
+const MILLIS_BLOCK_TIME: u64 = 100_000_000_000;
+// MILLIS_BLOCK_TIME_PROTOCOLS are protocols whose blocks were stored with time in milliseconds
+const MILLIS_BLOCK_TIME_PROTOCOLS: [Protocol; 2] = [Protocol::Polkadot, Protocol::Tezos];
+// BLOCKCHAIN_BLOCKS_LIMIT is the max number of blocks returned with a blockchain, older blocks are served by block lookups
+const BLOCKCHAIN_BLOCKS_LIMIT: usize = 1000;
 pub static DATABASE: OnceCell<Redb> = OnceCell::new();
+
+/**
+ * NetworkMetadata is the stored head of a network
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct NetworkMetadata {
+    pub height: u64,
+    pub head_hash: String,
+    pub last_scrapping_task: u64,
+    #[serde(default)]
+    pub safe: Option<blockchain::Head>,
+    #[serde(default)]
+    pub finalized: Option<blockchain::Head>,
+}
+
 #[derive(Debug)]
 pub struct Redb {
     db: Database,
@@ -23,8 +73,9 @@ impl Redb {
         match db {
             Ok(db) => {
                 info!("Redb::new() db is ok");
-                let db = db;
-                DATABASE.set(Redb { db }).unwrap();
+                let rdb = Redb { db };
+                rdb.migrate()?;
+                DATABASE.set(rdb).unwrap();
                 Ok(())
             }
             Err(e) => {
@@ -39,7 +90,8 @@ impl Redb {
                                         info!("Redb db is created");
                                         let rdb = Redb { db };
                                         // it seem if we don't insert a first data, db will not be able to be reopen
-                                        rdb.set("keep", "1")?;
+                                        // schema version is written by migrate
+                                        rdb.migrate()?;
                                         DATABASE.set(rdb).unwrap();
                                         Ok(())
                                     }
@@ -63,44 +115,184 @@ impl Redb {
             }
         }
     }
-    pub fn set(
-        &self,
-        key: &str,
-        value: &str,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    pub fn schema_version(&self) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(SCHEMA_TABLE) {
+            Ok(table) => table,
+            Err(redb::Error::TableDoesNotExist(_)) => return Ok(1),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let version = table.get("version")?.map(|v| v.value()).unwrap_or(1);
+        Ok(version)
+    }
+    // migrate upgrade database to SCHEMA_VERSION, json blockchains of schema version 1 are split into blocks
+    // block times stored in milliseconds before version 3 are rescaled to seconds
+    pub fn migrate(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let version = self.schema_version()?;
+        if version >= SCHEMA_VERSION {
+            debug!("Redb schema version {} is up to date", version);
+            return Ok(());
+        }
+        info!(
+            "Migrate database schema from version {} to {}",
+            version, SCHEMA_VERSION
+        );
+        let mut legacy = Vec::new();
+        {
+            let read_txn = self.db.begin_read()?;
+            match read_txn.open_table(LEGACY_TABLE) {
+                Ok(table) => {
+                    for entry in table.iter()? {
+                        let (key, value) = entry?;
+                        match serde_json::from_str::<blockchain::Blockchain>(value.value()) {
+                            Ok(blockchain) => legacy.push((key.value().to_string(), blockchain)),
+                            Err(_) => debug!("Skip legacy entry {}", key.value()),
+                        }
+                    }
+                }
+                Err(redb::Error::TableDoesNotExist(_)) => {}
+                Err(e) => return Err(Box::new(e)),
+            };
+        }
+        let keep = Redb::keep_history();
         let write_txn = self.db.begin_write()?;
         {
-            let mut table = write_txn.open_table(TABLE)?;
-            table.insert(key, &value)?;
+            for (key, blockchain) in legacy.iter_mut() {
+                // legacy key is protocol-network, protocol never contains a dash
+                let (protocol, network) = match key.split_once('-') {
+                    Some(key) => key,
+                    None => continue,
+                };
+                blockchain.sort();
+                info!(
+                    "Migrate {} {} blockchain: {} blocks",
+                    protocol,
+                    network,
+                    blockchain.blocks.len()
+                );
+                let metadata = NetworkMetadata {
+                    height: blockchain.height,
+                    head_hash: blockchain
+                        .blocks
+                        .first()
+                        .map(|b| b.hash.clone())
+                        .unwrap_or_default(),
+                    last_scrapping_task: blockchain.last_scrapping_task,
+                    safe: None,
+                    finalized: None,
+                };
+                Redb::write_blocks(
+                    &write_txn,
+                    protocol,
+                    network,
+                    &blockchain.blocks,
+                    &metadata,
+                    keep,
+                )?;
+            }
+            write_txn.delete_table(LEGACY_TABLE)?;
+            write_txn.delete_table(LEGACY_BLOCKS_TABLE)?;
+            write_txn.delete_table(LEGACY_BLOCKS_HASH_TABLE)?;
+            let rescaled = Redb::rescale_block_times(&write_txn)?;
+            if rescaled > 0 {
+                info!(
+                    "Migrate {} block times from milliseconds to seconds",
+                    rescaled
+                );
+            }
+            let mut table = write_txn.open_table(SCHEMA_TABLE)?;
+            table.insert("version", SCHEMA_VERSION)?;
         }
         write_txn.commit()?;
         Ok(())
     }
+    // rescale_block_times convert block times in milliseconds to seconds, return the number of rescaled blocks
+    fn rescale_block_times(
+        write_txn: &WriteTransaction,
+    ) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let protocols = MILLIS_BLOCK_TIME_PROTOCOLS.map(|p| p.to_string());
+        let mut blocks_table = write_txn.open_table(BLOCKS_TABLE)?;
+        let mut rescaled = Vec::new();
+        for entry in blocks_table.iter()? {
+            let (key, value) = entry?;
+            let (protocol, network, height) = key.value();
+            if !protocols.iter().any(|p| p == protocol) {
+                continue;
+            }
+            let mut block: Block = serde_json::from_str(value.value())?;
+            if block.time >= MILLIS_BLOCK_TIME {
+                block.time /= 1000;
+                rescaled.push((protocol.to_string(), network.to_string(), height, block));
+            }
+        }
+        for (protocol, network, height, block) in rescaled.iter() {
+            let json_value = serde_json::to_string(block)?;
+            blocks_table.insert(
+                (protocol.as_str(), network.as_str(), *height),
+                json_value.as_str(),
+            )?;
+        }
+        Ok(rescaled.len())
+    }
     fn to_db_key(protocol: &Protocol, network: &Network) -> String {
         format!("{}-{}", protocol, network)
     }
+    fn keep_history() -> u32 {
+        get_configuration()
+            .map(|c| c.database.keep_history)
+            .unwrap_or(DEFAULT_DATABASE_KEEP_HISTORY)
+    }
+    pub fn get_metadata(
+        &self,
+        protocol: &Protocol,
+        network: &Network,
+    ) -> Result<Option<NetworkMetadata>, Box<dyn Error + Send + Sync>> {
+        debug!("Redb get_metadata({:?},{:?})", protocol, network);
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(METADATA_TABLE) {
+            Ok(table) => table,
+            Err(redb::Error::TableDoesNotExist(_)) => return Ok(None),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let protocol = protocol.to_string();
+        let res = table.get((protocol.as_str(), network.as_str()))?;
+        match res {
+            Some(data) => Ok(Some(serde_json::from_str(data.value())?)),
+            None => Ok(None),
+        }
+    }
+    // get_blockchain return network head and its last stored blocks
     pub fn get_blockchain(
         &self,
         protocol: &Protocol,
         network: &Network,
     ) -> Result<blockchain::Blockchain, Box<dyn Error + Send + Sync>> {
         debug!("Redb get_blockchain({:?},{:?})", protocol, network);
-        let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(TABLE)?;
-        let key = Redb::to_db_key(protocol, network);
-        let res = table.get(key.as_str())?;
-        match res {
-            Some(data) => {
-                let blockchain: blockchain::Blockchain = serde_json::from_str(data.value())?;
-                Ok(blockchain)
-            }
+        let metadata = match self.get_metadata(protocol, network)? {
+            Some(metadata) => metadata,
             None => {
                 debug!("Redb get_blockchain return an empty response.");
-                Err("Error: Reddb return None".into())
+                return Err("Error: Reddb return None".into());
             }
-        }
+        };
+        let blocks = self.get_blocks(
+            protocol,
+            network,
+            0,
+            u64::MAX,
+            BLOCKCHAIN_BLOCKS_LIMIT,
+            true,
+        )?;
+        Ok(blockchain::Blockchain {
+            blocks,
+            height: metadata.height,
+            last_scrapping_task: metadata.last_scrapping_task,
+            safe: metadata.safe,
+            finalized: metadata.finalized,
+        })
     }
 
+    // set_blockchain write blocks of a new head, blocks out of keep_history are pruned
     pub fn set_blockchain(
         &self,
         blockchain: &blockchain::Blockchain,
@@ -108,58 +300,635 @@ impl Redb {
         network: &Network,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         debug!("Redb set_blockchain({:?},{:?})", protocol, network);
-        let chain_db = self.get_blockchain(protocol, network);
-        let chain_db = match chain_db {
-            Ok(data) => data,
-            Err(e) => {
-                debug!(
-                    "Redb get_blockchain return an error {:?} init empty blockchain",
-                    e
-                );
-                blockchain::Blockchain::new(None)
+        let head_hash = blockchain
+            .blocks
+            .iter()
+            .max_by_key(|b| b.height)
+            .map(|b| b.hash.clone())
+            .unwrap_or_default();
+        if let Some(stored) = self.get_metadata(protocol, network)? {
+            if stored.height > blockchain.height
+                || (stored.height == blockchain.height && stored.head_hash == head_hash)
+            {
+                debug!("Redb set_blockchain: same height, do nothing");
+                return Ok(());
             }
+            debug!(
+                "Detect new chain height {} vs {}, updating database.",
+                stored.height, blockchain.height
+            );
+        }
+        let metadata = NetworkMetadata {
+            height: blockchain.height,
+            head_hash,
+            last_scrapping_task: blockchain.last_scrapping_task,
+            safe: blockchain.safe.clone(),
+            finalized: blockchain.finalized.clone(),
         };
-        if chain_db.height >= blockchain.height {
-            debug!("Redb set_blockchain: same height, do nothing");
-            return Ok(());
+        let protocol = protocol.to_string();
+        let write_txn = self.db.begin_write()?;
+        Redb::write_blocks(
+            &write_txn,
+            &protocol,
+            network,
+            &blockchain.blocks,
+            &metadata,
+            Redb::keep_history(),
+        )?;
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    // write_blocks insert blocks and network metadata, replaced and pruned blocks are removed from hash index
+    fn write_blocks(
+        write_txn: &WriteTransaction,
+        protocol: &str,
+        network: &str,
+        blocks: &[Block],
+        metadata: &NetworkMetadata,
+        keep: u32,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut blocks_table = write_txn.open_table(BLOCKS_TABLE)?;
+        let mut hash_table = write_txn.open_table(BLOCKS_HASH_TABLE)?;
+        let mut stale_hashes = Vec::new();
+        for block in blocks.iter() {
+            let json_value = serde_json::to_string(block)?;
+            if let Some(old) =
+                blocks_table.insert((protocol, network, block.height), json_value.as_str())?
+            {
+                let old: Block = serde_json::from_str(old.value())?;
+                if old.hash != block.hash {
+                    stale_hashes.push(old.hash);
+                }
+            }
+            hash_table.insert((protocol, network, block.hash.as_str()), block.height)?;
         }
+        let lowest = metadata.height.saturating_sub(keep.max(1) as u64 - 1);
+        for pruned in blocks_table.drain((protocol, network, 0)..(protocol, network, lowest))? {
+            let (_, value) = pruned?;
+            let pruned: Block = serde_json::from_str(value.value())?;
+            stale_hashes.push(pruned.hash);
+        }
+        for hash in stale_hashes {
+            hash_table.remove((protocol, network, hash.as_str()))?;
+        }
+        let mut metadata_table = write_txn.open_table(METADATA_TABLE)?;
+        let json_value = serde_json::to_string(metadata)?;
+        metadata_table.insert((protocol, network), json_value.as_str())?;
+        Ok(())
+    }
+
+    // get_block return stored block at height, None if block is outside stored history
+    pub fn get_block(
+        &self,
+        protocol: &Protocol,
+        network: &Network,
+        height: u64,
+    ) -> Result<Option<Block>, Box<dyn Error + Send + Sync>> {
+        debug!("Redb get_block({:?},{:?},{})", protocol, network, height);
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(BLOCKS_TABLE) {
+            Ok(table) => table,
+            Err(redb::Error::TableDoesNotExist(_)) => return Ok(None),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let protocol = protocol.to_string();
+        let res = table.get((protocol.as_str(), network.as_str(), height))?;
+        match res {
+            Some(data) => Ok(Some(serde_json::from_str(data.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_block_by_hash(
+        &self,
+        protocol: &Protocol,
+        network: &Network,
+        hash: &str,
+    ) -> Result<Option<Block>, Box<dyn Error + Send + Sync>> {
         debug!(
-            "Detect new chain height {} vs {}, updating database.",
-            chain_db.height, blockchain.height
+            "Redb get_block_by_hash({:?},{:?},{})",
+            protocol, network, hash
         );
+        let height = {
+            let read_txn = self.db.begin_read()?;
+            let table = match read_txn.open_table(BLOCKS_HASH_TABLE) {
+                Ok(table) => table,
+                Err(redb::Error::TableDoesNotExist(_)) => return Ok(None),
+                Err(e) => return Err(Box::new(e)),
+            };
+            let protocol = protocol.to_string();
+            let res = table.get((protocol.as_str(), network.as_str(), hash))?;
+            match res {
+                Some(height) => height.value(),
+                None => return Ok(None),
+            }
+        };
+        self.get_block(protocol, network, height)
+    }
 
-        let keep = get_configuration().unwrap().database.keep_history;
+    // get_blocks return at most limit stored blocks between from and to heights (included)
+    // blocks are sorted by height, descending if rev is true
+    pub fn get_blocks(
+        &self,
+        protocol: &Protocol,
+        network: &Network,
+        from: u64,
+        to: u64,
+        limit: usize,
+        rev: bool,
+    ) -> Result<Vec<Block>, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Redb get_blocks({:?},{:?},{},{},{})",
+            protocol, network, from, to, limit
+        );
+        if from > to {
+            return Ok(Vec::new());
+        }
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(BLOCKS_TABLE) {
+            Ok(table) => table,
+            Err(redb::Error::TableDoesNotExist(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let protocol = protocol.to_string();
+        let range = table.range(
+            (protocol.as_str(), network.as_str(), from)..=(protocol.as_str(), network.as_str(), to),
+        )?;
+        let range: Box<dyn Iterator<Item = _>> = if rev {
+            Box::new(range.rev())
+        } else {
+            Box::new(range)
+        };
+        let mut blocks = Vec::new();
+        for entry in range.take(limit) {
+            let (_, value) = entry?;
+            blocks.push(serde_json::from_str(value.value())?);
+        }
+        Ok(blocks)
+    }
 
-        let mut merged_blocks: Vec<Block> = chain_db
-            .blocks
-            .into_iter()
-            .chain(blockchain.blocks.clone().into_iter())
-            .fold(Vec::new(), |mut acc, b| {
-                // if block is not in acc, add it
-                if !acc.iter().any(|block: &Block| block.height == b.height) {
-                    trace!("Add block {}", b.height);
-                    acc.push(b);
-                } else {
-                    // by using chain on param blockchain at second arg, we ensure blockchain param have priority and will replace the block in db
-                    if let Some(idx) = acc.iter().position(|block| block.height == b.height) {
-                        trace!("Replace block {} with {}", acc[idx].height, b.height);
-                        acc[idx] = b;
-                    }
+    // add_provider_heads store heads observed during a collector tick, heads older than provider_history_retention are pruned
+    pub fn add_provider_heads(
+        &self,
+        protocol: &Protocol,
+        network: &Network,
+        heads: &[(String, ProviderHead)],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        debug!("Redb add_provider_heads({:?},{:?})", protocol, network);
+        let retention = get_configuration()
+            .map(|c| c.database.provider_history_retention)
+            .unwrap_or(DEFAULT_DATABASE_PROVIDER_HISTORY_RETENTION);
+        let protocol = protocol.to_string();
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(PROVIDER_HEADS_TABLE)?;
+            for (provider, head) in heads {
+                let key = (
+                    protocol.as_str(),
+                    network.as_str(),
+                    provider.as_str(),
+                    head.observed_at,
+                );
+                let json_value = serde_json::to_string(head)?;
+                table.insert(key, json_value.as_str())?;
+                let expired = head.observed_at.saturating_sub(retention * 1000);
+                for pruned in table.drain(
+                    (protocol.as_str(), network.as_str(), provider.as_str(), 0)
+                        ..(
+                            protocol.as_str(),
+                            network.as_str(),
+                            provider.as_str(),
+                            expired,
+                        ),
+                )? {
+                    pruned?;
                 }
-                acc
-            });
-        merged_blocks.sort_by(|a, b| b.height.cmp(&a.height));
-        let merged_blocks = merged_blocks.iter().take(keep as usize).cloned().collect();
-        let mut blockchain = blockchain.clone();
-        blockchain.blocks = merged_blocks;
+            }
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    // get_provider_heads return at most limit heads observed between from and to (unix time in milliseconds), oldest first
+    pub fn get_provider_heads(
+        &self,
+        protocol: &Protocol,
+        network: &Network,
+        provider: &str,
+        from: u64,
+        to: u64,
+        limit: usize,
+    ) -> Result<Vec<ProviderHead>, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Redb get_provider_heads({:?},{:?},{},{},{},{})",
+            protocol, network, provider, from, to, limit
+        );
+        if from > to {
+            return Ok(Vec::new());
+        }
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(PROVIDER_HEADS_TABLE) {
+            Ok(table) => table,
+            Err(redb::Error::TableDoesNotExist(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let protocol = protocol.to_string();
+        let range = table.range(
+            (protocol.as_str(), network.as_str(), provider, from)
+                ..=(protocol.as_str(), network.as_str(), provider, to),
+        )?;
+        let mut heads = Vec::new();
+        for entry in range.take(limit) {
+            let (_, value) = entry?;
+            heads.push(serde_json::from_str(value.value())?);
+        }
+        Ok(heads)
+    }
+
+    pub fn get_reorgs(
+        &self,
+        protocol: &Protocol,
+        network: &Network,
+    ) -> Result<Vec<Reorg>, Box<dyn Error + Send + Sync>> {
+        debug!("Redb get_reorgs({:?},{:?})", protocol, network);
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(REORG_TABLE) {
+            Ok(table) => table,
+            Err(redb::Error::TableDoesNotExist(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let key = Redb::to_db_key(protocol, network);
+        let res = table.get(key.as_str())?;
+        match res {
+            Some(data) => Ok(serde_json::from_str(data.value())?),
+            None => Ok(Vec::new()),
+        }
+    }
+    // add_reorg prepend reorg to the network reorg log, log is truncated to keep_history entries
+    pub fn add_reorg(
+        &self,
+        reorg: &Reorg,
+        protocol: &Protocol,
+        network: &Network,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        debug!("Redb add_reorg({:?},{:?})", protocol, network);
+        let keep = Redb::keep_history();
+        let mut reorgs = self.get_reorgs(protocol, network)?;
+        reorgs.insert(0, reorg.clone());
+        reorgs.truncate(keep as usize);
         let key = Redb::to_db_key(protocol, network);
         let write_txn = self.db.begin_write()?;
         {
-            let mut table = write_txn.open_table(TABLE)?;
-            let json_value = serde_json::to_string(&blockchain)?;
+            let mut table = write_txn.open_table(REORG_TABLE)?;
+            let json_value = serde_json::to_string(&reorgs)?;
             table.insert(key.as_str(), json_value.as_str())?;
         }
         write_txn.commit()?;
         Ok(())
     }
+
+    // get_alerts return network firing alerts
+    pub fn get_alerts(
+        &self,
+        protocol: &Protocol,
+        network: &Network,
+    ) -> Result<Vec<Alert>, Box<dyn Error + Send + Sync>> {
+        debug!("Redb get_alerts({:?},{:?})", protocol, network);
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(ALERTS_TABLE) {
+            Ok(table) => table,
+            Err(redb::Error::TableDoesNotExist(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let protocol = protocol.to_string();
+        let mut alerts = Vec::new();
+        for entry in table.range((protocol.as_str(), network.as_str(), "")..)? {
+            let (key, value) = entry?;
+            let (p, n, _) = key.value();
+            if p != protocol || n != network {
+                break;
+            }
+            alerts.push(serde_json::from_str(value.value())?);
+        }
+        Ok(alerts)
+    }
+
+    // set_alert insert or replace a firing alert
+    pub fn set_alert(&self, alert: &Alert) -> Result<(), Box<dyn Error + Send + Sync>> {
+        debug!("Redb set_alert({})", alert.id);
+        let protocol = alert.protocol.to_string();
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(ALERTS_TABLE)?;
+            let json_value = serde_json::to_string(alert)?;
+            table.insert(
+                (protocol.as_str(), alert.network.as_str(), alert.id.as_str()),
+                json_value.as_str(),
+            )?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    // remove_alert delete a resolved alert
+    pub fn remove_alert(
+        &self,
+        protocol: &Protocol,
+        network: &Network,
+        id: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        debug!("Redb remove_alert({:?},{:?},{})", protocol, network, id);
+        let protocol = protocol.to_string();
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(ALERTS_TABLE)?;
+            table.remove((protocol.as_str(), network.as_str(), id))?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alerts::{AlertRule, AlertStatus},
+        tests,
+    };
+
+    fn test_db(name: &str) -> Redb {
+        let path =
+            std::env::temp_dir().join(format!("blockhead-{}-{}.redb", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Redb {
+            db: Database::create(path).unwrap(),
+        }
+    }
+    fn test_blockchain(blocks: &[(u64, &str)]) -> blockchain::Blockchain {
+        let mut blockchain = blockchain::Blockchain::new(None);
+        for (height, hash) in blocks {
+            blockchain.add_block(Block {
+                hash: hash.to_string(),
+                height: *height,
+                time: 0,
+                txs: 0,
+            });
+        }
+        blockchain.sort();
+        blockchain
+    }
+
+    #[test]
+    fn db_block_lookup() {
+        tests::setup();
+        let db = test_db("block-lookup");
+        let (protocol, network) = (Protocol::Bitcoin, String::from("mainnet"));
+        let chain = test_blockchain(&[(10, "a10"), (11, "a11"), (12, "a12")]);
+        db.set_blockchain(&chain, &protocol, &network).unwrap();
+        let block = db.get_block(&protocol, &network, 11).unwrap().unwrap();
+        assert_eq!(block.hash, "a11");
+        assert!(db.get_block(&protocol, &network, 9).unwrap().is_none());
+        let block = db.get_block_by_hash(&protocol, &network, "a12").unwrap();
+        assert_eq!(block.unwrap().height, 12);
+        let blocks = db
+            .get_blocks(&protocol, &network, 11, 20, 10, false)
+            .unwrap();
+        assert_eq!(
+            blocks.iter().map(|b| b.height).collect::<Vec<_>>(),
+            vec![11, 12]
+        );
+        let blocks = db
+            .get_blocks(&protocol, &network, 0, u64::MAX, 2, true)
+            .unwrap();
+        assert_eq!(
+            blocks.iter().map(|b| b.height).collect::<Vec<_>>(),
+            vec![12, 11]
+        );
+        assert!(db
+            .get_blockchain(&protocol, &network)
+            .unwrap()
+            .finalized
+            .is_none());
+        // replaced block is not found by its hash anymore
+        let mut chain = test_blockchain(&[(12, "b12"), (13, "b13")]);
+        chain.finalized = Some(blockchain::Head {
+            height: 11,
+            hash: "a11".to_string(),
+        });
+        db.set_blockchain(&chain, &protocol, &network).unwrap();
+        let stored = db.get_blockchain(&protocol, &network).unwrap();
+        assert_eq!(stored.finalized, chain.finalized);
+        assert_eq!(stored.finality_lag(), Some(2));
+        assert!(db
+            .get_block_by_hash(&protocol, &network, "a12")
+            .unwrap()
+            .is_none());
+        let block = db.get_block(&protocol, &network, 12).unwrap().unwrap();
+        assert_eq!(block.hash, "b12");
+        // other networks are not visible
+        assert!(db
+            .get_block(&protocol, &String::from("testnet"), 12)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn db_migrate_legacy_blockchain() {
+        tests::setup();
+        let db = test_db("migrate");
+        let (protocol, network) = (Protocol::Tezos, String::from("ghost-net"));
+        let chain = test_blockchain(&[(10, "a10"), (11, "a11"), (12, "a12")]);
+        let write_txn = db.db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(LEGACY_TABLE).unwrap();
+            table.insert("keep", "1").unwrap();
+            let json_value = serde_json::to_string(&chain).unwrap();
+            table
+                .insert(
+                    Redb::to_db_key(&protocol, &network).as_str(),
+                    json_value.as_str(),
+                )
+                .unwrap();
+        }
+        write_txn.commit().unwrap();
+        assert_eq!(db.schema_version().unwrap(), 1);
+
+        db.migrate().unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        let stored = db.get_blockchain(&protocol, &network).unwrap();
+        assert_eq!(stored.height, 12);
+        assert_eq!(stored.blocks.len(), 3);
+        let block = db.get_block_by_hash(&protocol, &network, "a11").unwrap();
+        assert_eq!(block.unwrap().height, 11);
+        let read_txn = db.db.begin_read().unwrap();
+        assert!(read_txn.open_table(LEGACY_TABLE).is_err());
+        drop(read_txn);
+        // migration run once
+        db.migrate().unwrap();
+        assert_eq!(db.get_blockchain(&protocol, &network).unwrap().height, 12);
+
+        // blocks out of history are pruned
+        let metadata = NetworkMetadata {
+            height: 13,
+            head_hash: "a13".to_string(),
+            last_scrapping_task: 0,
+            safe: None,
+            finalized: None,
+        };
+        let chain = test_blockchain(&[(13, "a13")]);
+        let write_txn = db.db.begin_write().unwrap();
+        Redb::write_blocks(&write_txn, "tezos", &network, &chain.blocks, &metadata, 2).unwrap();
+        write_txn.commit().unwrap();
+        let stored = db.get_blockchain(&protocol, &network).unwrap();
+        assert_eq!(
+            stored.blocks.iter().map(|b| b.height).collect::<Vec<_>>(),
+            vec![13, 12]
+        );
+        assert!(db
+            .get_block_by_hash(&protocol, &network, "a10")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn db_migrate_block_times() {
+        tests::setup();
+        let db = test_db("migrate-block-times");
+        let network = String::from("mainnet");
+        let metadata = NetworkMetadata {
+            height: 11,
+            head_hash: "a11".to_string(),
+            last_scrapping_task: 0,
+            safe: None,
+            finalized: None,
+        };
+        let mut chain = test_blockchain(&[(10, "a10"), (11, "a11")]);
+        chain.blocks[0].time = 1_700_000_012_000;
+        chain.blocks[1].time = 1_700_000_000;
+        let write_txn = db.db.begin_write().unwrap();
+        for protocol in ["polkadot", "bitcoin"] {
+            Redb::write_blocks(&write_txn, protocol, &network, &chain.blocks, &metadata, 10)
+                .unwrap();
+        }
+        write_txn
+            .open_table(SCHEMA_TABLE)
+            .unwrap()
+            .insert("version", 2)
+            .unwrap();
+        write_txn.commit().unwrap();
+
+        db.migrate().unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        let times = |protocol: Protocol| {
+            let stored = db.get_blockchain(&protocol, &network).unwrap();
+            stored.blocks.iter().map(|b| b.time).collect::<Vec<_>>()
+        };
+        // block times in seconds are kept, other protocols never stored milliseconds
+        assert_eq!(
+            times(Protocol::Polkadot),
+            vec![1_700_000_012, 1_700_000_000]
+        );
+        assert_eq!(
+            times(Protocol::Bitcoin),
+            vec![1_700_000_012_000, 1_700_000_000]
+        );
+    }
+
+    #[test]
+    fn db_provider_heads() {
+        tests::setup();
+        let db = test_db("provider-heads");
+        let (protocol, network) = (Protocol::Ethereum, String::from("mainnet"));
+        let head = |height: u64, observed_at: u64| ProviderHead {
+            height,
+            hash: format!("h{}", height),
+            observed_at,
+            latency: Some(10),
+            error: None,
+        };
+        let day = 24 * 3600 * 1000;
+        db.add_provider_heads(
+            &protocol,
+            &network,
+            &[
+                ("node1".to_string(), head(1, day)),
+                ("node2".to_string(), head(2, day)),
+            ],
+        )
+        .unwrap();
+        db.add_provider_heads(
+            &protocol,
+            &network,
+            &[("node1".to_string(), head(3, day + 1))],
+        )
+        .unwrap();
+        let heads = db
+            .get_provider_heads(&protocol, &network, "node1", 0, u64::MAX, 10)
+            .unwrap();
+        assert_eq!(heads, vec![head(1, day), head(3, day + 1)]);
+        let heads = db
+            .get_provider_heads(&protocol, &network, "node1", day + 1, u64::MAX, 10)
+            .unwrap();
+        assert_eq!(heads, vec![head(3, day + 1)]);
+        // heads older than retention are pruned
+        db.add_provider_heads(
+            &protocol,
+            &network,
+            &[("node1".to_string(), head(4, 10 * day))],
+        )
+        .unwrap();
+        let heads = db
+            .get_provider_heads(&protocol, &network, "node1", 0, u64::MAX, 10)
+            .unwrap();
+        assert_eq!(heads, vec![head(4, 10 * day)]);
+        let heads = db
+            .get_provider_heads(&protocol, &network, "node2", 0, u64::MAX, 10)
+            .unwrap();
+        assert_eq!(heads.len(), 1);
+    }
+
+    #[test]
+    fn db_alerts() {
+        tests::setup();
+        let db = test_db("alerts");
+        let (protocol, network) = (Protocol::Bitcoin, String::from("mainnet"));
+        let alert = |id: &str, network: &str| Alert {
+            id: id.to_string(),
+            rule: AlertRule::EndpointDown,
+            status: AlertStatus::Firing,
+            protocol,
+            network: network.to_string(),
+            provider: Some("node1".to_string()),
+            value: 30,
+            threshold: 20,
+            summary: "node1 is down for 30s".to_string(),
+            started_at: 1000,
+            resolved_at: None,
+            notified_at: 1000,
+        };
+        assert!(db.get_alerts(&protocol, &network).unwrap().is_empty());
+        db.set_alert(&alert("endpoint_down:node1", "mainnet"))
+            .unwrap();
+        db.set_alert(&alert("endpoint_down:node2", "mainnet"))
+            .unwrap();
+        db.set_alert(&alert("endpoint_down:node1", "testnet"))
+            .unwrap();
+        let alerts = db.get_alerts(&protocol, &network).unwrap();
+        assert_eq!(
+            alerts.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(),
+            vec!["endpoint_down:node1", "endpoint_down:node2"]
+        );
+        db.remove_alert(&protocol, &network, "endpoint_down:node1")
+            .unwrap();
+        let alerts = db.get_alerts(&protocol, &network).unwrap();
+        assert_eq!(alerts, vec![alert("endpoint_down:node2", "mainnet")]);
+        assert_eq!(
+            db.get_alerts(&protocol, &"testnet".to_string())
+                .unwrap()
+                .len(),
+            1
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use super::ProviderActions;
+use super::{ProviderActions, NO_NEW_BLOCK};
 use crate::commons::blockchain::{self};
 use crate::conf::{self, Endpoint, Network, Protocol};
 use crate::prom::registry::set_blockchain_height_endpoint;
@@ -10,6 +10,9 @@ use crate::requests::rpc::{
 use async_trait::async_trait;
 
 use serde::{Deserialize, Serialize};
+
+// MIN_VERIFICATION_PROGRESS is the progress under which a node which left initial block download is still syncing
+const MIN_VERIFICATION_PROGRESS: f64 = 0.9999;
 #[derive(Serialize, Debug, Clone)]
 pub struct BitcoinNode {
     pub endpoint: conf::Endpoint,
@@ -76,7 +79,7 @@ impl ProviderActions for BitcoinNode {
             trace!("compare {} and {}", prev_head, best_block_hash);
             if prev_head == best_block_hash {
                 debug!("No new block (head: {}), skip task", best_block_hash);
-                return Err("No new block".into());
+                return Err(NO_NEW_BLOCK.into());
             }
         }
 
@@ -112,9 +115,40 @@ impl ProviderActions for BitcoinNode {
         );
         Ok(blockchain)
     }
+    async fn chain_identity(
+        &mut self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.get_blockchain_info().await?.map(|info| info.chain))
+    }
+    async fn sync_status(
+        &mut self,
+    ) -> Result<Option<bool>, Box<dyn std::error::Error + Send + Sync>> {
+        let info = match self.get_blockchain_info().await? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+        Ok(Some(
+            info.initialblockdownload || info.verificationprogress < MIN_VERIFICATION_PROGRESS,
+        ))
+    }
 }
 
 impl BitcoinNode {
+    pub async fn get_blockchain_info(
+        &mut self,
+    ) -> Result<Option<Getblockchaininfo>, Box<dyn std::error::Error + Send + Sync>> {
+        let body = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            id: 1,
+            method: "getblockchaininfo".to_string(),
+            params: vec![],
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<Getblockchaininfo> = client
+            .rpc(&body, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        Ok(res.result)
+    }
     pub async fn get_best_block_hash(
         &mut self,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
@@ -129,7 +163,7 @@ impl BitcoinNode {
         let res: JsonRpcResponse<String> = client
             .rpc(&body, &self.endpoint.protocol, &self.endpoint.network)
             .await?;
-        Ok(res.result.unwrap())
+        Ok(res.into_result()?)
     }
     pub async fn get_block(
         &mut self,
@@ -149,7 +183,7 @@ impl BitcoinNode {
         let res: JsonRpcResponse<Getblock> = client
             .rpc(&body, &self.endpoint.protocol, &self.endpoint.network)
             .await?;
-        Ok(res.result.unwrap())
+        Ok(res.into_result()?)
     }
 }
 
@@ -162,7 +196,10 @@ pub struct Getblockchaininfo {
     pub difficulty: f64,
     pub mediantime: i64,
     pub pruned: bool,
-    pub bip9_softforks: BIP9Softforks,
+    pub initialblockdownload: bool,
+    pub verificationprogress: f64,
+    // removed from getblockchaininfo since bitcoin core 0.19
+    pub bip9_softforks: Option<BIP9Softforks>,
 }
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Getblock {
@@ -208,32 +245,33 @@ pub struct BIP9 {
 mod test {
     extern crate env_logger;
     use super::*;
-    use crate::{conf::Network, tests};
-    use std::env;
+    use crate::{
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+    };
 
     #[tokio::test]
     async fn bitcoin_node_get_best_block_hash() {
         tests::setup();
-        let url = env::var("BITCOIN_NODE_URL").unwrap();
+        let mock = tests::mock_server(SimApi::BitcoinNode);
         let mut bitcoin_node =
-            BitcoinNode::test_new(url.as_str(), Protocol::Bitcoin, String::from("mainnet"));
+            BitcoinNode::test_new(&mock.url(), Protocol::Bitcoin, String::from("mainnet"));
         let res = bitcoin_node.get_best_block_hash().await;
         assert!(
             res.is_ok(),
             "get_best_block_hash returned error: {}, expected OK",
             res.err().unwrap()
         );
-        assert!(res.unwrap().len() > 0)
+        assert_eq!(res.unwrap(), mock.chain().head().hex());
     }
     #[tokio::test]
     async fn bitcoin_node_get_block() {
         tests::setup();
-        let url = env::var("BITCOIN_NODE_URL").unwrap();
+        let mock = tests::mock_server(SimApi::BitcoinNode);
         let mut bitcoin_node =
-            BitcoinNode::test_new(url.as_str(), Protocol::Bitcoin, String::from("mainnet"));
-        let res = bitcoin_node
-            .get_block(&"00000000000000000005bdd33e8c4ac8b3b1754f72416b9cb88ce278ea25f6ce")
-            .await;
+            BitcoinNode::test_new(&mock.url(), Protocol::Bitcoin, String::from("mainnet"));
+        let expected = mock.chain().block(50).unwrap().clone();
+        let res = bitcoin_node.get_block(&expected.hex()).await;
         assert!(
             res.is_ok(),
             "get_block returned error: {}, expected OK",
@@ -241,9 +279,52 @@ mod test {
         );
         let res = res.unwrap();
         assert_eq!(
-            &res.hash, "00000000000000000005bdd33e8c4ac8b3b1754f72416b9cb88ce278ea25f6ce",
+            &res.hash,
+            &expected.hex(),
             "get_block returned wrong hash {}, expected {}",
-            &res.hash, "00000000000000000005bdd33e8c4ac8b3b1754f72416b9cb88ce278ea25f6ce"
+            &res.hash,
+            &expected.hex()
+        );
+        assert_eq!(res.height, 50);
+        assert_eq!(res.previousblockhash, expected.parent_hex());
+        assert_eq!(res.tx.len() as u64, expected.txs);
+    }
+    #[tokio::test]
+    async fn bitcoin_node_parse_top_blocks_after_reorg() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::BitcoinNode);
+        let mut bitcoin_node =
+            BitcoinNode::test_new(&mock.url(), Protocol::Bitcoin, String::from("mainnet"));
+        let blockchain = bitcoin_node.parse_top_blocks(5, None).await.unwrap();
+        assert_eq!(blockchain.height, MOCK_HEIGHT);
+        let previous_head = blockchain.blocks[0].hash.clone();
+        let res = bitcoin_node
+            .parse_top_blocks(5, Some(previous_head.clone()))
+            .await;
+        assert_eq!(res.unwrap_err().to_string(), NO_NEW_BLOCK);
+
+        // a reorg replace head at the same height
+        mock.chain().reorg(2);
+        let blockchain = bitcoin_node
+            .parse_top_blocks(5, Some(previous_head.clone()))
+            .await
+            .unwrap();
+        assert_eq!(blockchain.height, MOCK_HEIGHT);
+        assert_ne!(blockchain.blocks[0].hash, previous_head);
+        assert_eq!(blockchain.blocks[0].hash, mock.chain().head().hex());
+    }
+    #[tokio::test]
+    async fn bitcoin_node_sync_status() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::BitcoinNode);
+        let mut bitcoin_node =
+            BitcoinNode::test_new(&mock.url(), Protocol::Bitcoin, String::from("mainnet"));
+        assert_eq!(bitcoin_node.sync_status().await.unwrap(), Some(false));
+        assert_eq!(
+            bitcoin_node.chain_identity().await.unwrap(),
+            Some("main".to_string())
         );
+        mock.behavior().syncing = true;
+        assert_eq!(bitcoin_node.sync_status().await.unwrap(), Some(true));
     }
 }
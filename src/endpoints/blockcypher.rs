@@ -9,7 +9,7 @@ use crate::{
     requests::client::ReqwestClient,
 };
 
-use super::ProviderActions;
+use super::{ProviderActions, NO_NEW_BLOCK};
 
 #[derive(Debug, Clone)]
 pub struct Blockcypher {
@@ -38,7 +38,7 @@ impl ProviderActions for Blockcypher {
                     "No new block (head: {} block with hash {}), skip task",
                     chain_state.height, chain_state.hash
                 );
-                return Err("No new block".into());
+                return Err(NO_NEW_BLOCK.into());
             }
         }
         let height = chain_state.height;
@@ -169,29 +169,31 @@ pub struct BlockResponse {
 // log all info and print to stdout
 mod tests {
     extern crate env_logger;
-    use std::env;
 
     use super::*;
-    use crate::tests;
+    use crate::{
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+    };
 
     #[tokio::test]
     async fn blockcypherget_chain_height() {
         tests::setup();
-        let url = env::var("BLOCKCYPHER_URL").unwrap();
+        let mock = tests::mock_server(SimApi::Blockcypher);
         let mut blockcypher =
-            Blockcypher::test_new(&url, Protocol::Bitcoin, String::from("mainnet"));
+            Blockcypher::test_new(&mock.url(), Protocol::Bitcoin, String::from("mainnet"));
         let chain_state = blockcypher.get_chain_height().await.unwrap();
-        assert!(chain_state.height > 0);
+        assert_eq!(chain_state.height, MOCK_HEIGHT as u32);
     }
 
     #[tokio::test]
     async fn blockcypher_get_blocks_from_height() {
         tests::setup();
         let n_block = 5;
-        let height = 100;
-        let url = env::var("BLOCKCYPHER_URL").unwrap();
+        let height = 50;
+        let mock = tests::mock_server(SimApi::Blockcypher);
         let mut blockcypher =
-            Blockcypher::test_new(&url, Protocol::Bitcoin, String::from("mainnet"));
+            Blockcypher::test_new(&mock.url(), Protocol::Bitcoin, String::from("mainnet"));
         let res = blockcypher
             .get_blocks_from_height(height, n_block)
             .await
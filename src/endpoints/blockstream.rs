@@ -8,7 +8,7 @@ use crate::{
     requests::client::ReqwestClient,
 };
 
-use super::ProviderActions;
+use super::{ProviderActions, NO_NEW_BLOCK};
 
 #[derive(Debug, Clone)]
 pub struct Blockstream {
@@ -38,7 +38,7 @@ impl ProviderActions for Blockstream {
                     "No new block (head: {} block with hash {}), skip task",
                     tip.height, tip.id
                 );
-                return Err("No new block".into());
+                return Err(NO_NEW_BLOCK.into());
             }
         }
 
@@ -88,6 +88,12 @@ impl Blockstream {
         };
         Blockstream { endpoint }
     }
+    #[cfg(test)]
+    pub fn test_new(url: &str, proto: Protocol, net: conf::Network) -> Self {
+        Blockstream {
+            endpoint: Endpoint::test_new(url, proto, net, None, None),
+        }
+    }
     async fn get_blocks_from_height(
         &mut self,
         height: u64,
@@ -141,3 +147,28 @@ pub struct Block {
     pub bits: u64,
     pub difficulty: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate env_logger;
+
+    use super::*;
+    use crate::{
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+        utils,
+    };
+
+    #[tokio::test]
+    async fn blockstream_parse_top_blocks() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::Blockstream);
+        let mut blockstream =
+            Blockstream::test_new(&mock.url(), Protocol::Bitcoin, String::from("mainnet"));
+        let blockchain = blockstream.parse_top_blocks(5, None).await.unwrap();
+        assert_eq!(blockchain.blocks.len(), 5);
+        assert_eq!(blockchain.height, MOCK_HEIGHT);
+        assert_eq!(blockchain.blocks[0].hash, mock.chain().head().hex());
+        utils::assert_blockchain(blockchain);
+    }
+}
@@ -0,0 +1,242 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+use super::cosmos_node::parse_time;
+use super::{ProviderActions, NO_NEW_BLOCK};
+use crate::commons::blockchain;
+
+use crate::conf::{self, Endpoint, Network, Protocol};
+use crate::prom::registry::set_blockchain_height_endpoint;
+use crate::requests::client::ReqwestClient;
+use crate::utils::deserialize_from_str_to_u64;
+
+/**
+ * CosmosLcd parse blocks from Cosmos SDK LCD (REST) api
+ * LCD encode block hashes in base64, they are converted to the uppercase hex used by CometBFT RPC
+ */
+#[derive(Serialize, Debug, Clone)]
+pub struct CosmosLcd {
+    pub endpoint: conf::Endpoint,
+}
+#[async_trait]
+impl ProviderActions for CosmosLcd {
+    async fn parse_top_blocks(
+        &mut self,
+        n_block: u32,
+        previous_head: Option<String>,
+    ) -> Result<blockchain::Blockchain, Box<dyn std::error::Error + Send + Sync>> {
+        trace!(
+            "parse_top_blocks: n_block: {} previous_head: {:?}",
+            n_block,
+            previous_head
+        );
+        if !self.endpoint.reqwest.available() {
+            return Err("Endpoint is not available".into());
+        }
+        let previous_head: String = previous_head.unwrap_or_default();
+        let head = self.get_block("latest").await?;
+        let head_hash = hex_hash(&head.block_id.hash)?;
+        if previous_head == head_hash {
+            debug!(
+                "No new block (head: {} block with hash {}), skip task",
+                head.block.header.height, head_hash
+            );
+            return Err(NO_NEW_BLOCK.into());
+        }
+        let mut blockchain: blockchain::Blockchain = blockchain::Blockchain::new(None);
+        let head_height = head.block.header.height;
+        let mut block = head;
+        for i in 0..(n_block as u64).min(head_height) {
+            if i > 0 {
+                block = self.get_block(&(head_height - i).to_string()).await?;
+            }
+            let hash = hex_hash(&block.block_id.hash)?;
+            if hash == previous_head {
+                debug!("Previous head found, stop parsing blocks");
+                break;
+            }
+            blockchain.add_block(blockchain::Block {
+                hash,
+                height: block.block.header.height,
+                time: parse_time(&block.block.header.time)?,
+                txs: block.block.data.txs.len() as u64,
+            });
+        }
+        blockchain.sort();
+        // tendermint consensus give instant finality, committed head is final
+        blockchain.finalized = blockchain.blocks.first().map(|b| blockchain::Head {
+            height: b.height,
+            hash: b.hash.clone(),
+        });
+        set_blockchain_height_endpoint(
+            &self.endpoint.url,
+            &self.endpoint.reqwest.config.alias,
+            &self.endpoint.protocol,
+            &self.endpoint.network,
+            blockchain.height,
+        );
+        Ok(blockchain)
+    }
+    async fn chain_identity(
+        &mut self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/cosmos/base/tendermint/v1beta1/node_info",
+            self.endpoint.url
+        );
+        let client = &mut self.endpoint.reqwest;
+        let res: LcdNodeInfo = client
+            .run_request(
+                reqwest::Method::GET,
+                None,
+                &url,
+                &self.endpoint.protocol,
+                &self.endpoint.network,
+            )
+            .await?;
+        Ok(Some(res.default_node_info.network))
+    }
+    async fn sync_status(
+        &mut self,
+    ) -> Result<Option<bool>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Some(self.get_syncing().await?))
+    }
+}
+
+impl CosmosLcd {
+    pub fn new(options: conf::EndpointOptions, protocol: Protocol, network: Network) -> CosmosLcd {
+        let endpoint = Endpoint {
+            url: options.url.clone().unwrap(),
+            reqwest: ReqwestClient::new(options),
+            protocol,
+            network,
+            last_request: 0,
+        };
+        CosmosLcd { endpoint }
+    }
+    #[cfg(test)]
+    pub fn test_new(url: &str, proto: Protocol, net: crate::conf::Network) -> Self {
+        CosmosLcd {
+            endpoint: conf::Endpoint::test_new(url, proto, net, None, None),
+        }
+    }
+
+    async fn get_syncing(&mut self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/cosmos/base/tendermint/v1beta1/syncing",
+            self.endpoint.url
+        );
+        let client = &mut self.endpoint.reqwest;
+        let res: LcdSyncing = client
+            .run_request(
+                reqwest::Method::GET,
+                None,
+                &url,
+                &self.endpoint.protocol,
+                &self.endpoint.network,
+            )
+            .await?;
+        Ok(res.syncing)
+    }
+
+    // get_block return block at height, "latest" for head
+    async fn get_block(
+        &mut self,
+        height: &str,
+    ) -> Result<LcdBlockResult, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "{}/cosmos/base/tendermint/v1beta1/blocks/{}",
+            self.endpoint.url, height
+        );
+        let client = &mut self.endpoint.reqwest;
+        let res: LcdBlockResult = client
+            .run_request(
+                reqwest::Method::GET,
+                None,
+                &url,
+                &self.endpoint.protocol,
+                &self.endpoint.network,
+            )
+            .await?;
+        Ok(res)
+    }
+}
+
+fn hex_hash(hash: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(hex::encode_upper(STANDARD.decode(hash)?))
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LcdSyncing {
+    pub syncing: bool,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LcdNodeInfo {
+    pub default_node_info: LcdDefaultNodeInfo,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LcdDefaultNodeInfo {
+    pub network: String,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LcdBlockId {
+    pub hash: String,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LcdHeader {
+    #[serde(deserialize_with = "deserialize_from_str_to_u64")]
+    pub height: u64,
+    pub time: String,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LcdData {
+    #[serde(default)]
+    pub txs: Vec<String>,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LcdBlock {
+    pub header: LcdHeader,
+    pub data: LcdData,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LcdBlockResult {
+    pub block_id: LcdBlockId,
+    pub block: LcdBlock,
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate env_logger;
+
+    use super::*;
+    use crate::sim::SimApi;
+    use crate::tests::{self, MOCK_HEIGHT};
+    use crate::utils::assert_blockchain;
+
+    #[test]
+    fn cosmos_lcd_hex_hash() {
+        assert_eq!(hex_hash("3q2+7w==").unwrap(), "DEADBEEF");
+        assert!(hex_hash("not base64!").is_err());
+    }
+
+    #[tokio::test]
+    async fn cosmos_lcd_parse_top_blocks() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::CosmosLcd);
+        let mut cosmos_lcd =
+            CosmosLcd::test_new(&mock.url(), Protocol::Cosmos, String::from("cosmoshub-4"));
+        let blockchain = cosmos_lcd.parse_top_blocks(5, None).await;
+        assert!(blockchain.is_ok());
+        let blockchain = blockchain.unwrap();
+        assert_eq!(blockchain.blocks.len(), 5);
+        assert_eq!(blockchain.height, MOCK_HEIGHT);
+        // lcd base64 hashes are converted to cometbft hex
+        assert_eq!(
+            blockchain.blocks[0].hash,
+            mock.chain().head().hex().to_uppercase()
+        );
+        assert_blockchain(blockchain);
+        assert_eq!(cosmos_lcd.sync_status().await.unwrap(), Some(false));
+    }
+}
@@ -0,0 +1,270 @@
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use super::{ProviderActions, NO_NEW_BLOCK};
+use crate::commons::blockchain;
+
+use crate::conf::{self, Endpoint, Network, Protocol};
+use crate::prom::registry::set_blockchain_height_endpoint;
+use crate::requests::client::ReqwestClient;
+use crate::utils::deserialize_from_str_to_u64;
+
+// MAX_BLOCKCHAIN_METAS is the maximum number of block metas returned by /blockchain
+const MAX_BLOCKCHAIN_METAS: u64 = 20;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CosmosNode {
+    pub endpoint: conf::Endpoint,
+}
+#[async_trait]
+impl ProviderActions for CosmosNode {
+    async fn parse_top_blocks(
+        &mut self,
+        n_block: u32,
+        previous_head: Option<String>,
+    ) -> Result<blockchain::Blockchain, Box<dyn std::error::Error + Send + Sync>> {
+        trace!(
+            "parse_top_blocks: n_block: {} previous_head: {:?}",
+            n_block,
+            previous_head
+        );
+        if !self.endpoint.reqwest.available() {
+            return Err("Endpoint is not available".into());
+        }
+        let status = self.get_status().await?;
+        if let Some(previous_head) = previous_head {
+            if previous_head == status.sync_info.latest_block_hash {
+                debug!(
+                    "No new block (head: {} block with hash {}), skip task",
+                    status.sync_info.latest_block_height, status.sync_info.latest_block_hash
+                );
+                return Err(NO_NEW_BLOCK.into());
+            }
+        }
+        let head = self.get_block(status.sync_info.latest_block_height).await?;
+        let mut blockchain: blockchain::Blockchain = blockchain::Blockchain::new(None);
+        blockchain.add_block(blockchain::Block {
+            hash: head.block_id.hash,
+            height: head.block.header.height,
+            time: parse_time(&head.block.header.time)?,
+            txs: head.block.data.txs.unwrap_or_default().len() as u64,
+        });
+        // lowest is excluded, the head block is already in blockchain
+        let lowest = head.block.header.height.saturating_sub(n_block as u64);
+        let mut max_height = head.block.header.height.saturating_sub(1);
+        while max_height > lowest {
+            let min_height = max_height
+                .saturating_sub(MAX_BLOCKCHAIN_METAS - 1)
+                .max(lowest + 1);
+            let metas = self.get_blockchain(min_height, max_height).await?;
+            if metas.is_empty() {
+                return Err(
+                    format!("No block metas between {} and {}", min_height, max_height).into(),
+                );
+            }
+            for meta in metas {
+                blockchain.add_block(blockchain::Block {
+                    hash: meta.block_id.hash,
+                    height: meta.header.height,
+                    time: parse_time(&meta.header.time)?,
+                    txs: meta.num_txs,
+                });
+            }
+            max_height = min_height - 1;
+        }
+        blockchain.sort();
+        // tendermint consensus give instant finality, committed head is final
+        blockchain.finalized = blockchain.blocks.first().map(|b| blockchain::Head {
+            height: b.height,
+            hash: b.hash.clone(),
+        });
+        set_blockchain_height_endpoint(
+            &self.endpoint.url,
+            &self.endpoint.reqwest.config.alias,
+            &self.endpoint.protocol,
+            &self.endpoint.network,
+            blockchain.height,
+        );
+        Ok(blockchain)
+    }
+    async fn chain_identity(
+        &mut self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let status = self.get_status().await?;
+        Ok(Some(status.node_info.network))
+    }
+    async fn sync_status(
+        &mut self,
+    ) -> Result<Option<bool>, Box<dyn std::error::Error + Send + Sync>> {
+        let status = self.get_status().await?;
+        Ok(Some(status.sync_info.catching_up))
+    }
+}
+
+impl CosmosNode {
+    pub fn new(options: conf::EndpointOptions, protocol: Protocol, network: Network) -> CosmosNode {
+        let endpoint = Endpoint {
+            url: options.url.clone().unwrap(),
+            reqwest: ReqwestClient::new(options),
+            protocol,
+            network,
+            last_request: 0,
+        };
+        CosmosNode { endpoint }
+    }
+    #[cfg(test)]
+    pub fn test_new(url: &str, proto: Protocol, net: crate::conf::Network) -> Self {
+        CosmosNode {
+            endpoint: conf::Endpoint::test_new(url, proto, net, None, None),
+        }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &mut self,
+        path: &str,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}{}", self.endpoint.url, path);
+        let client = &mut self.endpoint.reqwest;
+        let res: CometResponse<T> = client
+            .run_request(
+                reqwest::Method::GET,
+                None,
+                &url,
+                &self.endpoint.protocol,
+                &self.endpoint.network,
+            )
+            .await?;
+        Ok(res.result)
+    }
+
+    async fn get_status(
+        &mut self,
+    ) -> Result<CometStatus, Box<dyn std::error::Error + Send + Sync>> {
+        let status: CometStatus = self.get("/status").await?;
+        trace!("status: {:?}", status);
+        Ok(status)
+    }
+
+    async fn get_block(
+        &mut self,
+        height: u64,
+    ) -> Result<CometBlockResult, Box<dyn std::error::Error + Send + Sync>> {
+        self.get(&format!("/block?height={}", height)).await
+    }
+
+    // get_blockchain return block metas between min_height and max_height (at most 20)
+    async fn get_blockchain(
+        &mut self,
+        min_height: u64,
+        max_height: u64,
+    ) -> Result<Vec<CometBlockMeta>, Box<dyn std::error::Error + Send + Sync>> {
+        let res: CometBlockchain = self
+            .get(&format!(
+                "/blockchain?minHeight={}&maxHeight={}",
+                min_height, max_height
+            ))
+            .await?;
+        Ok(res.block_metas)
+    }
+}
+
+// parse_time convert CometBFT RFC3339 (nanoseconds) time to unix timestamp
+pub fn parse_time(time: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(DateTime::parse_from_rfc3339(time)?.timestamp() as u64)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometResponse<T> {
+    pub result: T,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometStatus {
+    pub node_info: CometNodeInfo,
+    pub sync_info: CometSyncInfo,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometNodeInfo {
+    pub network: String,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometSyncInfo {
+    pub latest_block_hash: String,
+    #[serde(deserialize_with = "deserialize_from_str_to_u64")]
+    pub latest_block_height: u64,
+    pub catching_up: bool,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometBlockId {
+    pub hash: String,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometHeader {
+    #[serde(deserialize_with = "deserialize_from_str_to_u64")]
+    pub height: u64,
+    pub time: String,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometData {
+    pub txs: Option<Vec<String>>,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometBlock {
+    pub header: CometHeader,
+    pub data: CometData,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometBlockResult {
+    pub block_id: CometBlockId,
+    pub block: CometBlock,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometBlockMeta {
+    pub block_id: CometBlockId,
+    pub header: CometHeader,
+    #[serde(deserialize_with = "deserialize_from_str_to_u64")]
+    pub num_txs: u64,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CometBlockchain {
+    pub block_metas: Vec<CometBlockMeta>,
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate env_logger;
+
+    use super::*;
+    use crate::sim::SimApi;
+    use crate::tests::{self, MOCK_HEIGHT};
+    use crate::utils::assert_blockchain;
+
+    #[test]
+    fn cosmos_parse_time() {
+        assert_eq!(
+            parse_time("2023-05-10T12:00:00.123456789Z").unwrap(),
+            1683720000
+        );
+        assert!(parse_time("yesterday").is_err());
+    }
+
+    #[tokio::test]
+    async fn cosmos_parse_top_blocks() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::CosmosNode);
+        let mut cosmos_node =
+            CosmosNode::test_new(&mock.url(), Protocol::Cosmos, String::from("cosmoshub-4"));
+        let blockchain = cosmos_node.parse_top_blocks(30, None).await;
+        assert!(blockchain.is_ok());
+        let blockchain = blockchain.unwrap();
+        assert_eq!(blockchain.blocks.len(), 30);
+        assert_eq!(blockchain.height, MOCK_HEIGHT);
+        assert_eq!(
+            blockchain.blocks[0].hash,
+            mock.chain().head().hex().to_uppercase()
+        );
+        assert_blockchain(blockchain);
+        let chain_id = cosmos_node.chain_identity().await.unwrap();
+        assert_eq!(chain_id, Some(String::from("cosmoshub-4")));
+    }
+}
@@ -1,4 +1,4 @@
-use super::ProviderActions;
+use super::{is_syncing_result, ProviderActions, NO_NEW_BLOCK};
 use crate::commons::blockchain::{self};
 use crate::conf::{self, Endpoint, EndpointOptions, Network, Protocol};
 use crate::prom::registry::set_blockchain_height_endpoint;
@@ -37,7 +37,7 @@ impl ProviderActions for EthereumNode {
                     "No new block (head: {} block with hash {}), skip task",
                     head.number, head.hash
                 );
-                return Err("No new block".into());
+                return Err(NO_NEW_BLOCK.into());
             }
         }
 
@@ -57,6 +57,13 @@ impl ProviderActions for EthereumNode {
             });
         }
         blockchain.sort();
+        match self.get_finality_heads().await {
+            Ok((safe, finalized)) => {
+                blockchain.safe = safe;
+                blockchain.finalized = finalized;
+            }
+            Err(e) => debug!("Finality heads not available: {}", e),
+        }
 
         set_blockchain_height_endpoint(
             &self.endpoint.url,
@@ -67,6 +74,26 @@ impl ProviderActions for EthereumNode {
         );
         Ok(blockchain)
     }
+    async fn chain_identity(
+        &mut self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Some(self.get_chain_id().await?.to_string()))
+    }
+    async fn sync_status(
+        &mut self,
+    ) -> Result<Option<bool>, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "eth_syncing".to_string(),
+            params: vec![],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<serde_json::Value> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        Ok(res.result.as_ref().map(is_syncing_result))
+    }
 }
 
 impl EthereumNode {
@@ -86,6 +113,62 @@ impl EthereumNode {
             endpoint: conf::Endpoint::test_new(url, proto, net, None, None),
         }
     }
+    pub async fn get_chain_id(&mut self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "eth_chainId".to_string(),
+            params: vec![],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<String> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        let chain_id = res.into_result()?;
+        Ok(u64::from_str_radix(chain_id.trim_start_matches("0x"), 16)?)
+    }
+    // get_finality_heads return safe and finalized heads, None when the chain doesn't support the tag
+    pub async fn get_finality_heads(
+        &mut self,
+    ) -> Result<
+        (Option<blockchain::Head>, Option<blockchain::Head>),
+        Box<dyn std::error::Error + Send + Sync>,
+    > {
+        let req = JsonRpcReqBody::Batch(
+            FINALITY_TAGS
+                .iter()
+                .enumerate()
+                .map(|(i, tag)| JsonRpcReq {
+                    jsonrpc: JSON_RPC_VER.to_string(),
+                    method: "eth_getBlockByNumber".to_string(),
+                    params: vec![
+                        JsonRpcParams::String(tag.to_string()),
+                        JsonRpcParams::Bool(false),
+                    ],
+                    id: i as u32 + 1,
+                })
+                .collect(),
+        );
+        let client = &mut self.endpoint.reqwest;
+        let rpc_res: Vec<EthTaggedBlockResponse> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        let mut safe = None;
+        let mut finalized = None;
+        // batch responses can be unordered
+        for r in rpc_res {
+            if let Some(error) = r.error {
+                trace!("Tagged block request {:?} error: {}", r.id, error);
+            }
+            let head = r.result.map(EthHeadBlock::into_head);
+            match r.id {
+                Some(1) => safe = head,
+                Some(2) => finalized = head,
+                _ => {}
+            }
+        }
+        Ok((safe, finalized))
+    }
     pub async fn get_block_by_number(
         &mut self,
         block_numbers: Option<&Vec<u64>>,
@@ -130,7 +213,7 @@ impl EthereumNode {
                 let rpc_res: JsonRpcResponse<EthBlock> = client
                     .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
                     .await?;
-                Ok(vec![rpc_res.result.unwrap()])
+                Ok(vec![rpc_res.into_result()?])
             }
             JsonRpcReqBody::Batch(_) => {
                 let rpc_res: Vec<JsonRpcResponse<EthBlock>> = client
@@ -166,6 +249,35 @@ impl EthereumNode {
     }
 }
 
+// FINALITY_TAGS are block tags requested by get_finality_heads, ids follow the slice order
+const FINALITY_TAGS: [&str; 2] = ["safe", "finalized"];
+
+/**
+ * EthTaggedBlockResponse is a json rpc response of a block requested by tag
+ * error is kept as raw json as nodes return an error object for unsupported tags
+ */
+#[derive(Deserialize, Debug, Clone)]
+struct EthTaggedBlockResponse {
+    pub id: Option<u32>,
+    pub result: Option<EthHeadBlock>,
+    #[serde(default)]
+    pub error: Option<serde_json::Value>,
+}
+#[derive(Deserialize, Debug, Clone)]
+struct EthHeadBlock {
+    #[serde(deserialize_with = "deserialize_from_hex_to_u64")]
+    pub number: u64,
+    pub hash: String,
+}
+impl EthHeadBlock {
+    fn into_head(self) -> blockchain::Head {
+        blockchain::Head {
+            height: self.number,
+            hash: self.hash,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct EthBlock {
@@ -206,32 +318,32 @@ mod test {
 
     extern crate env_logger;
     use super::*;
-    use crate::tests;
-    use std::env;
+    use crate::{
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+    };
     #[tokio::test]
     async fn eth_node_get_latest_block_by_number() {
         tests::setup();
-        let mut ethereum_node = EthereumNode::test_new(
-            &env::var("ETHEREUM_NODE_URL").unwrap(),
-            Protocol::Ethereum,
-            String::from("mainnet"),
-        );
+        let mock = tests::mock_server(SimApi::EthereumNode);
+        let mut ethereum_node =
+            EthereumNode::test_new(&mock.url(), Protocol::Ethereum, String::from("mainnet"));
         let block = ethereum_node
             .get_block_by_number(None, false)
             .await
             .unwrap();
         assert_eq!(block.len(), 1);
+        assert_eq!(block[0].number, MOCK_HEIGHT);
+        assert_eq!(block[0].hash, format!("0x{}", mock.chain().head().hex()));
     }
 
     #[tokio::test]
     async fn eth_node_get_multiple_block_by_number() {
         tests::setup();
         let block_len = 5;
-        let mut ethereum_node = EthereumNode::test_new(
-            &env::var("ETHEREUM_NODE_URL").unwrap(),
-            Protocol::Ethereum,
-            String::from("mainnet"),
-        );
+        let mock = tests::mock_server(SimApi::EthereumNode);
+        let mut ethereum_node =
+            EthereumNode::test_new(&mock.url(), Protocol::Ethereum, String::from("mainnet"));
         let block = ethereum_node
             .get_block_by_number(None, false)
             .await
@@ -266,23 +378,66 @@ mod test {
     #[tokio::test]
     async fn eth_node_parse_top_blocks() {
         tests::setup();
-        let mut ethereum_node = EthereumNode::test_new(
-            &env::var("ETHEREUM_NODE_URL").unwrap(),
-            Protocol::Ethereum,
-            String::from("mainnet"),
-        );
+        let mock = tests::mock_server(SimApi::EthereumNode);
+        let mut ethereum_node =
+            EthereumNode::test_new(&mock.url(), Protocol::Ethereum, String::from("mainnet"));
         let res = ethereum_node.parse_top_blocks(5, None).await.unwrap();
         assert_eq!(res.blocks.len(), 5);
+        assert_eq!(res.height, MOCK_HEIGHT);
+        assert_eq!(res.safe.map(|h| h.height), Some(MOCK_HEIGHT - 1));
+        assert_eq!(res.finalized.map(|h| h.height), Some(MOCK_HEIGHT - 2));
+        assert_eq!(
+            ethereum_node.chain_identity().await.unwrap(),
+            Some("1".to_string())
+        );
+        assert_eq!(ethereum_node.sync_status().await.unwrap(), Some(false));
     }
     #[tokio::test]
     async fn eth_node_fork_parse_top_blocks() {
         tests::setup();
-        let mut ethereum_node = EthereumNode::test_new(
-            &env::var("EWF_NODE_URL").unwrap(),
-            Protocol::Ethereum,
-            String::from("mainnet"),
+        // energy web chain is an ethereum fork, its nodes serve the same rpc api
+        let mock = tests::mock_server(SimApi::EthereumNode);
+        mock.behavior().chain_id = Some("0xf6".to_string());
+        let mut ethereum_node =
+            EthereumNode::test_new(&mock.url(), Protocol::Ewf, String::from("mainnet"));
+        let res = ethereum_node.parse_top_blocks(5, None).await.unwrap();
+        assert_eq!(res.blocks.len(), 5);
+        assert_eq!(res.height, MOCK_HEIGHT);
+        assert_eq!(
+            res.blocks[0].hash,
+            format!("0x{}", mock.chain().head().hex())
+        );
+        assert_eq!(
+            ethereum_node.chain_identity().await.unwrap(),
+            Some("246".to_string())
         );
+    }
+    #[tokio::test]
+    async fn eth_node_lagging_parse_top_blocks() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::EthereumNode);
+        mock.behavior().lag = 3;
+        let mut ethereum_node =
+            EthereumNode::test_new(&mock.url(), Protocol::Ethereum, String::from("mainnet"));
         let res = ethereum_node.parse_top_blocks(5, None).await.unwrap();
         assert_eq!(res.blocks.len(), 5);
+        assert_eq!(res.height, MOCK_HEIGHT - 3);
+
+        // provider catch up with new blocks
+        mock.behavior().lag = 0;
+        mock.chain().produce(1);
+        let res = ethereum_node.parse_top_blocks(5, None).await.unwrap();
+        assert_eq!(res.height, MOCK_HEIGHT + 1);
+    }
+    #[tokio::test]
+    async fn eth_node_retry_failed_request() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::EthereumNode);
+        mock.behavior().fail_next = 1;
+        let mut ethereum_node =
+            EthereumNode::test_new(&mock.url(), Protocol::Ethereum, String::from("mainnet"));
+        let res = ethereum_node.parse_top_blocks(5, None).await.unwrap();
+        assert_eq!(res.height, MOCK_HEIGHT);
+        assert!(mock.requests() > 3);
     }
 }
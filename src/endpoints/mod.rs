@@ -3,14 +3,22 @@ use async_trait::async_trait;
 pub mod bitcoin_node;
 pub mod blockcypher;
 pub mod blockstream;
+pub mod cosmos_lcd;
+pub mod cosmos_node;
 pub mod ethereum_node;
 pub mod polkadot_node;
+pub mod solana_node;
 pub mod starknet_node;
 pub mod subscan;
 pub mod tezos_node;
 pub mod tzkt;
 pub mod tzstats;
 
+// NO_NEW_BLOCK is returned by parse_top_blocks when provider head is still previous_head
+pub const NO_NEW_BLOCK: &str = "No new block";
+// CHAIN_MISMATCH prefix errors of endpoints serving another chain than the configured network
+pub const CHAIN_MISMATCH: &str = "Chain identity mismatch";
+
 #[async_trait]
 pub trait ProviderActions: Send {
     // parse_top_blocks return basic task to parse top blocks
@@ -19,4 +27,37 @@ pub trait ProviderActions: Send {
         n_block: u32, // number of block to look ahead
         previous_head: Option<String>,
     ) -> Result<blockchain::Blockchain, Box<dyn std::error::Error + Send + Sync>>;
+    // chain_identity return the chain served by endpoint (chain id, genesis hash...)
+    // None when provider api does not expose it
+    async fn chain_identity(
+        &mut self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(None)
+    }
+    // sync_status return true while node is syncing or stuck, None when provider api does not expose it
+    async fn sync_status(
+        &mut self,
+    ) -> Result<Option<bool>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(None)
+    }
+}
+
+// is_syncing_result parse eth_syncing like results, false when synced or an object with sync progress
+pub fn is_syncing_result(result: &serde_json::Value) -> bool {
+    !matches!(result, serde_json::Value::Bool(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_is_syncing_result() {
+        assert!(!is_syncing_result(&serde_json::json!(false)));
+        assert!(is_syncing_result(&serde_json::json!({
+            "startingBlock": "0x0",
+            "currentBlock": "0x10",
+            "highestBlock": "0x20"
+        })));
+    }
 }
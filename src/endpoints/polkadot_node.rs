@@ -2,7 +2,7 @@ use async_trait::async_trait;
 
 use serde::{Deserialize, Serialize};
 
-use super::ProviderActions;
+use super::{ProviderActions, NO_NEW_BLOCK};
 use crate::commons::blockchain;
 
 use crate::conf::{self, Endpoint, Network, Protocol};
@@ -56,7 +56,7 @@ impl ProviderActions for PolkadotNode {
             return Err("Endpoint is not available".into());
         }
         let previous_head = previous_head.unwrap_or("".to_string());
-        let head_hash = self.get_finalized_head().await?;
+        let head_hash = self.get_best_head().await?;
 
         let mut i = 0;
         let mut prev_hash = head_hash.clone();
@@ -67,7 +67,7 @@ impl ProviderActions for PolkadotNode {
                     "No new block (head: {} block with hash {}), skip task",
                     head_hash, prev_hash
                 );
-                return Err("No new block".into());
+                return Err(NO_NEW_BLOCK.into());
             }
 
             let block_res = self.get_blocks(vec![prev_hash.clone()]).await?;
@@ -78,7 +78,8 @@ impl ProviderActions for PolkadotNode {
                     return Err("Get block return empty vec".into());
                 }
             };
-            let decode_timestamp = get_block_timestamp(block).unwrap_or(0);
+            // timestamp inherent is in milliseconds
+            let decode_timestamp = get_block_timestamp(block).unwrap_or(0) / 1000;
 
             let b = blockchain::Block {
                 hash: prev_hash.clone(),
@@ -91,6 +92,16 @@ impl ProviderActions for PolkadotNode {
             blockchain.add_block(b);
         }
         blockchain.sort();
+        // grandpa finalize blocks behind the best one
+        let finalized_hash = self.get_finalized_head().await?;
+        let finalized_height = match blockchain.blocks.iter().find(|b| b.hash == finalized_hash) {
+            Some(block) => block.height,
+            None => self.get_header(&finalized_hash).await?.number,
+        };
+        blockchain.finalized = Some(blockchain::Head {
+            height: finalized_height,
+            hash: finalized_hash,
+        });
 
         set_blockchain_height_endpoint(
             &self.endpoint.url,
@@ -101,9 +112,76 @@ impl ProviderActions for PolkadotNode {
         );
         Ok(blockchain)
     }
+    async fn chain_identity(
+        &mut self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        // genesis block hash identify substrate chains
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "chain_getBlockHash".to_string(),
+            params: vec![JsonRpcParams::Number(0)],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<String> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        Ok(res.result)
+    }
+    async fn sync_status(
+        &mut self,
+    ) -> Result<Option<bool>, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "system_health".to_string(),
+            params: vec![],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<PolkadotHealth> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        Ok(res.result.map(|health| health.is_syncing))
+    }
 }
 
 impl PolkadotNode {
+    // get_best_head return hash of the best block, it may not be finalized yet
+    async fn get_best_head(&mut self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "chain_getBlockHash".to_string(),
+            params: vec![],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<String> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        match res.result {
+            Some(res) => Ok(res),
+            None => Err("get_best_head return empty hash".into()),
+        }
+    }
+    async fn get_header(
+        &mut self,
+        hash: &str,
+    ) -> Result<PolkadotBlockHeader, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "chain_getHeader".to_string(),
+            params: vec![JsonRpcParams::String(hash.to_string())],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<PolkadotBlockHeader> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        match res.result {
+            Some(res) => Ok(res),
+            None => Err(format!("get_header return empty header for {}", hash).into()),
+        }
+    }
     async fn get_finalized_head(
         &mut self,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
@@ -190,6 +268,11 @@ struct PolkadotBlock {
     extrinsics: Vec<String>,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PolkadotHealth {
+    pub is_syncing: bool,
+}
 #[derive(Deserialize, Serialize, Debug, Clone)]
 struct PolkadotBlockResponse {
     block: PolkadotBlock,
@@ -201,48 +284,51 @@ struct PolkadotBlockResponse {
 mod tests {
     extern crate env_logger;
     use super::*;
-    use crate::tests;
-    use crate::utils;
+    use crate::{
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+        utils,
+    };
     use hex;
 
     #[tokio::test]
     async fn polkadot_node_parse_top_blocks() {
         tests::setup();
-        let mut endpoint = PolkadotNode::test_new(
-            "https://rpc.polkadot.io",
-            Protocol::Polkadot,
-            String::from("mainnet"),
-        );
+        let mock = tests::mock_server(SimApi::PolkadotNode);
+        let mut endpoint =
+            PolkadotNode::test_new(&mock.url(), Protocol::Polkadot, String::from("mainnet"));
         let res = endpoint.parse_top_blocks(10, None).await;
         assert!(res.is_ok());
         let blockchain = res.unwrap();
+        assert_eq!(blockchain.height, MOCK_HEIGHT);
+        // block time is in seconds
+        let head_time = mock.chain().block(MOCK_HEIGHT).unwrap().time;
+        assert_eq!(blockchain.blocks[0].time, head_time);
+        let finalized = blockchain.finalized.clone().unwrap();
+        assert!(finalized.height <= blockchain.height);
         utils::assert_blockchain(blockchain);
     }
 
     #[tokio::test]
     async fn polkadot_node_get_finalized_head() {
         tests::setup();
-        let mut endpoint = PolkadotNode::test_new(
-            "https://rpc.polkadot.io",
-            Protocol::Polkadot,
-            String::from("mainnet"),
-        );
+        let mock = tests::mock_server(SimApi::PolkadotNode);
+        let mut endpoint =
+            PolkadotNode::test_new(&mock.url(), Protocol::Polkadot, String::from("mainnet"));
         let res = endpoint.get_finalized_head().await;
         assert!(res.is_ok());
-        assert!(res.unwrap().starts_with("0x"));
+        let expected = mock.chain().finalized(MOCK_HEIGHT).hex();
+        assert_eq!(res.unwrap(), format!("0x{}", expected));
     }
     #[tokio::test]
     async fn polkadot_node_get_block() {
         tests::setup();
-        let mut endpoint = PolkadotNode::test_new(
-            "https://rpc.polkadot.io",
-            Protocol::Polkadot,
-            String::from("mainnet"),
-        );
+        let mock = tests::mock_server(SimApi::PolkadotNode);
+        let mut endpoint =
+            PolkadotNode::test_new(&mock.url(), Protocol::Polkadot, String::from("mainnet"));
+        let expected = mock.chain().block(50).unwrap().clone();
         let res = endpoint
-            .get_blocks(vec![
-                "0xed74086309b9ac5e152188f5fbb6163a8f5fbb8b44f5b400a27e516386c478b6".to_string(),
-            ])
+            .get_blocks(vec![format!("0x{}", expected.hex())])
             .await;
         assert!(res.is_ok());
         let res = res.unwrap();
@@ -250,7 +336,7 @@ mod tests {
         let block = res[0].clone();
         assert_eq!(
             block.block.header.parent_hash,
-            "0x6a830b3dc9cf8b30100c6074a8c63b84679186646e972d79a7b2d5cf4e921baa"
+            format!("0x{}", expected.parent_hex())
         );
         let extrinsics = block.block.extrinsics[0].clone();
         let extrinsics = &extrinsics[2..];
@@ -258,34 +344,19 @@ mod tests {
         let r = decode_timestamp_extrinsic(&extrinsic_bytes[..]);
         assert!(r.is_some());
         let r = r.unwrap();
-        assert!(r == 1682686764001);
+        assert!(r == expected.time * 1000);
     }
     #[tokio::test]
     async fn polkadot_node_decode_timestamp_extrinsics() {
         tests::setup();
-        let mut endpoint = PolkadotNode::test_new(
-            "https://rpc.polkadot.io",
-            Protocol::Polkadot,
-            String::from("mainnet"),
-        );
-        let block_hashs = vec![
-            "0xf752e2ec759fc921b7fb63f5c4c3798ed608059dc239dfb2f2d41bb50190dd35".to_string(),
-            "0xe8018641a76511fabe2625768315191a4708ec535ee0c58a13ab8ded8b6e9dd9".to_string(),
-            "0x4ed9d382d06717b818ae8a9d24f82842a56efac1d39ae9ab09b1a9fb29fbc229".to_string(),
-            "0xf250c96cfe3a8281d9520a904c6ea77dbee2f4f71cd476116cc515c980e90fb3".to_string(),
-            "0x7363a5134e66b051af633c8d5ce81e61768379df26bca9f90e26674f4efd3a5d".to_string(),
-            "0x8cc9b0c66d1d742f8eb978c1ce51cff96004f291c0d311cc16ed4c48860e05d7".to_string(),
-            "0x721c3890a18ed9d5595aa599e3241a269d2da29d53ac004b172ca262768cb8a6".to_string(),
-        ];
-        let assert_timestamps: Vec<u64> = vec![
-            1682756880000,
-            1682756874000,
-            1682756856000,
-            1682756850001,
-            1682756844000,
-            1676704524000,
-            1664682306006,
-        ];
+        let mock = tests::mock_server(SimApi::PolkadotNode);
+        let mut endpoint =
+            PolkadotNode::test_new(&mock.url(), Protocol::Polkadot, String::from("mainnet"));
+        let blocks = (90..=MOCK_HEIGHT)
+            .map(|h| mock.chain().block(h).unwrap().clone())
+            .collect::<Vec<_>>();
+        let block_hashs = blocks.iter().map(|b| format!("0x{}", b.hex())).collect();
+        let assert_timestamps: Vec<u64> = blocks.iter().map(|b| b.time * 1000).collect();
         let res = endpoint.get_blocks(block_hashs).await;
         assert!(res.is_ok());
         for (i, r) in res.unwrap().iter().enumerate() {
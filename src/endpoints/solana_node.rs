@@ -0,0 +1,360 @@
+use async_trait::async_trait;
+
+use serde::{Deserialize, Serialize};
+
+use super::{ProviderActions, NO_NEW_BLOCK};
+use crate::commons::blockchain;
+
+use crate::conf::{self, Endpoint, Network, Protocol};
+use crate::prom::registry::set_blockchain_height_endpoint;
+use crate::requests::client::ReqwestClient;
+use crate::requests::rpc::{
+    JsonRpcParams, JsonRpcReq, JsonRpcReqBody, JsonRpcResponse, JSON_RPC_VER,
+};
+
+// Solana RPC error codes returned by getBlock when no block has been produced for a slot
+const SLOT_SKIPPED: i64 = -32007;
+const LONG_TERM_STORAGE_SLOT_SKIPPED: i64 = -32009;
+// NODE_UNHEALTHY is returned by getHealth when node is behind the cluster
+const NODE_UNHEALTHY: i64 = -32005;
+// MAX_SLOTS_FACTOR bound the number of slots scanned to find n_block blocks
+const MAX_SLOTS_FACTOR: u64 = 4;
+const COMMITMENT: &str = "confirmed";
+const FINALIZED_COMMITMENT: &str = "finalized";
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SolanaNode {
+    pub endpoint: conf::Endpoint,
+}
+#[async_trait]
+impl ProviderActions for SolanaNode {
+    async fn parse_top_blocks(
+        &mut self,
+        n_block: u32,
+        previous_head: Option<String>,
+    ) -> Result<blockchain::Blockchain, Box<dyn std::error::Error + Send + Sync>> {
+        trace!(
+            "parse_top_blocks: n_block: {} previous_head: {:?}",
+            n_block,
+            previous_head
+        );
+        if !self.endpoint.reqwest.available() {
+            return Err("Endpoint is not available".into());
+        }
+        let head = self.get_slot().await?;
+        let blocks = self.get_top_blocks(head, n_block as u64).await?;
+        if let (Some(previous_head), Some((slot, block))) = (previous_head, blocks.first()) {
+            if previous_head == block.blockhash {
+                debug!(
+                    "No new block (head: {} block with hash {}), skip task",
+                    slot, block.blockhash
+                );
+                return Err(NO_NEW_BLOCK.into());
+            }
+        }
+        let mut blockchain: blockchain::Blockchain = blockchain::Blockchain::new(None);
+        for (slot, block) in blocks {
+            blockchain.add_block(blockchain::Block {
+                hash: block.blockhash,
+                height: slot,
+                time: block.block_time.unwrap_or_default(),
+                txs: block.signatures.len() as u64,
+            });
+        }
+        blockchain.sort();
+        match self.get_finalized_head().await {
+            Ok(finalized) => blockchain.finalized = Some(finalized),
+            Err(e) => debug!("Finalized head not available: {}", e),
+        }
+        set_blockchain_height_endpoint(
+            &self.endpoint.url,
+            &self.endpoint.reqwest.config.alias,
+            &self.endpoint.protocol,
+            &self.endpoint.network,
+            blockchain.height,
+        );
+        Ok(blockchain)
+    }
+    async fn chain_identity(
+        &mut self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "getGenesisHash".to_string(),
+            params: vec![],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<String> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        Ok(res.result)
+    }
+    async fn sync_status(
+        &mut self,
+    ) -> Result<Option<bool>, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "getHealth".to_string(),
+            params: vec![],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<String> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        match res.error {
+            Some(e) if e.code == NODE_UNHEALTHY => Ok(Some(true)),
+            Some(e) => Err(format!("getHealth error: {}", e).into()),
+            None => Ok(Some(false)),
+        }
+    }
+}
+
+impl SolanaNode {
+    pub fn new(options: conf::EndpointOptions, protocol: Protocol, network: Network) -> SolanaNode {
+        let endpoint = Endpoint {
+            url: options.url.clone().unwrap(),
+            reqwest: ReqwestClient::new(options),
+            protocol,
+            network,
+            last_request: 0,
+        };
+        SolanaNode { endpoint }
+    }
+    #[cfg(test)]
+    pub fn test_new(url: &str, proto: Protocol, net: crate::conf::Network) -> Self {
+        SolanaNode {
+            endpoint: conf::Endpoint::test_new(url, proto, net, None, None),
+        }
+    }
+
+    async fn get_slot(&mut self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let body = JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "getSlot".to_string(),
+            params: vec![JsonRpcParams::SerdeValue(serde_json::json!({
+                "commitment": COMMITMENT
+            }))],
+            id: 1,
+        };
+        let req = JsonRpcReqBody::Single(body);
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<u64> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        trace!("slot: {:?}", res);
+        Ok(res.into_result()?)
+    }
+
+    // get_finalized_head return the slot and hash of the latest block rooted by the cluster
+    async fn get_finalized_head(
+        &mut self,
+    ) -> Result<blockchain::Head, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "getLatestBlockhash".to_string(),
+            params: vec![JsonRpcParams::SerdeValue(serde_json::json!({
+                "commitment": FINALIZED_COMMITMENT
+            }))],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<SolanaContextResult<SolanaLatestBlockhash>> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        let res = res.into_result()?;
+        Ok(blockchain::Head {
+            height: res.context.slot,
+            hash: res.value.blockhash,
+        })
+    }
+
+    // get_top_blocks walk slots down from head until n_block produced blocks are found
+    // skipped slots are ignored, blocks are returned from highest to lowest slot
+    async fn get_top_blocks(
+        &mut self,
+        head: u64,
+        n_block: u64,
+    ) -> Result<Vec<(u64, SolanaBlock)>, Box<dyn std::error::Error + Send + Sync>> {
+        let lowest = head.saturating_sub(n_block * MAX_SLOTS_FACTOR);
+        let mut blocks: Vec<(u64, SolanaBlock)> = Vec::new();
+        let mut next = head;
+        while (blocks.len() as u64) < n_block && next > lowest {
+            let count = (n_block - blocks.len() as u64).min(next - lowest);
+            let slots: Vec<u64> = (0..count).map(|i| next - i).collect();
+            next -= count;
+            let found = self.get_blocks(&slots).await?;
+            for (slot, block) in slots.into_iter().zip(found) {
+                if let Some(block) = block {
+                    blocks.push((slot, block));
+                }
+            }
+        }
+        check_parent_slots(&blocks)?;
+        Ok(blocks)
+    }
+
+    // get_blocks return None for skipped slots
+    async fn get_blocks(
+        &mut self,
+        slots: &[u64],
+    ) -> Result<Vec<Option<SolanaBlock>>, Box<dyn std::error::Error + Send + Sync>> {
+        let batch = slots
+            .iter()
+            .enumerate()
+            .map(|(i, slot)| JsonRpcReq {
+                jsonrpc: JSON_RPC_VER.to_string(),
+                method: "getBlock".to_string(),
+                params: vec![
+                    JsonRpcParams::SerdeValue(serde_json::json!(slot)),
+                    JsonRpcParams::SerdeValue(serde_json::json!({
+                        "commitment": COMMITMENT,
+                        "encoding": "json",
+                        "transactionDetails": "signatures",
+                        "maxSupportedTransactionVersion": 0,
+                        "rewards": false
+                    })),
+                ],
+                id: i as u32,
+            })
+            .collect();
+        let req = JsonRpcReqBody::Batch(batch);
+        let client = &mut self.endpoint.reqwest;
+        let mut res: Vec<JsonRpcResponse<SolanaBlock>> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        res.sort_by_key(|r| r.id);
+        if res.len() != slots.len() {
+            return Err(format!(
+                "Batch response contains {} blocks, {} expected",
+                res.len(),
+                slots.len()
+            )
+            .into());
+        }
+        res.into_iter()
+            .map(|r| {
+                trace!("batch block: {:?}", r);
+                match r.error {
+                    Some(e)
+                        if e.code == SLOT_SKIPPED || e.code == LONG_TERM_STORAGE_SLOT_SKIPPED =>
+                    {
+                        Ok(None)
+                    }
+                    Some(e) => {
+                        error!("Error in batch response: {}", e);
+                        Err("Error in batch response".into())
+                    }
+                    None => Ok(r.result),
+                }
+            })
+            .collect()
+    }
+}
+
+// check_parent_slots ensure every block points to the next lower block found,
+// a gap means a slot was reported skipped while a block exists for it
+fn check_parent_slots(
+    blocks: &[(u64, SolanaBlock)],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for w in blocks.windows(2) {
+        let ((slot, block), (parent, _)) = (&w[0], &w[1]);
+        if block.parent_slot != *parent {
+            return Err(format!(
+                "Block at slot {} has parent slot {}, found {}",
+                slot, block.parent_slot, parent
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct SolanaContext {
+    pub slot: u64,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct SolanaContextResult<T> {
+    pub context: SolanaContext,
+    pub value: T,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct SolanaLatestBlockhash {
+    pub blockhash: String,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SolanaBlock {
+    pub blockhash: String,
+    pub previous_blockhash: String,
+    pub parent_slot: u64,
+    pub block_height: Option<u64>,
+    pub block_time: Option<u64>,
+    #[serde(default)]
+    pub signatures: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate env_logger;
+
+    use super::*;
+    use crate::sim::SimApi;
+    use crate::tests::{self, MOCK_HEIGHT};
+    use crate::utils::assert_blockchain;
+
+    fn block(parent_slot: u64) -> SolanaBlock {
+        SolanaBlock {
+            blockhash: String::new(),
+            previous_blockhash: String::new(),
+            parent_slot,
+            block_height: None,
+            block_time: None,
+            signatures: vec![],
+        }
+    }
+
+    #[test]
+    fn solana_check_parent_slots() {
+        // slot 9 skipped
+        let blocks = vec![(10, block(8)), (8, block(7)), (7, block(6))];
+        assert!(check_parent_slots(&blocks).is_ok());
+        // slot 9 reported skipped while block 10 points to it
+        let blocks = vec![(10, block(9)), (8, block(7))];
+        assert!(check_parent_slots(&blocks).is_err());
+    }
+
+    #[tokio::test]
+    async fn solana_get_top_blocks() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::SolanaNode);
+        let mut solana_node =
+            SolanaNode::test_new(&mock.url(), Protocol::Solana, String::from("mainnet"));
+        let head = solana_node.get_slot().await.unwrap();
+        assert_eq!(head, MOCK_HEIGHT);
+        let blocks = solana_node.get_top_blocks(head, 10).await.unwrap();
+        assert_eq!(blocks.len(), 10);
+        assert_eq!(blocks[0].0, head);
+        assert_eq!(
+            blocks[0].1.blockhash,
+            format!("0x{}", mock.chain().head().hex())
+        );
+    }
+
+    #[tokio::test]
+    async fn solana_parse_top_blocks() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::SolanaNode);
+        let mut solana_node =
+            SolanaNode::test_new(&mock.url(), Protocol::Solana, String::from("mainnet"));
+        let blockchain = solana_node.parse_top_blocks(5, None).await.unwrap();
+        assert_eq!(blockchain.blocks.len(), 5);
+        assert_eq!(
+            blockchain.finalized.as_ref().unwrap().height,
+            MOCK_HEIGHT - 2
+        );
+        assert_blockchain(blockchain);
+    }
+}
@@ -1,8 +1,10 @@
+use std::time::{Duration, Instant};
+
 use async_trait::async_trait;
 
 use serde::{Deserialize, Serialize};
 
-use super::ProviderActions;
+use super::{is_syncing_result, ProviderActions, NO_NEW_BLOCK};
 use crate::commons::blockchain;
 
 use crate::conf::{self, Endpoint, Network, Protocol};
@@ -12,9 +14,22 @@ use crate::requests::rpc::{
     JsonRpcParams, JsonRpcReq, JsonRpcReqBody, JsonRpcResponse, JSON_RPC_VER,
 };
 
+// ACCEPTED_ON_L1 is the status of blocks whose state update is settled on Ethereum
+const ACCEPTED_ON_L1: &str = "ACCEPTED_ON_L1";
+// FINALITY_REFRESH is the minimum delay between two searches of the finalized head
+const FINALITY_REFRESH: Duration = Duration::from_secs(60);
+
+/**
+ * StarknetNode parse blocks accepted on L2, the finalized head is the highest block accepted on L1
+ * L1 acceptance lag hours behind the head, it is searched by bisection from the last known finalized head
+ */
 #[derive(Serialize, Debug, Clone)]
 pub struct StarknetNode {
     pub endpoint: conf::Endpoint,
+    #[serde(skip)]
+    finalized: Option<blockchain::Head>,
+    #[serde(skip)]
+    finalized_checked_at: Option<Instant>,
 }
 #[async_trait]
 impl ProviderActions for StarknetNode {
@@ -38,7 +53,7 @@ impl ProviderActions for StarknetNode {
                     "No new block (head: {} block with hash {}), skip task",
                     head.block_number, head.block_hash
                 );
-                return Err("No new block".into());
+                return Err(NO_NEW_BLOCK.into());
             }
         }
         let mut blockchain: blockchain::Blockchain = blockchain::Blockchain::new(None);
@@ -48,6 +63,14 @@ impl ProviderActions for StarknetNode {
             block_numbers.push(head.block_number - i as u64);
         }
         let blocks = self.get_blocks_by_number(&block_numbers).await?;
+        let accepted_on_l1 = blocks
+            .iter()
+            .filter(|b| b.status == ACCEPTED_ON_L1)
+            .max_by_key(|b| b.block_number)
+            .map(|b| blockchain::Head {
+                height: b.block_number,
+                hash: b.block_hash.clone(),
+            });
         for block in blocks {
             blockchain.add_block(blockchain::Block {
                 hash: block.block_hash,
@@ -57,6 +80,16 @@ impl ProviderActions for StarknetNode {
             });
         }
         blockchain.sort();
+        blockchain.finalized = match accepted_on_l1 {
+            Some(head) => Some(head),
+            None => match self.get_finalized_head(head.block_number).await {
+                Ok(head) => head,
+                Err(e) => {
+                    debug!("Error while searching finalized head: {}", e);
+                    self.finalized.clone()
+                }
+            },
+        };
         set_blockchain_height_endpoint(
             &self.endpoint.url,
             &self.endpoint.reqwest.config.alias,
@@ -66,6 +99,36 @@ impl ProviderActions for StarknetNode {
         );
         Ok(blockchain)
     }
+    async fn chain_identity(
+        &mut self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "starknet_chainId".to_string(),
+            params: vec![],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<String> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        Ok(res.result)
+    }
+    async fn sync_status(
+        &mut self,
+    ) -> Result<Option<bool>, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "starknet_syncing".to_string(),
+            params: vec![],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<serde_json::Value> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        Ok(res.result.as_ref().map(is_syncing_result))
+    }
 }
 
 impl StarknetNode {
@@ -81,12 +144,72 @@ impl StarknetNode {
             network,
             last_request: 0,
         };
-        StarknetNode { endpoint }
+        StarknetNode {
+            endpoint,
+            finalized: None,
+            finalized_checked_at: None,
+        }
     }
     #[cfg(test)]
     pub fn test_new(url: &str, proto: Protocol, net: crate::conf::Network) -> Self {
         StarknetNode {
             endpoint: conf::Endpoint::test_new(url, proto, net, None, None),
+            finalized: None,
+            finalized_checked_at: None,
+        }
+    }
+
+    // get_finalized_head return the highest block accepted on L1 below head, the result is cached FINALITY_REFRESH
+    // None when no block above the last known finalized head is accepted on L1
+    async fn get_finalized_head(
+        &mut self,
+        head: u64,
+    ) -> Result<Option<blockchain::Head>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(checked_at) = self.finalized_checked_at {
+            if checked_at.elapsed() < FINALITY_REFRESH {
+                return Ok(self.finalized.clone());
+            }
+        }
+        let mut finalized = self.finalized.clone();
+        // low is accepted on L1, high is the highest block which may be accepted on L1
+        let mut low = finalized.as_ref().map(|f| f.height).unwrap_or(0);
+        let mut high = head;
+        while low < high {
+            let mid = low + (high - low + 1) / 2;
+            let block = self.get_block_status(mid).await?;
+            if block.status == ACCEPTED_ON_L1 {
+                low = mid;
+                finalized = Some(blockchain::Head {
+                    height: block.block_number,
+                    hash: block.block_hash,
+                });
+            } else {
+                high = mid - 1;
+            }
+        }
+        self.finalized = finalized;
+        self.finalized_checked_at = Some(Instant::now());
+        Ok(self.finalized.clone())
+    }
+    async fn get_block_status(
+        &mut self,
+        block_number: u64,
+    ) -> Result<StarknetBlockStatus, Box<dyn std::error::Error + Send + Sync>> {
+        let req = JsonRpcReqBody::Single(JsonRpcReq {
+            jsonrpc: JSON_RPC_VER.to_string(),
+            method: "starknet_getBlockWithTxHashes".to_string(),
+            params: vec![JsonRpcParams::SerdeValue(serde_json::json!({
+                "block_number": block_number
+            }))],
+            id: 1,
+        });
+        let client = &mut self.endpoint.reqwest;
+        let res: JsonRpcResponse<StarknetBlockStatus> = client
+            .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
+            .await?;
+        match res.result {
+            Some(block) => Ok(block),
+            None => Err(format!("Block {} not found", block_number).into()),
         }
     }
 
@@ -105,7 +228,7 @@ impl StarknetNode {
             .rpc(&req, &self.endpoint.protocol, &self.endpoint.network)
             .await?;
         trace!("head: {:?}", res);
-        Ok(res.result.unwrap())
+        Ok(res.into_result()?)
     }
 
     async fn get_blocks_by_number(
@@ -167,6 +290,12 @@ struct StarknetBlock {
     pub transactions: Vec<serde_json::Value>,
 }
 #[derive(Deserialize, Serialize, Debug, Clone)]
+struct StarknetBlockStatus {
+    pub status: String,
+    pub block_hash: String,
+    pub block_number: u64,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct StarnetBlockHashAndNumber {
     pub block_hash: String,
     pub block_number: u64,
@@ -176,33 +305,31 @@ struct StarnetBlockHashAndNumber {
 
 mod tests {
     extern crate env_logger;
-    use std::env;
 
     use super::*;
-    use crate::tests;
+    use crate::{
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+    };
 
     #[tokio::test]
     async fn starknet_get_head() {
         tests::setup();
-        let mut starknet_node = StarknetNode::test_new(
-            &env::var("STARKNET_NODE_URL").unwrap(),
-            Protocol::Starknet,
-            String::from("mainnet"),
-        );
+        let mock = tests::mock_server(SimApi::StarknetNode);
+        let mut starknet_node =
+            StarknetNode::test_new(&mock.url(), Protocol::Starknet, String::from("mainnet"));
         let head = starknet_node.get_head().await;
         assert!(head.is_ok());
         let head = head.unwrap();
-        assert!(head.block_number > 0);
+        assert_eq!(head.block_number, MOCK_HEIGHT);
     }
 
     #[tokio::test]
     async fn starknet_get_blocks_by_number() {
         tests::setup();
-        let mut starknet_node = StarknetNode::test_new(
-            &env::var("STARKNET_NODE_URL").unwrap(),
-            Protocol::Starknet,
-            String::from("mainnet"),
-        );
+        let mock = tests::mock_server(SimApi::StarknetNode);
+        let mut starknet_node =
+            StarknetNode::test_new(&mock.url(), Protocol::Starknet, String::from("mainnet"));
         let head = starknet_node.get_head().await.unwrap();
         let mut block_numbers = Vec::new();
         for i in 0..10 {
@@ -213,4 +340,33 @@ mod tests {
         let blocks = blocks.unwrap();
         assert_eq!(blocks.len(), 10);
     }
+
+    #[tokio::test]
+    async fn starknet_search_finalized_head() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::StarknetNode);
+        // finalized head is below parsed blocks, it's searched by block status
+        mock.chain().finality_depth = 20;
+        let mut starknet_node =
+            StarknetNode::test_new(&mock.url(), Protocol::Starknet, String::from("mainnet"));
+        let blockchain = starknet_node.parse_top_blocks(5, None).await.unwrap();
+        let finalized = blockchain.finalized.unwrap();
+        assert_eq!(finalized.height, MOCK_HEIGHT - 20);
+        assert_eq!(
+            finalized.hash,
+            format!("0x{}", mock.chain().block(MOCK_HEIGHT - 20).unwrap().hex())
+        );
+    }
+
+    #[tokio::test]
+    async fn starknet_no_finalized_head() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::StarknetNode);
+        // no block above genesis is accepted on L1
+        mock.chain().finality_depth = MOCK_HEIGHT + 1;
+        let mut starknet_node =
+            StarknetNode::test_new(&mock.url(), Protocol::Starknet, String::from("mainnet"));
+        let blockchain = starknet_node.parse_top_blocks(5, None).await.unwrap();
+        assert!(blockchain.finalized.is_none());
+    }
 }
@@ -3,7 +3,7 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use super::ProviderActions;
+use super::{ProviderActions, NO_NEW_BLOCK};
 use crate::commons::blockchain;
 
 use crate::conf::{self, Endpoint, Network, Protocol};
@@ -49,9 +49,9 @@ impl ProviderActions for Subscan {
             return Err("Endpoint is not available".into());
         }
         let previous_head = previous_head.unwrap_or("".to_string());
-        let block_head = self.get_finalized_head().await;
-        let block_head = match block_head {
-            Ok(block_head) => block_head,
+        let heads = self.get_heads().await;
+        let (block_head, finalized_head) = match heads {
+            Ok(heads) => heads,
             Err(e) => {
                 debug!("Error while getting head: {}", e);
                 return Err(e);
@@ -70,16 +70,20 @@ impl ProviderActions for Subscan {
                 "No new block (head: {} block with hash {}), skip task",
                 block_head.block_num, block_head.hash
             );
-            return Err("No new block".into());
+            return Err(NO_NEW_BLOCK.into());
         }
         let mut blockchain = blockchain::Blockchain::new(None);
-        let res = self.get_finalized_blocks(n_block as u16, None).await?;
+        let res = self.get_top_blocks(n_block as u16, None).await?;
 
         for block in res {
             let b = block.to_block();
             blockchain.add_block(b);
         }
         blockchain.sort();
+        blockchain.finalized = finalized_head.map(|b| blockchain::Head {
+            height: b.block_num,
+            hash: b.hash,
+        });
 
         set_blockchain_height_endpoint(
             &self.endpoint.url,
@@ -94,9 +98,11 @@ impl ProviderActions for Subscan {
 const PAGE_MAX_ROW: u16 = 100;
 
 impl Subscan {
-    async fn get_finalized_head(
+    // get_heads return the latest block and the highest finalized one
+    async fn get_heads(
         &mut self,
-    ) -> Result<SubscanBlock, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<(SubscanBlock, Option<SubscanBlock>), Box<dyn std::error::Error + Send + Sync>>
+    {
         let client = &mut self.endpoint.reqwest;
         let url = format!("{}/api/v2/scan/blocks", self.endpoint.url);
         let body = json!({
@@ -112,15 +118,16 @@ impl Subscan {
                 &self.endpoint.network,
             )
             .await?;
-        // get highest number of block with finalized = true
-        let block = res
+        let finalized = res
             .data
             .blocks
             .iter()
             .filter(|b| b.finalized)
-            .max_by_key(|b| b.block_num);
+            .max_by_key(|b| b.block_num)
+            .cloned();
+        let block = res.data.blocks.into_iter().max_by_key(|b| b.block_num);
         match block {
-            Some(block) => Ok(block.clone()),
+            Some(block) => Ok((block, finalized)),
             None => Err("No head".into()),
         }
     }
@@ -145,14 +152,14 @@ impl Subscan {
         Ok(res)
     }
 
-    async fn get_finalized_blocks(
+    async fn get_top_blocks(
         &mut self,
         n_block: u16,
         from_hash: Option<String>,
     ) -> Result<Vec<SubscanBlock>, Box<dyn std::error::Error + Send + Sync>> {
         // let from_hash = from_hash.unwrap_or("".to_string());
         trace!(
-            "get_finalized_blocks: n_block: {}, hash : {:?}",
+            "get_top_blocks: n_block: {}, hash : {:?}",
             n_block,
             from_hash,
         );
@@ -176,21 +183,16 @@ impl Subscan {
                 &self.endpoint.network,
             )
             .await?;
-        let mut finalized_blocks: Vec<SubscanBlock> = res
-            .data
-            .blocks
-            .iter()
-            .filter(|b| b.finalized)
-            .cloned()
-            .collect();
-        finalized_blocks.sort_by(|a, b| b.block_num.cmp(&a.block_num));
+        let mut top_blocks: Vec<SubscanBlock> = res.data.blocks;
+        top_blocks.sort_by_key(|b| std::cmp::Reverse(b.block_num));
+        top_blocks.dedup_by_key(|b| b.block_num);
         let mut blocks = vec![];
-        // from hash is none, we take just last blocks finalized from the last block
+        // from hash is none, we take just last blocks from the last block
         let mut found = from_hash.is_none();
         let from_hash = from_hash.unwrap_or("".to_string());
         // find the block with the same hash as the previous head
         let mut expected_block_num = None;
-        for block in finalized_blocks {
+        for block in top_blocks {
             if found {
                 // blocks.push(block.clone());
                 // if blocks.len() >= n_block as usize {
@@ -272,17 +274,16 @@ impl SubscanBlock {
 mod tests {
     extern crate env_logger;
     use super::*;
+    use crate::sim::SimApi;
     use crate::tests;
     use crate::utils;
 
     #[tokio::test]
     async fn subscan_parse_top_blocks() {
         tests::setup();
-        let mut subscan = Subscan::test_new(
-            "https://polkadot.api.subscan.io",
-            Protocol::Polkadot,
-            String::from("mainnet"),
-        );
+        let mock = tests::mock_server(SimApi::Subscan);
+        let mut subscan =
+            Subscan::test_new(&mock.url(), Protocol::Polkadot, String::from("mainnet"));
         let blockchain = subscan.parse_top_blocks(5, None).await;
         assert!(blockchain.is_ok(), "Subscan should return a blockchain");
         let blockchain = blockchain.unwrap();
@@ -4,13 +4,16 @@ use async_trait::async_trait;
 use chrono::DateTime;
 use serde::{Deserialize, Serialize};
 
-use super::ProviderActions;
+use super::{ProviderActions, NO_NEW_BLOCK};
 use crate::commons::blockchain;
 
 use crate::conf::{self, Endpoint, Network, Protocol};
 use crate::prom::registry::set_blockchain_height_endpoint;
 use crate::requests::client::ReqwestClient;
 
+// TENDERBAKE_FINALITY is the number of successors after which a block is final
+const TENDERBAKE_FINALITY: u64 = 2;
+
 #[derive(Serialize, Debug, Clone)]
 pub struct TezosNode {
     pub endpoint: conf::Endpoint,
@@ -57,7 +60,7 @@ impl ProviderActions for TezosNode {
                 "No new block (head: {} block with hash {}), skip task",
                 head.header.level, head.hash
             );
-            return Err("No new block".into());
+            return Err(NO_NEW_BLOCK.into());
         }
 
         let mut height = head.header.level;
@@ -87,6 +90,24 @@ impl ProviderActions for TezosNode {
         }
         let mut blockchain: blockchain::Blockchain = blockchain::Blockchain::new(Some(blocks));
         blockchain.sort();
+        // tenderbake finalize a block once it has two successors
+        let finalized_height = blockchain.height.saturating_sub(TENDERBAKE_FINALITY);
+        let finalized = match blockchain
+            .blocks
+            .iter()
+            .find(|b| b.height == finalized_height)
+        {
+            Some(b) => b.hash.clone(),
+            None => {
+                let head_hash = &blockchain.blocks.first().unwrap().hash;
+                let path = format!("{}~{}", head_hash, TENDERBAKE_FINALITY);
+                self.get_block(Some(&path)).await?.hash
+            }
+        };
+        blockchain.finalized = Some(blockchain::Head {
+            height: finalized_height,
+            hash: finalized,
+        });
 
         set_blockchain_height_endpoint(
             &self.endpoint.url,
@@ -97,6 +118,39 @@ impl ProviderActions for TezosNode {
         );
         Ok(blockchain)
     }
+    async fn chain_identity(
+        &mut self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/chains/main/chain_id", self.endpoint.url);
+        let client = &mut self.endpoint.reqwest;
+        let chain_id: String = client
+            .run_request(
+                reqwest::Method::GET,
+                None,
+                &url,
+                &self.endpoint.protocol,
+                &self.endpoint.network,
+            )
+            .await?;
+        Ok(Some(chain_id))
+    }
+    async fn sync_status(
+        &mut self,
+    ) -> Result<Option<bool>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/chains/main/is_bootstrapped", self.endpoint.url);
+        let client = &mut self.endpoint.reqwest;
+        let res: TezosBootstrapped = client
+            .run_request(
+                reqwest::Method::GET,
+                None,
+                &url,
+                &self.endpoint.protocol,
+                &self.endpoint.network,
+            )
+            .await?;
+        // stuck nodes are bootstrapped but no longer progress
+        Ok(Some(!res.bootstrapped || res.sync_state != "synced"))
+    }
 }
 impl TezosNode {
     async fn get_block(
@@ -242,41 +296,56 @@ impl TezosBlock {
     }
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct TezosBootstrapped {
+    pub bootstrapped: bool,
+    pub sync_state: String,
+}
+
 #[cfg(test)]
 
 mod tests {
     extern crate env_logger;
-    use std::env;
 
     use super::*;
-    use crate::tests;
+    use crate::{
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+    };
     #[tokio::test]
     async fn tezos_get_block() {
         tests::setup();
-        let url = env::var("TEZOS_NODE_URL").unwrap();
-        let mut tezos_node = TezosNode::test_new(&url, Protocol::Tezos, String::from("mainnet"));
+        let mock = tests::mock_server(SimApi::TezosNode);
+        let mut tezos_node =
+            TezosNode::test_new(&mock.url(), Protocol::Tezos, String::from("mainnet"));
         let r = tezos_node.get_block(None).await;
         assert!(r.is_ok());
         let block_head = r.unwrap();
-        assert!(
-            block_head.header.level > 0,
-            "Block level should be greater than 0"
+        assert_eq!(
+            block_head.header.level, MOCK_HEIGHT,
+            "Block level should be chain height"
         );
+        assert_eq!(block_head.hash, format!("B{}", mock.chain().head().hex()));
     }
     #[tokio::test]
     async fn tezos_count_tx() {
         tests::setup();
-        let url = env::var("TEZOS_NODE_URL").unwrap();
-        let mut tezos_node = TezosNode::test_new(&url, Protocol::Tezos, String::from("mainnet"));
+        let mock = tests::mock_server(SimApi::TezosNode);
+        let mut tezos_node =
+            TezosNode::test_new(&mock.url(), Protocol::Tezos, String::from("mainnet"));
         let r = tezos_node.get_block(None).await;
 
         assert!(r.is_ok());
+        let block = r.unwrap().to_block();
+        assert_eq!(block.txs, mock.chain().head().txs);
+        assert_eq!(block.time, mock.chain().head().time);
     }
     #[tokio::test]
     async fn tezos_parse_top_blocks() {
         tests::setup();
-        let url = env::var("TEZOS_NODE_URL").unwrap();
-        let mut tezos_node = TezosNode::test_new(&url, Protocol::Tezos, String::from("mainnet"));
+        let mock = tests::mock_server(SimApi::TezosNode);
+        let mut tezos_node =
+            TezosNode::test_new(&mock.url(), Protocol::Tezos, String::from("mainnet"));
         let r = tezos_node.parse_top_blocks(10, None).await;
         assert!(r.is_ok());
         let blockchain = r.unwrap();
@@ -296,5 +365,10 @@ mod tests {
             );
             i += 1;
         }
+        assert_eq!(
+            tezos_node.chain_identity().await.unwrap(),
+            Some("NetXdQprcVkpaWU".to_string())
+        );
+        assert_eq!(tezos_node.sync_status().await.unwrap(), Some(false));
     }
 }
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use chrono::DateTime;
 use serde::{Deserialize, Serialize};
 
-use super::ProviderActions;
+use super::{ProviderActions, NO_NEW_BLOCK};
 use crate::commons::blockchain;
 
 use crate::conf::{self, Endpoint, Network, Protocol};
@@ -55,7 +55,7 @@ impl ProviderActions for Tzkt {
                 "No new block (head: {} block with hash {}), skip task",
                 head.level, head.hash
             );
-            return Err("No new block".into());
+            return Err(NO_NEW_BLOCK.into());
         }
         let mut blockchain: blockchain::Blockchain = blockchain::Blockchain::new(None);
         let mut i = 0;
@@ -211,28 +211,31 @@ struct TzktBlockFull {
 mod tests {
     extern crate env_logger;
     use super::*;
-    use crate::tests;
+    use crate::{
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+    };
     #[tokio::test]
     async fn tzkt_get_block_full() {
         tests::setup();
-        let url = "https://api.ghostnet.tzkt.io";
-        let mut tzkt = Tzkt::test_new(url, Protocol::Tezos, String::from("ghostnet"));
-        let r = tzkt.get_block_full(123456).await.unwrap();
-        assert_eq!(r.level, 123456);
+        let mock = tests::mock_server(SimApi::Tzkt);
+        let mut tzkt = Tzkt::test_new(&mock.url(), Protocol::Tezos, String::from("ghostnet"));
+        let r = tzkt.get_block_full(50).await.unwrap();
+        assert_eq!(r.level, 50);
     }
     #[tokio::test]
     async fn tzkt_get_head() {
         tests::setup();
-        let url = "https://api.ghostnet.tzkt.io";
-        let mut tzkt = Tzkt::test_new(url, Protocol::Tezos, String::from("ghostnet"));
+        let mock = tests::mock_server(SimApi::Tzkt);
+        let mut tzkt = Tzkt::test_new(&mock.url(), Protocol::Tezos, String::from("ghostnet"));
         let r = tzkt.get_head().await.unwrap();
-        assert!(r.level > 123456);
+        assert_eq!(r.level, MOCK_HEIGHT as u32);
     }
     #[tokio::test]
     async fn tzkt_parse_top() {
         tests::setup();
-        let url = "https://api.ghostnet.tzkt.io";
-        let mut tzkt = Tzkt::test_new(url, Protocol::Tezos, String::from("ghostnet"));
+        let mock = tests::mock_server(SimApi::Tzkt);
+        let mut tzkt = Tzkt::test_new(&mock.url(), Protocol::Tezos, String::from("ghostnet"));
         let r = tzkt.parse_top_blocks(5, None).await.unwrap();
         assert_eq!(r.blocks.len(), 5);
     }
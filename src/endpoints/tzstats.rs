@@ -1,4 +1,4 @@
-use super::ProviderActions;
+use super::{ProviderActions, NO_NEW_BLOCK};
 use crate::commons::blockchain::{self, Block};
 use async_trait::async_trait;
 use chrono::DateTime;
@@ -76,12 +76,12 @@ impl ProviderActions for TzStats {
                 "No new block (head: {} block with hash {}), skip task",
                 head.height, head.hash
             );
-            return Err("No new block".into());
+            return Err(NO_NEW_BLOCK.into());
         }
         let head_block = Block {
             hash: head.hash,
             height: head.height,
-            time: DateTime::parse_from_rfc3339(&head.time)?.timestamp_millis() as u64,
+            time: DateTime::parse_from_rfc3339(&head.time)?.timestamp() as u64,
             txs: head.n_tx,
         };
         let mut blockchain: blockchain::Blockchain = blockchain::Blockchain::new(None);
@@ -93,7 +93,7 @@ impl ProviderActions for TzStats {
             let block = Block {
                 hash: r.hash,
                 height: r.height,
-                time: DateTime::parse_from_rfc3339(&r.time)?.timestamp_millis() as u64,
+                time: DateTime::parse_from_rfc3339(&r.time)?.timestamp() as u64,
                 txs: r.n_tx,
             };
             if block.hash == previous_head {
@@ -169,17 +169,22 @@ struct TzStatsBlock {
 mod tests {
 
     extern crate env_logger;
-    use std::env;
 
     use super::*;
-    use crate::tests;
+    use crate::{
+        sim::SimApi,
+        tests::{self, MOCK_HEIGHT},
+    };
     #[tokio::test]
     async fn tzstats_parse_top_blocks() {
         tests::setup();
-        let api_key = env::var("TZSTATS_API_KEY").unwrap();
-        let url = "https://api.ghost.tzpro.io";
-        let mut tzstats =
-            TzStats::test_new(url, Protocol::Tezos, String::from("ghostnet"), &api_key);
+        let mock = tests::mock_server(SimApi::TzStats);
+        let mut tzstats = TzStats::test_new(
+            &mock.url(),
+            Protocol::Tezos,
+            String::from("ghostnet"),
+            "key",
+        );
         let blockchain = tzstats
             .parse_top_blocks(5, None)
             .await
@@ -187,6 +192,9 @@ mod tests {
         assert_eq!(blockchain.blocks.len(), 5);
 
         let height_start = blockchain.blocks[0].height;
+        assert_eq!(height_start, MOCK_HEIGHT);
+        let head_time = mock.chain().block(MOCK_HEIGHT).unwrap().time;
+        assert_eq!(blockchain.blocks[0].time, head_time);
         for i in 1..blockchain.blocks.len() {
             assert_eq!(blockchain.blocks[i].height, height_start - i as u64);
         }
@@ -0,0 +1,4 @@
+/*
+ * Blockhead library expose simulated chains, shared by blockhead test suite and blockhead-sim
+ */
+pub mod sim;
@@ -2,6 +2,7 @@ use std::process;
 
 use conf::get_configuration;
 use futures::future;
+pub mod alerts;
 pub mod api;
 pub mod collectors;
 pub mod commons;
@@ -13,10 +14,13 @@ pub mod requests;
 pub mod utils;
 use actix_cors::Cors;
 #[cfg(test)]
+pub use blockhead::sim;
+#[cfg(test)]
 pub mod tests;
 
 use crate::{
-    api::{app, metrics},
+    api::{app, metrics, proxy},
+    commons::status,
     prom::registry::register_custom_metrics,
 };
 use actix_web::{middleware, App, HttpServer};
@@ -54,6 +58,7 @@ async fn start_tasks(mut rx: mpsc::Receiver<bool>) -> Result<(), io::Error> {
         for t in tasks.iter() {
             t.abort();
         }
+        status::clear_providers_status();
         tasks = run_tasks();
     }
     Ok(())
@@ -126,11 +131,56 @@ async fn main() -> std::io::Result<()> {
             .wrap(middleware::Logger::default())
             .service(app::protocols_handler)
             .service(app::protocol_handler)
+            .service(app::protocol_net_reorgs_handler)
+            .service(app::protocol_net_stream_handler)
+            .service(app::protocol_net_blocks_handler)
+            .service(app::protocol_net_latest_blocks_handler)
+            .service(app::protocol_net_block_hash_handler)
+            .service(app::protocol_net_block_handler)
+            .service(app::protocol_net_providers_handler)
+            .service(app::protocol_net_provider_history_handler)
+            .service(app::protocol_net_health_handler)
+            .service(app::protocol_net_stats_handler)
+            .service(app::health_handler)
             .service(app::protocol_net_handler)
     })
     .bind(("0.0.0.0", server_port))?
     .run();
 
+    // rpc proxy is optional, served only if configured
+    let proxy_server = match &config.global.proxy {
+        Some(proxy_config) => {
+            info!(
+                "Started rpc proxy server at http://localhost:{}/rpc",
+                proxy_config.port
+            );
+            Some(
+                HttpServer::new(move || {
+                    let cors = Cors::default()
+                        .allow_any_origin()
+                        .send_wildcard()
+                        .allowed_methods(vec!["POST"])
+                        .allow_any_header()
+                        .max_age(3600);
+                    App::new()
+                        .wrap(cors)
+                        .wrap(middleware::Compress::default())
+                        .wrap(middleware::Logger::default())
+                        .service(proxy::rpc_proxy_handler)
+                })
+                .bind(("0.0.0.0", proxy_config.port))?
+                .run(),
+            )
+        }
+        None => None,
+    };
+    let proxy = async move {
+        match proxy_server {
+            Some(server) => server.await,
+            None => Ok(()),
+        }
+    };
+
     tokio::spawn(async move {
         match signal::ctrl_c().await {
             Ok(()) => {
@@ -144,7 +194,7 @@ async fn main() -> std::io::Result<()> {
         }
     });
 
-    future::try_join4(metrics_server, api, scrapp, conf_watcher).await?;
+    future::try_join5(metrics_server, api, proxy, scrapp, conf_watcher).await?;
 
     Ok(())
 }
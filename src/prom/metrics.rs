@@ -56,6 +56,22 @@ pub static BLOCKCHAIN_HEIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
     )
     .expect("metric can be created")
 });
+pub static BLOCKCHAIN_FINALIZED_HEIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_blockchain_finalized_height",
+        "Height of last finalized block in the blockchain",
+        &["protocol", "network"]
+    )
+    .expect("metric can be created")
+});
+pub static BLOCKCHAIN_FINALITY_LAG: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_blockchain_finality_lag",
+        "Number of blocks between latest and finalized heads",
+        &["protocol", "network"]
+    )
+    .expect("metric can be created")
+});
 pub static BLOCKCHAIN_HEIGHT_ENDPOINT: Lazy<IntGaugeVec> = Lazy::new(|| {
     register_int_gauge_vec!(
         "blockhead_blockchain_height_endpoint",
@@ -64,6 +80,30 @@ pub static BLOCKCHAIN_HEIGHT_ENDPOINT: Lazy<IntGaugeVec> = Lazy::new(|| {
     )
     .expect("metric can be created")
 });
+pub static BLOCKCHAIN_LAG_ENDPOINT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_blockchain_lag_endpoint",
+        "Number of blocks endpoint is behind canonical head (negative when ahead)",
+        &["endpoint", "alias", "protocol", "network"]
+    )
+    .expect("metric can be created")
+});
+pub static BLOCKCHAIN_HEAD_AGE_ENDPOINT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_blockchain_head_age_endpoint_seconds",
+        "Seconds since endpoint head changed",
+        &["endpoint", "alias", "protocol", "network"]
+    )
+    .expect("metric can be created")
+});
+pub static BLOCKCHAIN_DIVERGENT_ENDPOINT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_blockchain_divergent_endpoint",
+        "Endpoint block hash disagree with canonical blockchain at a common height (1 = divergent, 0 = agree)",
+        &["endpoint", "alias", "protocol", "network"]
+    )
+    .expect("metric can be created")
+});
 pub static BLOCKCHAIN_HEAD_TIMESTAMP: Lazy<IntGaugeVec> = Lazy::new(|| {
     register_int_gauge_vec!(
         "blockhead_blockchain_head_timestamp",
@@ -80,3 +120,110 @@ pub static BLOCKCHAIN_HEAD_TXS: Lazy<IntGaugeVec> = Lazy::new(|| {
     )
     .expect("metric can be created")
 });
+pub static BLOCKCHAIN_HEAD_AGE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_blockchain_head_age_seconds",
+        "Seconds since the last block in the blockchain was produced",
+        &["protocol", "network"]
+    )
+    .expect("metric can be created")
+});
+pub static BLOCKCHAIN_STALLED: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_blockchain_stalled",
+        "Blockchain head is older than stall_factor expected block times (1 = stalled, 0 = producing)",
+        &["protocol", "network"]
+    )
+    .expect("metric can be created")
+});
+pub static BLOCK_INTERVAL: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "blockhead_block_interval_seconds",
+        "Seconds between a new canonical block and its parent",
+        &["protocol", "network"],
+        vec![
+            0.5, 1.0, 2.0, 3.0, 5.0, 6.0, 8.0, 10.0, 12.0, 15.0, 20.0, 30.0, 60.0, 120.0, 300.0,
+            600.0, 1200.0, 3600.0
+        ]
+    )
+    .expect("metric can be created")
+});
+/**
+ * Reorg metrics
+ */
+pub static ERRORS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "blockhead_errors_total",
+        "Number of failed endpoint requests by error kind",
+        &["endpoint", "alias", "protocol", "network", "kind"]
+    )
+    .expect("metric can be created")
+});
+pub static ENDPOINT_CIRCUIT_STATE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_endpoint_circuit_state",
+        "Endpoint circuit breaker state (0 = closed, 1 = half open, 2 = open)",
+        &["endpoint", "alias", "protocol", "network"]
+    )
+    .expect("metric can be created")
+});
+pub static RATE_LIMIT_REMAINING: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_rate_limit_remaining",
+        "Requests remaining in endpoint quota, reported by endpoint headers or local rate limits",
+        &["endpoint", "alias", "protocol", "network"]
+    )
+    .expect("metric can be created")
+});
+pub static REORG_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "blockhead_reorg_total",
+        "Number of reorgs detected in the blockchain",
+        &["protocol", "network", "provider"]
+    )
+    .expect("metric can be created")
+});
+pub static REORG_MAX_DEPTH: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_reorg_max_depth",
+        "Deepest reorg detected in the blockchain",
+        &["protocol", "network"]
+    )
+    .expect("metric can be created")
+});
+/**
+ * Subscription metrics
+ */
+pub static SUBSCRIPTION_STATUS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_subscription_status",
+        "New heads subscription status (1 = connected, 0 = disconnected)",
+        &["endpoint", "alias", "protocol", "network"]
+    )
+    .expect("metric can be created")
+});
+pub static BLOCK_PROPAGATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "blockhead_block_propagation_seconds",
+        "Delay between first subscription receiving a block and endpoint receiving it in seconds",
+        &["endpoint", "alias", "protocol", "network"],
+        vec![0.0, 0.1, 0.25, 0.5, 1.0, 2.0, 3.0, 5.0, 10.0, 20.0, 30.0, 60.0]
+    )
+    .expect("metric can be created")
+});
+pub static ENDPOINT_SYNCING: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_endpoint_syncing",
+        "Node sync state reported by endpoint (1 = syncing, 0 = synced)",
+        &["endpoint", "alias", "protocol", "network"]
+    )
+    .expect("metric can be created")
+});
+pub static ENDPOINT_CHAIN_MISMATCH: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "blockhead_endpoint_chain_mismatch",
+        "Endpoint serve another chain than the configured network (1 = mismatch, 0 = verified)",
+        &["endpoint", "alias", "protocol", "network"]
+    )
+    .expect("metric can be created")
+});
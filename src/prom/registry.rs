@@ -5,10 +5,15 @@ use once_cell::sync::Lazy;
 use prometheus::Registry;
 
 use crate::conf::{Network, Protocol};
+use crate::requests::circuit_breaker::CircuitState;
 
 use super::metrics::{
-    self, BLOCKCHAIN_HEAD_TIMESTAMP, BLOCKCHAIN_HEAD_TXS, BLOCKCHAIN_HEIGHT,
-    BLOCKCHAIN_HEIGHT_ENDPOINT,
+    self, BLOCKCHAIN_DIVERGENT_ENDPOINT, BLOCKCHAIN_FINALITY_LAG, BLOCKCHAIN_FINALIZED_HEIGHT,
+    BLOCKCHAIN_HEAD_AGE, BLOCKCHAIN_HEAD_AGE_ENDPOINT, BLOCKCHAIN_HEAD_TIMESTAMP,
+    BLOCKCHAIN_HEAD_TXS, BLOCKCHAIN_HEIGHT, BLOCKCHAIN_HEIGHT_ENDPOINT, BLOCKCHAIN_LAG_ENDPOINT,
+    BLOCKCHAIN_STALLED, BLOCK_INTERVAL, BLOCK_PROPAGATION, ENDPOINT_CHAIN_MISMATCH,
+    ENDPOINT_CIRCUIT_STATE, ENDPOINT_SYNCING, ERRORS_TOTAL, RATE_LIMIT_REMAINING, REORG_MAX_DEPTH,
+    REORG_TOTAL, SUBSCRIPTION_STATUS,
 };
 
 static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| Mutex::new(Registry::new()));
@@ -21,12 +26,46 @@ pub fn register_custom_metrics() {
         .expect("collector can be registered");
     r.register(Box::new(metrics::BLOCKCHAIN_HEIGHT.clone()))
         .expect("collector can be registered");
+    r.register(Box::new(metrics::BLOCKCHAIN_FINALIZED_HEIGHT.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::BLOCKCHAIN_FINALITY_LAG.clone()))
+        .expect("collector can be registered");
     r.register(Box::new(metrics::BLOCKCHAIN_HEIGHT_ENDPOINT.clone()))
         .expect("collector can be registered");
+    r.register(Box::new(metrics::BLOCKCHAIN_LAG_ENDPOINT.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::BLOCKCHAIN_HEAD_AGE_ENDPOINT.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::BLOCKCHAIN_DIVERGENT_ENDPOINT.clone()))
+        .expect("collector can be registered");
     r.register(Box::new(metrics::BLOCKCHAIN_HEAD_TIMESTAMP.clone()))
         .expect("collector can be registered");
     r.register(Box::new(metrics::BLOCKCHAIN_HEAD_TXS.clone()))
         .expect("collector can be registered");
+    r.register(Box::new(metrics::BLOCKCHAIN_HEAD_AGE.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::BLOCK_INTERVAL.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::BLOCKCHAIN_STALLED.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::ERRORS_TOTAL.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::ENDPOINT_CIRCUIT_STATE.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::RATE_LIMIT_REMAINING.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::REORG_TOTAL.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::REORG_MAX_DEPTH.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::SUBSCRIPTION_STATUS.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::BLOCK_PROPAGATION.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::ENDPOINT_SYNCING.clone()))
+        .expect("collector can be registered");
+    r.register(Box::new(metrics::ENDPOINT_CHAIN_MISMATCH.clone()))
+        .expect("collector can be registered");
 }
 pub fn track_status_code(
     url: &str,
@@ -59,6 +98,42 @@ pub fn track_status_code(
         .inc();
 }
 
+pub fn track_request_error(
+    url: &str,
+    alias: &str,
+    protocol: &Protocol,
+    network: &Network,
+    kind: &str,
+) {
+    let u = get_base_url(url);
+    ERRORS_TOTAL
+        .with_label_values(&[&u, alias, &protocol.to_string(), &network.to_string(), kind])
+        .inc();
+}
+pub fn set_endpoint_circuit_state(
+    url: &str,
+    alias: &str,
+    protocol: &Protocol,
+    network: &Network,
+    state: CircuitState,
+) {
+    let u = get_base_url(url);
+    ENDPOINT_CIRCUIT_STATE
+        .with_label_values(&[&u, alias, &protocol.to_string(), &network.to_string()])
+        .set(state.metric_value());
+}
+pub fn set_rate_limit_remaining(
+    url: &str,
+    alias: &str,
+    protocol: &Protocol,
+    network: &Network,
+    remaining: u64,
+) {
+    let u = get_base_url(url);
+    RATE_LIMIT_REMAINING
+        .with_label_values(&[&u, alias, &protocol.to_string(), &network.to_string()])
+        .set(remaining as i64);
+}
 pub fn track_response_time(
     url: &str,
     alias: &str,
@@ -100,6 +175,51 @@ pub fn set_blockchain_height_endpoint(
         .set(height as i64);
 }
 
+pub fn set_endpoint_consensus_metrics(
+    url: &str,
+    alias: &str,
+    protocol: &Protocol,
+    network: &Network,
+    lag: i64,
+    head_age: u64,
+    divergent: bool,
+) {
+    let u = get_base_url(url);
+    let labels = [
+        u.as_str(),
+        alias,
+        &protocol.to_string(),
+        &network.to_string(),
+    ];
+    BLOCKCHAIN_LAG_ENDPOINT.with_label_values(&labels).set(lag);
+    BLOCKCHAIN_HEAD_AGE_ENDPOINT
+        .with_label_values(&labels)
+        .set(head_age as i64);
+    BLOCKCHAIN_DIVERGENT_ENDPOINT
+        .with_label_values(&labels)
+        .set(if divergent { 1 } else { 0 });
+}
+
+// remove_endpoint_consensus_metrics remove lag, head age and divergence of an endpoint which did not return a blockchain
+pub fn remove_endpoint_consensus_metrics(
+    url: &str,
+    alias: &str,
+    protocol: &Protocol,
+    network: &Network,
+) {
+    let u = get_base_url(url);
+    let labels = [
+        u.as_str(),
+        alias,
+        &protocol.to_string(),
+        &network.to_string(),
+    ];
+    // metrics are missing when endpoint is already removed or never returned a blockchain
+    let _ = BLOCKCHAIN_LAG_ENDPOINT.remove_label_values(&labels);
+    let _ = BLOCKCHAIN_HEAD_AGE_ENDPOINT.remove_label_values(&labels);
+    let _ = BLOCKCHAIN_DIVERGENT_ENDPOINT.remove_label_values(&labels);
+}
+
 pub fn set_blockchain_metrics(
     protocol: &Protocol,
     network: &Network,
@@ -126,6 +246,110 @@ pub fn set_blockchain_metrics(
         .set(head_txs);
 }
 
+pub fn set_blockchain_finality_metrics(
+    protocol: &Protocol,
+    network: &Network,
+    finalized_height: i64,
+    finality_lag: i64,
+) {
+    BLOCKCHAIN_FINALIZED_HEIGHT
+        .with_label_values(&[&protocol.to_string(), &network.to_string()])
+        .set(finalized_height);
+    BLOCKCHAIN_FINALITY_LAG
+        .with_label_values(&[&protocol.to_string(), &network.to_string()])
+        .set(finality_lag);
+}
+
+pub fn set_blockchain_health_metrics(
+    protocol: &Protocol,
+    network: &Network,
+    head_age: i64,
+    stalled: bool,
+) {
+    BLOCKCHAIN_HEAD_AGE
+        .with_label_values(&[&protocol.to_string(), &network.to_string()])
+        .set(head_age);
+    BLOCKCHAIN_STALLED
+        .with_label_values(&[&protocol.to_string(), &network.to_string()])
+        .set(if stalled { 1 } else { 0 });
+}
+// remove_blockchain_health_metrics remove head age and stalled state of a network whose head has no time
+pub fn remove_blockchain_health_metrics(protocol: &Protocol, network: &Network) {
+    let labels: [&str; 2] = [&protocol.to_string(), &network.to_string()];
+    // metrics are missing when head never had a time
+    let _ = BLOCKCHAIN_HEAD_AGE.remove_label_values(&labels);
+    let _ = BLOCKCHAIN_STALLED.remove_label_values(&labels);
+}
+
+pub fn track_block_interval(protocol: &Protocol, network: &Network, interval: f64) {
+    BLOCK_INTERVAL
+        .with_label_values(&[&protocol.to_string(), &network.to_string()])
+        .observe(interval);
+}
+
+pub fn track_reorg(protocol: &Protocol, network: &Network, provider: &str, depth: u64) {
+    REORG_TOTAL
+        .with_label_values(&[&protocol.to_string(), &network.to_string(), provider])
+        .inc();
+    let max_depth =
+        REORG_MAX_DEPTH.with_label_values(&[&protocol.to_string(), &network.to_string()]);
+    if max_depth.get() < depth as i64 {
+        max_depth.set(depth as i64);
+    }
+}
+
+pub fn set_subscription_status(
+    url: &str,
+    alias: &str,
+    protocol: &Protocol,
+    network: &Network,
+    connected: bool,
+) {
+    let u = get_base_url(url);
+    SUBSCRIPTION_STATUS
+        .with_label_values(&[&u, alias, &protocol.to_string(), &network.to_string()])
+        .set(if connected { 1 } else { 0 });
+}
+
+pub fn track_block_propagation(
+    url: &str,
+    alias: &str,
+    protocol: &Protocol,
+    network: &Network,
+    delay: f64,
+) {
+    let u = get_base_url(url);
+    BLOCK_PROPAGATION
+        .with_label_values(&[&u, alias, &protocol.to_string(), &network.to_string()])
+        .observe(delay);
+}
+
+pub fn set_endpoint_syncing(
+    url: &str,
+    alias: &str,
+    protocol: &Protocol,
+    network: &Network,
+    syncing: bool,
+) {
+    let u = get_base_url(url);
+    ENDPOINT_SYNCING
+        .with_label_values(&[&u, alias, &protocol.to_string(), &network.to_string()])
+        .set(if syncing { 1 } else { 0 });
+}
+
+pub fn set_endpoint_chain_mismatch(
+    url: &str,
+    alias: &str,
+    protocol: &Protocol,
+    network: &Network,
+    mismatch: bool,
+) {
+    let u = get_base_url(url);
+    ENDPOINT_CHAIN_MISMATCH
+        .with_label_values(&[&u, alias, &protocol.to_string(), &network.to_string()])
+        .set(if mismatch { 1 } else { 0 });
+}
+
 pub fn set_endpoint_status_metric(
     url: &str,
     alias: &str,
@@ -144,7 +368,7 @@ pub fn set_endpoint_status_metric(
         .with_label_values(&[&u, alias, &protocol.to_string(), &network.to_string()])
         .set(state);
 }
-fn get_base_url(url: &str) -> String {
+pub fn get_base_url(url: &str) -> String {
     let base_url = url
         .split('/')
         .nth(2)
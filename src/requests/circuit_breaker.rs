@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+/**
+ * CircuitState of an endpoint, an open circuit reject requests until cooldown is elapsed
+ * After cooldown the circuit is half open, a single probe request decide to close or re open it
+ */
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    #[default]
+    Closed,
+    HalfOpen,
+    Open,
+}
+impl CircuitState {
+    // metric_value is the circuit state gauge value
+    pub fn metric_value(&self) -> i64 {
+        match self {
+            CircuitState::Closed => 0,
+            CircuitState::HalfOpen => 1,
+            CircuitState::Open => 2,
+        }
+    }
+}
+impl std::fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CircuitState::Closed => write!(f, "closed"),
+            CircuitState::HalfOpen => write!(f, "half_open"),
+            CircuitState::Open => write!(f, "open"),
+        }
+    }
+}
+
+/**
+ * CircuitBreaker count consecutive failures of an endpoint, circuit is opened after `threshold` failures
+ * Breakers are shared by every client of an endpoint (collector, proxy, subscriptions)
+ */
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    failures: u32,
+    opened_at: Option<Instant>,
+    // probing is true while the half open probe request is in flight
+    probing: bool,
+}
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            threshold,
+            cooldown,
+            failures: 0,
+            opened_at: None,
+            probing: false,
+        }
+    }
+    pub fn state(&self, now: Instant) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(at) if now.saturating_duration_since(at) < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+    // available return false if a request would be rejected, without taking the probe slot
+    pub fn available(&self, now: Instant) -> bool {
+        match self.state(now) {
+            CircuitState::Closed => true,
+            CircuitState::HalfOpen => !self.probing,
+            CircuitState::Open => false,
+        }
+    }
+    // allow return true if a request can be sent, in half open state only one probe is allowed
+    pub fn allow(&mut self, now: Instant) -> bool {
+        if !self.available(now) {
+            return false;
+        }
+        if self.state(now) == CircuitState::HalfOpen {
+            self.probing = true;
+        }
+        true
+    }
+    // release_probe free the half open probe slot without result, next request is a new probe
+    pub fn release_probe(&mut self) {
+        self.probing = false;
+    }
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.opened_at = None;
+        self.probing = false;
+    }
+    // record_failure open circuit after threshold consecutive failures, a failed probe re open it
+    pub fn record_failure(&mut self, now: Instant) {
+        if self.threshold == 0 {
+            self.probing = false;
+            return;
+        }
+        self.failures = self.failures.saturating_add(1);
+        if self.probing || self.failures >= self.threshold {
+            self.opened_at = Some(now);
+        }
+        self.probing = false;
+    }
+}
+
+/**
+ * CircuitProbe is taken by a request allowed by a half open circuit
+ * The probe slot is released when it is dropped, a request throttled, rejected or cancelled before its result is recorded can't lock the endpoint out
+ */
+#[derive(Debug, Default)]
+pub struct CircuitProbe {
+    breaker: Option<Arc<Mutex<CircuitBreaker>>>,
+}
+impl CircuitProbe {
+    // allow take the probe slot if circuit is half open, None if the request is rejected
+    pub fn allow(breaker: &Arc<Mutex<CircuitBreaker>>, now: Instant) -> Option<CircuitProbe> {
+        let mut b = breaker.lock().unwrap();
+        let probing = b.state(now) == CircuitState::HalfOpen;
+        if !b.allow(now) {
+            return None;
+        }
+        Some(CircuitProbe {
+            breaker: probing.then(|| breaker.clone()),
+        })
+    }
+}
+impl Drop for CircuitProbe {
+    fn drop(&mut self) {
+        if let Some(breaker) = self.breaker.take() {
+            breaker.lock().unwrap().release_probe();
+        }
+    }
+}
+
+static CIRCUIT_BREAKERS: Lazy<Mutex<HashMap<String, Arc<Mutex<CircuitBreaker>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// shared_circuit_breaker return the breaker of an endpoint url, options of the last client created are applied
+pub fn shared_circuit_breaker(
+    url: Option<&str>,
+    threshold: u32,
+    cooldown: Duration,
+) -> Arc<Mutex<CircuitBreaker>> {
+    let url = match url {
+        Some(url) => url,
+        None => return Arc::new(Mutex::new(CircuitBreaker::new(threshold, cooldown))),
+    };
+    let mut breakers = CIRCUIT_BREAKERS.lock().unwrap();
+    let breaker = breakers
+        .entry(url.to_string())
+        .or_insert_with(|| Arc::new(Mutex::new(CircuitBreaker::new(threshold, cooldown))))
+        .clone();
+    {
+        let mut b = breaker.lock().unwrap();
+        b.threshold = threshold;
+        b.cooldown = cooldown;
+    }
+    breaker
+}
+
+// circuit_state return circuit state of an endpoint url, closed if no request was sent yet
+pub fn circuit_state(url: &str) -> CircuitState {
+    let breakers = CIRCUIT_BREAKERS.lock().unwrap();
+    breakers
+        .get(url)
+        .map(|b| b.lock().unwrap().state(Instant::now()))
+        .unwrap_or_default()
+}
+
+// backoff return delay before retry `attempt` (0 based), delay is multiplied by factor at each try up to max
+// with jitter the delay is randomized in [delay / 2, delay] so clients don't retry in lockstep
+pub fn backoff(delay: u32, factor: u32, max: u32, attempt: u32, jitter: bool) -> Duration {
+    let delay = (delay as u64)
+        .saturating_mul((factor.max(1) as u64).saturating_pow(attempt))
+        .min(max.max(delay) as u64);
+    let delay = Duration::from_secs(delay);
+    if !jitter || delay.is_zero() {
+        return delay;
+    }
+    delay.mul_f64(rand::random::<f64>() * 0.5 + 0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circuit_breaker_states() {
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        let now = Instant::now();
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(now), CircuitState::Closed);
+        // a success reset consecutive failures
+        breaker.record_success();
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert!(breaker.allow(now));
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(now), CircuitState::Open);
+        assert!(!breaker.allow(now + Duration::from_secs(59)));
+
+        // after cooldown a single probe is allowed
+        let later = now + Duration::from_secs(60);
+        assert_eq!(breaker.state(later), CircuitState::HalfOpen);
+        assert!(breaker.allow(later));
+        assert!(!breaker.allow(later));
+        // failed probe re open circuit for a new cooldown
+        breaker.record_failure(later);
+        assert_eq!(breaker.state(later), CircuitState::Open);
+        let later = later + Duration::from_secs(60);
+        assert!(breaker.allow(later));
+        breaker.record_success();
+        assert_eq!(breaker.state(later), CircuitState::Closed);
+
+        // dropped probe without result let the next request probe
+        let later = later + Duration::from_secs(60);
+        breaker.record_failure(later);
+        breaker.record_failure(later);
+        breaker.record_failure(later);
+        let breaker = Arc::new(Mutex::new(breaker));
+        let later = later + Duration::from_secs(60);
+        let probe = CircuitProbe::allow(&breaker, later).unwrap();
+        assert!(CircuitProbe::allow(&breaker, later).is_none());
+        drop(probe);
+        assert!(CircuitProbe::allow(&breaker, later).is_some());
+        assert_eq!(breaker.lock().unwrap().state(later), CircuitState::HalfOpen);
+
+        // threshold 0 disable circuit breaker
+        let mut breaker = CircuitBreaker::new(0, Duration::from_secs(60));
+        (0..10).for_each(|_| breaker.record_failure(now));
+        assert!(breaker.allow(now));
+    }
+
+    #[test]
+    fn circuit_breaker_backoff() {
+        assert_eq!(backoff(1, 2, 30, 0, false), Duration::from_secs(1));
+        assert_eq!(backoff(1, 2, 30, 3, false), Duration::from_secs(8));
+        assert_eq!(backoff(1, 2, 30, 10, false), Duration::from_secs(30));
+        assert_eq!(backoff(5, 1, 30, 4, false), Duration::from_secs(5));
+        assert_eq!(backoff(0, 2, 30, 4, true), Duration::ZERO);
+        // max lower than delay keep configured delay
+        assert_eq!(backoff(10, 2, 5, 2, false), Duration::from_secs(10));
+        for attempt in 0..5 {
+            let d = backoff(2, 2, 30, attempt, true);
+            let expected = Duration::from_secs((2 << attempt).min(30));
+            assert!(d >= expected / 2 && d <= expected);
+        }
+    }
+}
@@ -1,45 +1,156 @@
+use super::circuit_breaker::{backoff, shared_circuit_breaker, CircuitBreaker};
+use super::rate_limit::{shared_rate_limiter, RateLimiter};
 use crate::conf::EndpointOptions;
-use std::time::{SystemTime, UNIX_EPOCH};
+use reqwest::Client;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+// MAX_LATENCIES is the number of response times kept until they are taken
+const MAX_LATENCIES: usize = 100;
+
+/**
+ * ReqwestClient own a long lived http client per endpoint, connections and tls sessions are pooled between requests
+ * Clones share the same connection pool
+ * rate_limiter and circuit_breaker are shared by every client of the same endpoint url
+ * latencies are response times in milliseconds of requests sent since they were last taken
+ * last_request is the last request time in milliseconds, checked against endpoint scrapping rate
+ * clones share latencies and last_request
+ */
 #[derive(Debug, Clone)]
 pub struct ReqwestClient {
-    // pub client: Client,
+    pub client: Client,
     pub config: EndpointOptions,
-    pub last_request: u64,
+    last_request: Arc<AtomicU64>,
     pub alias: String,
+    pub rate_limiter: Arc<Mutex<RateLimiter>>,
+    pub circuit_breaker: Arc<Mutex<CircuitBreaker>>,
+    latencies: Arc<Mutex<Vec<u64>>>,
 }
 
 impl ReqwestClient {
     pub fn new(config: EndpointOptions) -> ReqwestClient {
         ReqwestClient {
+            client: build_client(&config),
+            rate_limiter: shared_rate_limiter(config.url.as_deref(), &config.rate_limits),
+            circuit_breaker: shared_circuit_breaker(
+                config.url.as_deref(),
+                config.breaker_threshold,
+                Duration::from_secs(config.breaker_cooldown as u64),
+            ),
             alias: config.alias.clone(),
             config,
-            last_request: 0,
+            last_request: Arc::new(AtomicU64::new(0)),
+            latencies: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+    // record_latency store a request response time, oldest ones are dropped if latencies are not taken
+    pub fn record_latency(&self, latency: u128) {
+        let mut latencies = self.latencies.lock().unwrap();
+        if latencies.len() >= MAX_LATENCIES {
+            latencies.remove(0);
         }
+        latencies.push(latency as u64);
+    }
+    // take_latencies return response times of requests sent since last call
+    pub fn take_latencies(&self) -> Vec<u64> {
+        std::mem::take(&mut *self.latencies.lock().unwrap())
+    }
+    // retry_delay return delay before retry of try `attempt`, exponential backoff with jitter
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+        backoff(
+            self.config.delay,
+            self.config.backoff_factor,
+            self.config.backoff_max,
+            attempt,
+            self.config.jitter,
+        )
     }
-    pub async fn iddle(&self) {
-        tokio::time::sleep(tokio::time::Duration::from_secs(self.config.delay as u64)).await;
+    // iddle wait before next try, there is no wait after the last one
+    pub async fn iddle(&self, attempt: u32, tries: u32) {
+        if attempt + 1 >= tries {
+            return;
+        }
+        let delay = self.retry_delay(attempt);
+        debug!(
+            "{} retry in {}ms",
+            self.config.url.clone().unwrap_or("UNSET_URL".to_string()),
+            delay.as_millis()
+        );
+        tokio::time::sleep(delay).await;
     }
+    // set_last_request store last request time in milliseconds
     pub fn set_last_request(&mut self) {
-        self.last_request = SystemTime::now()
+        let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Time went backwards")
-            .as_secs();
+            .as_millis() as u64;
+        self.last_request.store(now, Ordering::Relaxed);
+    }
+    pub fn last_request(&self) -> u64 {
+        self.last_request.load(Ordering::Relaxed)
     }
+    // restore_last_request reset last request time, requests sent since then don't consume scrapping rate
+    pub fn restore_last_request(&self, last_request: u64) {
+        self.last_request.store(last_request, Ordering::Relaxed);
+    }
+    // available return false if scrapping rate is not elapsed, endpoint asked to back off or its circuit is open
     pub fn available(&self) -> bool {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Time went backwards")
-            .as_secs();
-        let diff = now - self.last_request;
-        if diff < self.config.rate as u64 {
+            .as_millis() as u64;
+        let diff = now.saturating_sub(self.last_request());
+        if diff < self.config.rate as u64 * 1000 {
             debug!(
-                "Rate limit reached for {} ({}s)",
+                "Rate limit reached for {} ({}ms)",
                 self.config.url.clone().unwrap_or("UNSET_URL".to_string()),
                 diff
             );
             return false;
         }
+        if self.rate_limiter.lock().unwrap().is_blocked(Instant::now()) {
+            debug!(
+                "Endpoint {} asked to back off",
+                self.config.url.clone().unwrap_or("UNSET_URL".to_string())
+            );
+            return false;
+        }
+        if !self
+            .circuit_breaker
+            .lock()
+            .unwrap()
+            .available(Instant::now())
+        {
+            debug!(
+                "Endpoint {} circuit is open",
+                self.config.url.clone().unwrap_or("UNSET_URL".to_string())
+            );
+            return false;
+        }
         true
     }
 }
+
+// build_client configure connection pool, keepalive and http version from endpoint options
+fn build_client(config: &EndpointOptions) -> Client {
+    let builder = Client::builder()
+        .pool_max_idle_per_host(config.pool_max_idle)
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout as u64))
+        .tcp_keepalive(Duration::from_secs(config.tcp_keepalive as u64))
+        .connect_timeout(Duration::from_secs(config.timeout as u64));
+    let builder = if config.http2 {
+        builder
+    } else {
+        builder.http1_only()
+    };
+    builder.build().unwrap_or_else(|e| {
+        error!(
+            "Error building http client, fallback to default client: {}",
+            e
+        );
+        Client::new()
+    })
+}
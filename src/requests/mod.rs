@@ -1,2 +1,5 @@
+pub mod circuit_breaker;
 pub mod client;
-pub mod rpc;
\ No newline at end of file
+pub mod rate_limit;
+pub mod rpc;
+pub mod subscription;
\ No newline at end of file
@@ -0,0 +1,260 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::DateTime;
+use once_cell::sync::Lazy;
+use reqwest::header::HeaderMap;
+
+use crate::conf::RateLimit;
+
+// epoch seconds are distinguished from delays in reset headers by their magnitude
+const EPOCH_THRESHOLD: u64 = 1_000_000_000;
+const REMAINING_HEADERS: [&str; 2] = ["x-ratelimit-remaining", "ratelimit-remaining"];
+const RESET_HEADERS: [&str; 2] = ["x-ratelimit-reset", "ratelimit-reset"];
+
+/**
+ * TokenBucket allow `capacity` requests per period, tokens are refilled continuously
+ */
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    updated: Instant,
+}
+impl TokenBucket {
+    fn new(limit: &RateLimit, now: Instant) -> TokenBucket {
+        let capacity = limit.requests as f64;
+        TokenBucket {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / limit.per.seconds() as f64,
+            updated: now,
+        }
+    }
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated = now;
+    }
+    // wait return delay until a token is available
+    fn wait(&self) -> Duration {
+        if self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        if self.refill_per_sec <= 0.0 {
+            return Duration::MAX;
+        }
+        Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_sec)
+    }
+}
+
+/**
+ * RateLimiter hold a token bucket per configured limit, a request take a token in every bucket
+ * Endpoints can ask to back off with Retry-After or X-RateLimit-* headers, every request is blocked until the deadline
+ * Limiters are shared by every client of an endpoint (collector, proxy, subscriptions)
+ */
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: Vec<RateLimit>,
+    buckets: Vec<TokenBucket>,
+    blocked_until: Option<Instant>,
+    // remaining is the last quota reported by endpoint headers
+    remaining: Option<u64>,
+}
+impl RateLimiter {
+    pub fn new(limits: &[RateLimit]) -> RateLimiter {
+        let now = Instant::now();
+        RateLimiter {
+            limits: limits.to_vec(),
+            buckets: limits.iter().map(|l| TokenBucket::new(l, now)).collect(),
+            blocked_until: None,
+            remaining: None,
+        }
+    }
+    // try_acquire take a token in every bucket, or return the delay until a request is allowed
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        let wait = self.wait(now);
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        self.buckets.iter_mut().for_each(|b| b.tokens -= 1.0);
+        self.remaining = self.remaining.map(|r| r.saturating_sub(1));
+        Ok(())
+    }
+    // wait return delay until a request is allowed, zero if a request can be sent now
+    pub fn wait(&mut self, now: Instant) -> Duration {
+        let blocked = self
+            .blocked_until
+            .map(|until| until.saturating_duration_since(now))
+            .unwrap_or_default();
+        self.buckets.iter_mut().for_each(|b| b.refill(now));
+        self.buckets
+            .iter()
+            .map(|b| b.wait())
+            .fold(blocked, Duration::max)
+    }
+    pub fn is_blocked(&self, now: Instant) -> bool {
+        self.blocked_until.is_some_and(|until| until > now)
+    }
+    // block_until stop requests until deadline, the latest deadline win
+    pub fn block_until(&mut self, until: Instant) {
+        if self.blocked_until.map_or(true, |b| b < until) {
+            self.blocked_until = Some(until);
+        }
+    }
+    // set_remaining align buckets on the quota reported by endpoint
+    pub fn set_remaining(&mut self, remaining: u64) {
+        self.remaining = Some(remaining);
+        self.buckets
+            .iter_mut()
+            .for_each(|b| b.tokens = b.tokens.min(remaining as f64));
+    }
+    // remaining return quota reported by endpoint, or the lowest bucket level
+    pub fn remaining(&self) -> Option<u64> {
+        self.remaining
+            .or_else(|| self.buckets.iter().map(|b| b.tokens.max(0.0) as u64).min())
+    }
+    // update_from_headers apply backoff and quota sent by endpoint
+    pub fn update_from_headers(&mut self, headers: &HeaderMap, status: u16) {
+        let now = Instant::now();
+        if let Some(remaining) = header_u64(headers, &REMAINING_HEADERS) {
+            self.set_remaining(remaining);
+            if remaining == 0 {
+                if let Some(reset) = parse_reset(headers, SystemTime::now()) {
+                    self.block_until(now + reset);
+                }
+            }
+        }
+        if status == 429 || status == 503 {
+            if let Some(retry_after) = parse_retry_after(headers, SystemTime::now()) {
+                self.block_until(now + retry_after);
+            }
+        }
+    }
+}
+
+static RATE_LIMITERS: Lazy<Mutex<HashMap<String, Arc<Mutex<RateLimiter>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// shared_rate_limiter return the limiter of an endpoint url, it is replaced if limits changed
+pub fn shared_rate_limiter(url: Option<&str>, limits: &[RateLimit]) -> Arc<Mutex<RateLimiter>> {
+    let url = match url {
+        Some(url) => url,
+        None => return Arc::new(Mutex::new(RateLimiter::new(limits))),
+    };
+    let mut limiters = RATE_LIMITERS.lock().unwrap();
+    match limiters.get(url) {
+        Some(limiter) if limiter.lock().unwrap().limits == limits => limiter.clone(),
+        _ => {
+            let limiter = Arc::new(Mutex::new(RateLimiter::new(limits)));
+            limiters.insert(url.to_string(), limiter.clone());
+            limiter
+        }
+    }
+}
+
+fn header_u64(headers: &HeaderMap, names: &[&str]) -> Option<u64> {
+    names
+        .iter()
+        .filter_map(|name| headers.get(*name))
+        .filter_map(|v| v.to_str().ok())
+        .find_map(|v| v.trim().parse::<f64>().ok())
+        .map(|v| v.max(0.0) as u64)
+}
+
+// parse_retry_after read Retry-After header, either a delay in seconds or an http date
+pub fn parse_retry_after(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    let date = SystemTime::UNIX_EPOCH + Duration::from_secs(date.timestamp().max(0) as u64);
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+// parse_reset read X-RateLimit-Reset header, either a delay in seconds or an epoch timestamp
+fn parse_reset(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let reset = header_u64(headers, &RESET_HEADERS)?;
+    if reset < EPOCH_THRESHOLD {
+        return Some(Duration::from_secs(reset));
+    }
+    let reset = SystemTime::UNIX_EPOCH + Duration::from_secs(reset);
+    Some(reset.duration_since(now).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conf::RatePeriod;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn rate_limit_token_bucket() {
+        let mut limiter = RateLimiter::new(&[
+            RateLimit {
+                requests: 2,
+                per: RatePeriod::Second,
+            },
+            RateLimit {
+                requests: 3,
+                per: RatePeriod::Minute,
+            },
+        ]);
+        let now = Instant::now();
+        assert!(limiter.try_acquire(now).is_ok());
+        assert!(limiter.try_acquire(now).is_ok());
+        // per second bucket is empty, a token is back in half a second
+        let wait = limiter.try_acquire(now).unwrap_err();
+        assert!(wait <= Duration::from_millis(500) && wait > Duration::from_millis(400));
+        let now = now + Duration::from_secs(1);
+        assert!(limiter.try_acquire(now).is_ok());
+        // per minute bucket is empty
+        let wait = limiter
+            .try_acquire(now + Duration::from_secs(1))
+            .unwrap_err();
+        assert!(wait > Duration::from_secs(15));
+        assert_eq!(limiter.remaining(), Some(0));
+    }
+
+    #[test]
+    fn rate_limit_headers() {
+        let mut limiter = RateLimiter::new(&[]);
+        let now = Instant::now();
+        assert!(limiter.try_acquire(now).is_ok());
+        assert_eq!(limiter.remaining(), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Retry-After", HeaderValue::from_static("30"));
+        limiter.update_from_headers(&headers, 429);
+        assert!(limiter.is_blocked(Instant::now()));
+        let wait = limiter.wait(Instant::now());
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-RateLimit-Remaining", HeaderValue::from_static("0"));
+        headers.insert("X-RateLimit-Reset", HeaderValue::from_static("60"));
+        let mut limiter = RateLimiter::new(&[]);
+        limiter.update_from_headers(&headers, 200);
+        assert_eq!(limiter.remaining(), Some(0));
+        assert!(limiter.wait(Instant::now()) > Duration::from_secs(59));
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1445412470);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Retry-After",
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(
+            parse_retry_after(&headers, now),
+            Some(Duration::from_secs(10))
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert("X-RateLimit-Reset", HeaderValue::from_static("1445412500"));
+        assert_eq!(parse_reset(&headers, now), Some(Duration::from_secs(30)));
+    }
+}
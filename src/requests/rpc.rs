@@ -1,24 +1,55 @@
 use std::str::FromStr;
 
-use super::client::ReqwestClient;
+use super::{
+    circuit_breaker::{CircuitProbe, CircuitState},
+    client::ReqwestClient,
+};
 use crate::{
-    conf::{Network, Protocol},
-    prom::registry::track_status_code,
-    prom::registry::{set_endpoint_status_metric, track_response_time},
+    conf::{EndpointOptions, Network, Protocol},
+    prom::registry::{set_endpoint_circuit_state, set_endpoint_status_metric, track_response_time},
+    prom::registry::{set_rate_limit_remaining, track_request_error, track_status_code},
 };
 use reqwest::{
     header::{HeaderMap, HeaderName},
-    Client, StatusCode,
+    StatusCode,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use serde_json::Error;
+use serde_json::{Error, Value};
 pub const JSON_RPC_VER: &str = "2.0";
+/**
+ * JsonRpcResponse is a single json rpc response, jsonrpc is optional as legacy nodes (bitcoin core) don't return it
+ */
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct JsonRpcResponse<T> {
+    #[serde(default)]
     pub jsonrpc: String,
     pub id: Option<u32>,
     pub result: Option<T>,
-    pub error: Option<String>,
+    pub error: Option<JsonRpcError>,
+}
+impl<T> JsonRpcResponse<T> {
+    // into_result return result, or the rpc error returned by node
+    pub fn into_result(self) -> Result<T, RequestError> {
+        match (self.result, self.error) {
+            (_, Some(error)) => Err(RequestError::Rpc(error)),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(RequestError::EmptyResult),
+        }
+    }
+}
+/**
+ * JsonRpcError is the error object of a json rpc response
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Value>,
+}
+impl std::fmt::Display for JsonRpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "rpc error {}: {}", self.code, self.message)
+    }
 }
 #[derive(Deserialize, Debug, Clone)]
 pub enum JsonRpcParams {
@@ -65,78 +96,123 @@ impl Serialize for JsonRpcReqBody {
         }
     }
 }
+/**
+ * RequestError is returned by endpoint requests, kind is used as metric label
+ */
 #[derive(Debug)]
 pub enum RequestError {
     UndefinedUrl,
-    EndpointReachRateLimit(String),
-    DeserializeRequestError(Error),
+    SerializeRequest(Error),
+    Transport(reqwest::Error),
+    Timeout(String),
+    HttpStatus { url: String, status: u16 },
+    RateLimited(String),
+    CircuitOpen(String),
+    Rpc(JsonRpcError),
+    EmptyResult,
+    Decode(Error),
+}
+impl RequestError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RequestError::UndefinedUrl => "undefined_url",
+            RequestError::SerializeRequest(_) => "serialize",
+            RequestError::Transport(_) => "transport",
+            RequestError::Timeout(_) => "timeout",
+            RequestError::HttpStatus { .. } => "http_status",
+            RequestError::RateLimited(_) => "rate_limited",
+            RequestError::CircuitOpen(_) => "circuit_open",
+            RequestError::Rpc(_) => "rpc",
+            RequestError::EmptyResult => "empty_result",
+            RequestError::Decode(_) => "decode",
+        }
+    }
 }
 impl std::fmt::Display for RequestError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             RequestError::UndefinedUrl => write!(f, "Undefined url"),
-            RequestError::EndpointReachRateLimit(url) => {
-                write!(f, "Endpoint reach internal rate limit: {}", url)
-            }
-            RequestError::DeserializeRequestError(e) => {
-                write!(f, "Deserialize request error: {}", e)
+            RequestError::SerializeRequest(e) => write!(f, "Serialize request error: {}", e),
+            RequestError::Transport(e) => write!(f, "Transport error: {}", e),
+            RequestError::Timeout(url) => write!(f, "Request timeout: {}", url),
+            RequestError::HttpStatus { url, status } => {
+                write!(f, "Http status code {}: {}", status, url)
             }
+            RequestError::RateLimited(url) => write!(f, "Endpoint rate limited: {}", url),
+            RequestError::CircuitOpen(url) => write!(f, "Endpoint circuit open: {}", url),
+            RequestError::Rpc(e) => write!(f, "{}", e),
+            RequestError::EmptyResult => write!(f, "Empty rpc result"),
+            RequestError::Decode(e) => write!(f, "Decode response error: {}", e),
         }
     }
 }
 impl std::error::Error for RequestError {}
 
-impl ReqwestClient {
-    fn get_timout(&self) -> tokio::time::Duration {
-        tokio::time::Duration::from_secs(self.config.timeout as u64)
+// count_rpc_errors return number of error objects in a single or batch json rpc response
+fn count_rpc_errors(response: &Value) -> usize {
+    let has_error = |r: &Value| r.get("error").is_some_and(|e| !e.is_null());
+    match response {
+        Value::Array(batch) => batch.iter().filter(|r| has_error(r)).count(),
+        r => has_error(r) as usize,
     }
-    fn get_headers(&self) -> HeaderMap {
-        let mut headers = HeaderMap::new();
-        match &self.config.headers {
-            Some(h) => {
-                for (k, v) in h {
-                    let key = match HeaderName::from_str(k) {
-                        Ok(key) => key,
-                        Err(e) => {
-                            error!("Error parsing header name: {}", e);
-                            continue;
-                        }
-                    };
-                    match v.parse() {
-                        Ok(v) => {
-                            headers.insert(key, v);
-                        }
-                        Err(e) => {
-                            error!("Error parsing header value: {}", e);
-                            continue;
-                        }
-                    };
-                }
+}
+
+// endpoint_headers build request headers from endpoint options, used by clients and subscriptions
+pub(crate) fn endpoint_headers(options: &EndpointOptions) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    match &options.headers {
+        Some(h) => {
+            for (k, v) in h {
+                let key = match HeaderName::from_str(k) {
+                    Ok(key) => key,
+                    Err(e) => {
+                        error!("Error parsing header name: {}", e);
+                        continue;
+                    }
+                };
+                match v.parse() {
+                    Ok(v) => {
+                        headers.insert(key, v);
+                    }
+                    Err(e) => {
+                        error!("Error parsing header value: {}", e);
+                        continue;
+                    }
+                };
             }
-            None => {}
         }
-        match "application/json".parse() {
-            Ok(v) => {
-                headers.insert("Content-Type", v);
-            }
-            Err(e) => {
-                error!("Error parsing header value: {}", e);
-            }
+        None => {}
+    }
+    match "application/json".parse() {
+        Ok(v) => {
+            headers.insert("Content-Type", v);
         }
+        Err(e) => {
+            error!("Error parsing header value: {}", e);
+        }
+    }
+
+    headers
+}
 
-        headers
+impl ReqwestClient {
+    fn get_timout(&self) -> tokio::time::Duration {
+        tokio::time::Duration::from_secs(self.config.timeout as u64)
+    }
+    pub(crate) fn get_headers(&self) -> HeaderMap {
+        endpoint_headers(&self.config)
     }
-    pub async fn rpc<T: DeserializeOwned>(
+    pub async fn rpc<T: DeserializeOwned, B: Serialize>(
         &mut self,
-        body: &JsonRpcReqBody,
+        body: &B,
         protocol: &Protocol,
         network: &Network,
-    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<T, RequestError> {
         let b = serde_json::to_string(&body);
         let b = match b {
             Ok(b) => b,
             Err(e) => {
-                return Err(Box::new(RequestError::DeserializeRequestError(e)));
+                return Err(RequestError::SerializeRequest(e));
             }
         };
         trace!("RPC request: {}", &b);
@@ -144,16 +220,19 @@ impl ReqwestClient {
         let url = match url {
             Some(url) => url,
             None => {
-                return Err(Box::new(RequestError::UndefinedUrl));
+                return Err(RequestError::UndefinedUrl);
             }
         };
-        let mut c = 0;
-        for i in 0..self.config.retry {
-            c += i; // count for logger
+        let tries = self.config.retry.max(1);
+        let mut last_error = None;
+        for i in 0..tries {
+            self.acquire(&url, protocol, network).await?;
+            // probe slot is released at the end of the try if no result was recorded
+            let _probe = self.circuit_allow(&url, protocol, network)?;
             let time_start = std::time::Instant::now();
-            let client = Client::new();
             let headers = self.get_headers();
-            let request = client
+            let request = self
+                .client
                 .post(&url)
                 .body(b.clone())
                 .headers(headers)
@@ -169,51 +248,91 @@ impl ReqwestClient {
                 Ok(response) => response,
                 Err(e) => {
                     error!(
-                        "rpc {} request {} return error code {:?} source: {}, retrying in {} seconds, tries {} on {} ",
-                        &url, &b,e.status(),e.to_string(),  self.config.delay, i, self.config.retry
+                        "rpc {} request {} return error code {:?} source: {}, tries {} on {} ",
+                        &url,
+                        &b,
+                        e.status(),
+                        e.to_string(),
+                        i,
+                        tries
                     );
-                    // As we wait for a response to continue to process data, iter on timeout will take too much time
-                    if e.is_timeout() {
-                        error!("Timeout detected skip this requests...");
-                        track_status_code(&url, &self.alias, "POST", 504, protocol, network);
-                        return Err(Box::new(e));
-                    }
+                    let (error, status) = if e.is_timeout() {
+                        (
+                            RequestError::Timeout(url.clone()),
+                            StatusCode::GATEWAY_TIMEOUT,
+                        )
+                    } else {
+                        let status = e.status().unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+                        (RequestError::Transport(e), status)
+                    };
                     track_status_code(
                         &url,
                         &self.alias,
                         "POST",
-                        e.status()
-                            .unwrap_or(StatusCode::SERVICE_UNAVAILABLE)
-                            .as_u16(),
+                        status.as_u16(),
                         protocol,
                         network,
                     );
-                    self.iddle().await;
+                    last_error = Some(self.request_error(error, &url, protocol, network));
+                    self.circuit_record(false, &url, protocol, network);
+                    self.iddle(i, tries).await;
                     continue;
                 }
             };
 
+            self.record_latency(time_duration);
             let status = response.status().as_u16();
             debug!(
                 "POST {} {} {} {}ms",
                 &url, &self.alias, status, time_duration
             );
             track_status_code(&url, &self.alias, "POST", status, protocol, network);
+            self.update_rate_limit(response.headers(), status, &url, protocol, network);
             if status == StatusCode::TOO_MANY_REQUESTS.as_u16() {
-                error!("rpc {} return too many request, skipping this request", url,);
-                return Err(Box::new(RequestError::EndpointReachRateLimit(
-                    url.to_string(),
-                )));
+                // next try wait for the backoff asked by endpoint, or fail if it is too long
+                error!(
+                    "rpc {} return too many request, tries {} on {}",
+                    url, i, tries
+                );
+                last_error = Some(self.request_error(
+                    RequestError::RateLimited(url.clone()),
+                    &url,
+                    protocol,
+                    network,
+                ));
+                self.iddle_rate_limited(i, tries).await;
+                continue;
             }
             if status != StatusCode::OK.as_u16() {
                 error!(
-                    "rpc {} status code {}, retrying in {} seconds, tries {} on {}, body: {}",
-                    url, status, self.config.delay, i, self.config.retry, &b
+                    "rpc {} status code {}, tries {} on {}, body: {}",
+                    url, status, i, tries, &b
                 );
-                self.iddle().await;
+                last_error = Some(self.request_error(
+                    RequestError::HttpStatus {
+                        url: url.clone(),
+                        status,
+                    },
+                    &url,
+                    protocol,
+                    network,
+                ));
+                self.circuit_record(false, &url, protocol, network);
+                self.iddle(i, tries).await;
                 continue;
             }
-            let txt = response.text().await?;
+            self.circuit_record(true, &url, protocol, network);
+            let txt = match response.text().await {
+                Ok(txt) => txt,
+                Err(e) => {
+                    return Err(self.request_error(
+                        RequestError::Transport(e),
+                        &url,
+                        protocol,
+                        network,
+                    ))
+                }
+            };
             set_endpoint_status_metric(&url, &self.alias, protocol, network, true);
             track_response_time(
                 &url,
@@ -223,18 +342,142 @@ impl ReqwestClient {
                 network,
                 time_duration,
             );
-            let r: Result<T, Error> = serde_json::from_str(&txt);
+            let r: Result<T, Error> = serde_json::from_str::<Value>(&txt).and_then(|value| {
+                // json rpc errors are returned to callers, they are only counted here
+                for _ in 0..count_rpc_errors(&value) {
+                    track_request_error(&url, &self.alias, protocol, network, "rpc");
+                }
+                serde_json::from_value(value)
+            });
             match r {
                 Ok(r) => return Ok(r),
                 Err(e) => {
                     error!("rpc decode {} response error: {}\nraw : {}", url, e, &txt);
-                    return Err(e.into());
+                    return Err(self.request_error(
+                        RequestError::Decode(e),
+                        &url,
+                        protocol,
+                        network,
+                    ));
                 }
             }
         }
         // After all retry, set endpoint down and return error
+        error!("rpc {} fail after {} tries", &url, tries);
         set_endpoint_status_metric(&url, &self.alias, protocol, network, false);
-        Err(format!("rpc {} fail after {} retry", &url, &c).into())
+        Err(last_error.expect("at least one try"))
+    }
+
+    // acquire wait for a token of endpoint rate limiter, requests are not sent if the wait exceed request timeout
+    async fn acquire(
+        &self,
+        url: &str,
+        protocol: &Protocol,
+        network: &Network,
+    ) -> Result<(), RequestError> {
+        loop {
+            let res = self
+                .rate_limiter
+                .lock()
+                .unwrap()
+                .try_acquire(std::time::Instant::now());
+            match res {
+                Ok(()) => break,
+                Err(wait) if wait <= self.get_timout() => {
+                    debug!("{} rate limited, wait {}ms", url, wait.as_millis());
+                    tokio::time::sleep(wait).await;
+                }
+                Err(wait) => {
+                    warn!("{} rate limited for {}s, skip request", url, wait.as_secs());
+                    return Err(self.request_error(
+                        RequestError::RateLimited(url.to_string()),
+                        url,
+                        protocol,
+                        network,
+                    ));
+                }
+            }
+        }
+        if let Some(remaining) = self.rate_limiter.lock().unwrap().remaining() {
+            set_rate_limit_remaining(url, &self.alias, protocol, network, remaining);
+        }
+        Ok(())
+    }
+    // iddle_rate_limited wait retry delay if endpoint didn't say how long to back off, otherwise acquire wait for it
+    async fn iddle_rate_limited(&self, attempt: u32, tries: u32) {
+        let blocked = self
+            .rate_limiter
+            .lock()
+            .unwrap()
+            .is_blocked(std::time::Instant::now());
+        if !blocked {
+            self.iddle(attempt, tries).await;
+        }
+    }
+    // update_rate_limit apply backoff and quota sent by endpoint headers
+    fn update_rate_limit(
+        &self,
+        headers: &HeaderMap,
+        status: u16,
+        url: &str,
+        protocol: &Protocol,
+        network: &Network,
+    ) {
+        let mut limiter = self.rate_limiter.lock().unwrap();
+        limiter.update_from_headers(headers, status);
+        if let Some(remaining) = limiter.remaining() {
+            set_rate_limit_remaining(url, &self.alias, protocol, network, remaining);
+        }
+    }
+    // circuit_allow reject request if endpoint circuit is open, half open circuit let a single probe go
+    fn circuit_allow(
+        &self,
+        url: &str,
+        protocol: &Protocol,
+        network: &Network,
+    ) -> Result<CircuitProbe, RequestError> {
+        let now = std::time::Instant::now();
+        if let Some(probe) = CircuitProbe::allow(&self.circuit_breaker, now) {
+            return Ok(probe);
+        }
+        debug!("{} circuit open, skip request", url);
+        let state = self.circuit_breaker.lock().unwrap().state(now);
+        set_endpoint_circuit_state(url, &self.alias, protocol, network, state);
+        Err(self.request_error(
+            RequestError::CircuitOpen(url.to_string()),
+            url,
+            protocol,
+            network,
+        ))
+    }
+    // circuit_record count endpoint success or failure in circuit breaker
+    fn circuit_record(&self, success: bool, url: &str, protocol: &Protocol, network: &Network) {
+        let now = std::time::Instant::now();
+        let mut breaker = self.circuit_breaker.lock().unwrap();
+        let previous = breaker.state(now);
+        if success {
+            breaker.record_success();
+        } else {
+            breaker.record_failure(now);
+        }
+        let state = breaker.state(now);
+        if state != previous && state == CircuitState::Open {
+            warn!("{} circuit open after consecutive failures", url);
+        } else if state != previous {
+            info!("{} circuit {}", url, state);
+        }
+        set_endpoint_circuit_state(url, &self.alias, protocol, network, state);
+    }
+    // request_error count error by kind in errors metric and return it
+    fn request_error(
+        &self,
+        error: RequestError,
+        url: &str,
+        protocol: &Protocol,
+        network: &Network,
+    ) -> RequestError {
+        track_request_error(url, &self.alias, protocol, network, error.kind());
+        error
     }
 
     pub async fn run_request<T: DeserializeOwned>(
@@ -244,15 +487,18 @@ impl ReqwestClient {
         url: &str,
         protocol: &Protocol,
         network: &Network,
-    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<T, RequestError> {
         let url = url.to_string();
-        let mut c = 0;
-        for i in 0..self.config.retry {
-            c += i;
+        let tries = self.config.retry.max(1);
+        let mut last_error = None;
+        for i in 0..tries {
+            self.acquire(&url, protocol, network).await?;
+            // probe slot is released at the end of the try if no result was recorded
+            let _probe = self.circuit_allow(&url, protocol, network)?;
             let time_start = std::time::Instant::now();
-            let client = Client::new();
             let headers = self.get_headers();
-            let request = client
+            let request = self
+                .client
                 .request(method.clone(), &url)
                 .headers(headers)
                 .timeout(self.get_timout());
@@ -272,13 +518,21 @@ impl ReqwestClient {
                 Ok(response) => response,
                 Err(e) => {
                     error!(
-                        "Error: {} {} request error, retrying in {} seconds, tries {} on {} : {} ",
-                        &method, url, self.config.delay, i, self.config.retry, e
+                        "Error: {} {} request error, tries {} on {} : {} ",
+                        &method, url, i, tries, e
                     );
-                    self.iddle().await;
+                    let error = if e.is_timeout() {
+                        RequestError::Timeout(url.clone())
+                    } else {
+                        RequestError::Transport(e)
+                    };
+                    last_error = Some(self.request_error(error, &url, protocol, network));
+                    self.circuit_record(false, &url, protocol, network);
+                    self.iddle(i, tries).await;
                     continue;
                 }
             };
+            self.record_latency(time_duration);
             let status = response.status().as_u16();
             track_status_code(
                 &url,
@@ -288,25 +542,53 @@ impl ReqwestClient {
                 protocol,
                 network,
             );
+            self.update_rate_limit(response.headers(), status, &url, protocol, network);
 
             if status != 200 {
                 error!(
-                    "{} {} status code {}, retrying in {} seconds, tries {} on {} ",
-                    &method, url, status, self.config.delay, i, self.config.retry
+                    "{} {} status code {}, tries {} on {} ",
+                    &method, url, status, i, tries
                 );
-                self.iddle().await;
+                if status == StatusCode::TOO_MANY_REQUESTS.as_u16() {
+                    last_error = Some(self.request_error(
+                        RequestError::RateLimited(url.clone()),
+                        &url,
+                        protocol,
+                        network,
+                    ));
+                    self.iddle_rate_limited(i, tries).await;
+                    continue;
+                }
+                last_error = Some(self.request_error(
+                    RequestError::HttpStatus {
+                        url: url.clone(),
+                        status,
+                    },
+                    &url,
+                    protocol,
+                    network,
+                ));
+                self.circuit_record(false, &url, protocol, network);
+                self.iddle(i, tries).await;
                 continue;
             }
+            self.circuit_record(true, &url, protocol, network);
             track_response_time(&url, &self.alias, &method, protocol, network, time_duration);
             let r_txt = response.text().await;
             let r_txt = match r_txt {
                 Ok(r_txt) => r_txt,
                 Err(e) => {
                     error!(
-                        "{} {} response error: {}, retrying in {} seconds, tries {} on {} ",
-                        &method, url, e, self.config.delay, i, self.config.retry
+                        "{} {} response error: {}, tries {} on {} ",
+                        &method, url, e, i, tries
                     );
-                    self.iddle().await;
+                    last_error = Some(self.request_error(
+                        RequestError::Transport(e),
+                        &url,
+                        protocol,
+                        network,
+                    ));
+                    self.iddle(i, tries).await;
                     continue;
                 }
             };
@@ -315,16 +597,19 @@ impl ReqwestClient {
                 Ok(r) => r,
                 Err(e) => {
                     debug!(
-                        "{} {} response decode error: {}, retrying in {} seconds, tries {} on {}\nraw: {} ",
-                        &method, url, e, self.config.delay, i, self.config.retry, &r_txt
+                        "{} {} response decode error: {}, tries {} on {}\nraw: {} ",
+                        &method, url, e, i, tries, &r_txt
                     );
-                    self.iddle().await;
+                    last_error =
+                        Some(self.request_error(RequestError::Decode(e), &url, protocol, network));
+                    self.iddle(i, tries).await;
                     continue;
                 }
             };
             return Ok(r);
         }
-        Err(format!("{} {} fail after {} retry", &method, &url, &c).into())
+        error!("{} {} fail after {} tries", &method, &url, tries);
+        Err(last_error.expect("at least one try"))
     }
 }
 
@@ -335,14 +620,43 @@ mod tests {
     use super::*;
     use crate::{
         conf::{BasicAuth, EndpointOptions},
+        sim::SimApi,
         tests,
     };
+    #[test]
+    fn request_json_rpc_error() {
+        let res: JsonRpcResponse<String> = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32005,"message":"Node is unhealthy","data":{"numSlotsBehind":42}}}"#,
+        )
+        .unwrap();
+        let err = res.into_result().unwrap_err();
+        assert_eq!(err.kind(), "rpc");
+        assert_eq!(err.to_string(), "rpc error -32005: Node is unhealthy");
+        // legacy nodes don't return jsonrpc version
+        let res: JsonRpcResponse<String> =
+            serde_json::from_str(r#"{"id":1,"result":"0xabc","error":null}"#).unwrap();
+        assert_eq!(res.into_result().unwrap(), "0xabc");
+        let res: JsonRpcResponse<String> =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"result":null}"#).unwrap();
+        assert_eq!(res.into_result().unwrap_err().kind(), "empty_result");
+
+        let batch = serde_json::json!([
+            {"jsonrpc": "2.0", "id": 1, "result": "0x1"},
+            {"jsonrpc": "2.0", "id": 2, "error": {"code": -32000, "message": "header not found"}},
+            {"jsonrpc": "2.0", "id": 3, "result": null, "error": null},
+        ]);
+        assert_eq!(count_rpc_errors(&batch), 1);
+        assert_eq!(count_rpc_errors(&batch[0]), 0);
+        assert_eq!(count_rpc_errors(&batch[1]), 1);
+    }
     #[tokio::test]
     async fn request_basic_auth_get() {
         tests::setup();
-        let url = "http://httpbin.org/basic-auth/foo/bar";
+        let mock = tests::mock_server(SimApi::Tzkt);
+        mock.behavior().basic_auth = Some(("foo".to_string(), "bar".to_string()));
+        let url = format!("{}/v1/blocks", mock.url());
         let mut endpoint_options: EndpointOptions = Default::default();
-        endpoint_options.url = Some(url.to_string());
+        endpoint_options.url = Some(mock.url());
         endpoint_options.basic_auth = Some(BasicAuth {
             username: "foo".to_string(),
             password: "bar".to_string(),
@@ -352,11 +666,112 @@ mod tests {
             .run_request::<serde_json::Value>(
                 reqwest::Method::GET,
                 None,
-                url,
+                &url,
                 &Protocol::Tezos,
                 &String::from("mainnet"),
             )
             .await;
         assert!(res.is_ok());
+        assert_eq!(mock.requests(), 1);
+    }
+    #[tokio::test]
+    async fn request_latencies() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::Tzkt);
+        mock.behavior().fail_next = 1;
+        let mut endpoint_options = EndpointOptions::test_new(&mock.url(), None, None);
+        endpoint_options.retry = 2;
+        endpoint_options.delay = 1;
+        endpoint_options.jitter = false;
+        let mut client = ReqwestClient::new(endpoint_options);
+        let res = client
+            .run_request::<serde_json::Value>(
+                reqwest::Method::GET,
+                None,
+                &format!("{}/v1/blocks", mock.url()),
+                &Protocol::Tezos,
+                &String::from("mainnet"),
+            )
+            .await;
+        assert!(res.is_ok());
+        // failed response is timed, retry delay is not
+        let latencies = client.clone().take_latencies();
+        assert_eq!(latencies.len(), 2);
+        assert!(latencies.iter().all(|l| *l < 1000));
+        assert!(client.take_latencies().is_empty());
+    }
+    #[tokio::test]
+    async fn request_rate_limited_backoff() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::Tzkt);
+        // 429 without Retry-After nor configured limits
+        mock.behavior().rate_limit_rate = 1.0;
+        let mut endpoint_options = EndpointOptions::test_new(&mock.url(), None, None);
+        endpoint_options.retry = 2;
+        endpoint_options.delay = 1;
+        endpoint_options.jitter = false;
+        let mut client = ReqwestClient::new(endpoint_options);
+        let start = std::time::Instant::now();
+        let res = client
+            .run_request::<serde_json::Value>(
+                reqwest::Method::GET,
+                None,
+                &format!("{}/v1/blocks", mock.url()),
+                &Protocol::Tezos,
+                &String::from("mainnet"),
+            )
+            .await;
+        assert_eq!(res.unwrap_err().kind(), "rate_limited");
+        assert_eq!(mock.requests(), 2);
+        // retry wait for the backoff delay instead of hammering endpoint
+        assert!(start.elapsed() >= std::time::Duration::from_secs(1));
+    }
+    #[tokio::test]
+    async fn request_half_open_probe_rate_limited() {
+        tests::setup();
+        let mock = tests::mock_server(SimApi::Tzkt);
+        mock.behavior().fail_next = 1;
+        let mut endpoint_options = EndpointOptions::test_new(&mock.url(), None, None);
+        endpoint_options.retry = 1;
+        endpoint_options.breaker_threshold = 1;
+        // circuit is half open as soon as it is opened
+        endpoint_options.breaker_cooldown = 0;
+        let client = ReqwestClient::new(endpoint_options);
+        let url = format!("{}/v1/blocks", mock.url());
+        let request = || {
+            let mut client = client.clone();
+            let url = url.clone();
+            async move {
+                client
+                    .run_request::<serde_json::Value>(
+                        reqwest::Method::GET,
+                        None,
+                        &url,
+                        &Protocol::Tezos,
+                        &String::from("mainnet"),
+                    )
+                    .await
+            }
+        };
+        assert_eq!(request().await.unwrap_err().kind(), "http_status");
+        let state = |client: &ReqwestClient| {
+            client
+                .circuit_breaker
+                .lock()
+                .unwrap()
+                .state(std::time::Instant::now())
+        };
+        assert_eq!(state(&client), CircuitState::HalfOpen);
+        // throttled probe release its slot, circuit is still half open
+        mock.behavior().rate_limit_rate = 1.0;
+        assert_eq!(request().await.unwrap_err().kind(), "rate_limited");
+        assert!(client
+            .circuit_breaker
+            .lock()
+            .unwrap()
+            .available(std::time::Instant::now()));
+        mock.behavior().rate_limit_rate = 0.0;
+        assert!(request().await.is_ok());
+        assert_eq!(state(&client), CircuitState::Closed);
     }
 }
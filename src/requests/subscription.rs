@@ -0,0 +1,395 @@
+use std::time::{Duration, SystemTime};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::{SinkExt, StreamExt};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use serde_json::{json, Value};
+use tokio::{sync::mpsc::Sender, task::JoinHandle};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, Message},
+};
+
+use super::{client::ReqwestClient, rpc::endpoint_headers};
+use crate::{
+    conf::{EndpointOptions, Network, Protocol},
+    prom::registry::set_subscription_status,
+};
+
+/**
+ * Subscription is the push method used by a node provider to stream its new heads
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subscription {
+    // eth_subscribe newHeads over websocket
+    EthereumNewHeads,
+    // chain_subscribeNewHeads over websocket
+    PolkadotNewHeads,
+    // chain_subscribeFinalizedHeads over websocket
+    PolkadotFinalizedHeads,
+    // starknet_subscribeNewHeads over websocket
+    StarknetNewHeads,
+    // /monitor/heads/main http streaming rpc
+    TezosMonitorHeads,
+}
+
+/**
+ * HeadEvent is a new head pushed by a provider subscription
+ * received_at is unix time in seconds with millisecond precision
+ * hash is None when subscription only push headers (polkadot)
+ * finalized is true when head is pushed by a finalized heads subscription
+ */
+#[derive(Debug, Clone)]
+pub struct HeadEvent {
+    pub provider: String,
+    pub height: u64,
+    pub hash: Option<String>,
+    pub received_at: f64,
+    pub finalized: bool,
+}
+
+/**
+ * Subscriptions hold running subscription tasks, tasks are aborted when collector is dropped
+ */
+#[derive(Default)]
+pub struct Subscriptions {
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Subscriptions {
+    // spawn keep subscription alive, reconnecting after options.delay seconds when stream drop
+    pub fn spawn(
+        &mut self,
+        subscription: Subscription,
+        provider: String,
+        options: EndpointOptions,
+        protocol: Protocol,
+        network: Network,
+        tx: Sender<HeadEvent>,
+    ) {
+        let task = tokio::spawn(async move {
+            let url = match options.subscription_url.clone() {
+                Some(url) => url,
+                None => return,
+            };
+            info!(
+                "Subscribe to {} {} new heads from {} ({:?})",
+                protocol, network, provider, subscription
+            );
+            loop {
+                let r = match subscription {
+                    Subscription::TezosMonitorHeads => {
+                        stream_http(
+                            subscription,
+                            &provider,
+                            &url,
+                            &options,
+                            &protocol,
+                            &network,
+                            &tx,
+                        )
+                        .await
+                    }
+                    _ => {
+                        stream_ws(
+                            subscription,
+                            &provider,
+                            &url,
+                            &options,
+                            &protocol,
+                            &network,
+                            &tx,
+                        )
+                        .await
+                    }
+                };
+                set_subscription_status(
+                    options.url.as_deref().unwrap_or_default(),
+                    &options.alias,
+                    &protocol,
+                    &network,
+                    false,
+                );
+                if tx.is_closed() {
+                    return;
+                }
+                match r {
+                    Ok(_) => warn!(
+                        "{} {} subscription of {} closed, fallback to polling",
+                        protocol, network, provider
+                    ),
+                    Err(e) => warn!(
+                        "{} {} subscription of {} failed, fallback to polling: {}",
+                        protocol, network, provider, e
+                    ),
+                }
+                tokio::time::sleep(Duration::from_secs(options.delay.max(1) as u64)).await;
+            }
+        });
+        self.tasks.push(task);
+    }
+}
+
+impl Drop for Subscriptions {
+    fn drop(&mut self) {
+        self.tasks.iter().for_each(|t| t.abort());
+    }
+}
+
+// stream_ws subscribe to new heads over websocket, return when stream is closed
+async fn stream_ws(
+    subscription: Subscription,
+    provider: &str,
+    url: &str,
+    options: &EndpointOptions,
+    protocol: &Protocol,
+    network: &Network,
+    tx: &Sender<HeadEvent>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut request = url.into_client_request()?;
+    request.headers_mut().extend(endpoint_headers(options));
+    if let Some(auth) = &options.basic_auth {
+        let credentials = STANDARD.encode(format!("{}:{}", auth.username, auth.password));
+        request.headers_mut().insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Basic {}", credentials))?,
+        );
+    }
+    let (mut ws, _) = connect_async(request).await?;
+    ws.send(Message::Text(subscribe_request(subscription).to_string()))
+        .await?;
+    set_subscription_status(
+        options.url.as_deref().unwrap_or_default(),
+        &options.alias,
+        protocol,
+        network,
+        true,
+    );
+    while let Some(msg) = ws.next().await {
+        let txt = match msg? {
+            Message::Text(txt) => txt,
+            Message::Close(_) => return Ok(()),
+            _ => continue,
+        };
+        let value: Value = serde_json::from_str(&txt)?;
+        if let Some(error) = value.get("error") {
+            return Err(format!("subscription error: {}", error).into());
+        }
+        // subscription confirmation does not carry any head
+        if let Some((height, hash)) = parse_head(subscription, &value) {
+            if !send_head(tx, subscription, provider, height, hash).await {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+// stream_http read new heads from tezos streaming rpc, every chunk is one or more json objects
+async fn stream_http(
+    subscription: Subscription,
+    provider: &str,
+    url: &str,
+    options: &EndpointOptions,
+    protocol: &Protocol,
+    network: &Network,
+    tx: &Sender<HeadEvent>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let client = ReqwestClient::new(options.clone());
+    let request = client
+        .client
+        .get(format!("{}/monitor/heads/main", url))
+        .headers(client.get_headers());
+    let request = match options.basic_auth.clone() {
+        Some(auth) => request.basic_auth(auth.username, Some(auth.password)),
+        None => request,
+    };
+    let mut response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(format!("monitor heads status code {}", response.status()).into());
+    }
+    set_subscription_status(
+        options.url.as_deref().unwrap_or_default(),
+        &options.alias,
+        protocol,
+        network,
+        true,
+    );
+    let mut buf = String::new();
+    while let Some(chunk) = response.chunk().await? {
+        buf.push_str(&String::from_utf8_lossy(&chunk));
+        let mut heads = Vec::new();
+        let mut stream = serde_json::Deserializer::from_str(&buf).into_iter::<Value>();
+        let mut consumed = 0;
+        let mut invalid = None;
+        while let Some(value) = stream.next() {
+            match value {
+                Ok(value) => {
+                    consumed = stream.byte_offset();
+                    heads.push(value);
+                }
+                // incomplete object is kept until next chunk
+                Err(e) if e.is_eof() => break,
+                Err(e) => {
+                    invalid = Some(e);
+                    break;
+                }
+            }
+        }
+        buf.drain(..consumed);
+        for value in heads {
+            if let Some((height, hash)) = parse_head(subscription, &value) {
+                if !send_head(tx, subscription, provider, height, hash).await {
+                    return Ok(());
+                }
+            }
+        }
+        // stream can't be resynchronized after an invalid object
+        if let Some(e) = invalid {
+            return Err(format!("monitor heads invalid json: {}", e).into());
+        }
+    }
+    Ok(())
+}
+
+// send_head push head to collector, return false if collector is gone
+async fn send_head(
+    tx: &Sender<HeadEvent>,
+    subscription: Subscription,
+    provider: &str,
+    height: u64,
+    hash: Option<String>,
+) -> bool {
+    let received_at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as f64
+        / 1000.0;
+    trace!("{} push head {} {:?}", provider, height, hash);
+    tx.send(HeadEvent {
+        provider: provider.to_string(),
+        height,
+        hash,
+        received_at,
+        finalized: subscription == Subscription::PolkadotFinalizedHeads,
+    })
+    .await
+    .is_ok()
+}
+
+fn subscribe_request(subscription: Subscription) -> Value {
+    let (method, params) = match subscription {
+        Subscription::EthereumNewHeads => ("eth_subscribe", json!(["newHeads"])),
+        Subscription::PolkadotNewHeads => ("chain_subscribeNewHeads", json!([])),
+        Subscription::PolkadotFinalizedHeads => ("chain_subscribeFinalizedHeads", json!([])),
+        Subscription::StarknetNewHeads => ("starknet_subscribeNewHeads", json!({})),
+        Subscription::TezosMonitorHeads => return Value::Null,
+    };
+    json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params})
+}
+
+// parse_head return height and hash of a pushed head, None if message is not a head notification
+fn parse_head(subscription: Subscription, value: &Value) -> Option<(u64, Option<String>)> {
+    let hex_height = |v: &Value| u64::from_str_radix(v.as_str()?.trim_start_matches("0x"), 16).ok();
+    let hash = |v: &Value| v.as_str().map(|h| h.to_string());
+    match subscription {
+        Subscription::EthereumNewHeads => {
+            let head = value.get("params")?.get("result")?;
+            Some((hex_height(head.get("number")?)?, hash(head.get("hash")?)))
+        }
+        Subscription::PolkadotNewHeads | Subscription::PolkadotFinalizedHeads => {
+            let head = value.get("params")?.get("result")?;
+            Some((hex_height(head.get("number")?)?, None))
+        }
+        Subscription::StarknetNewHeads => {
+            let head = value.get("params")?.get("result")?;
+            Some((
+                head.get("block_number")?.as_u64()?,
+                hash(head.get("block_hash")?),
+            ))
+        }
+        Subscription::TezosMonitorHeads => {
+            Some((value.get("level")?.as_u64()?, hash(value.get("hash")?)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    #[test]
+    fn subscription_parse_head() {
+        let confirmation =
+            json!({"jsonrpc": "2.0", "id": 1, "result": "0x9cef478923ff08bf67fde6c64013158d"});
+        assert_eq!(
+            parse_head(Subscription::EthereumNewHeads, &confirmation),
+            None
+        );
+        let eth = json!({"jsonrpc": "2.0", "method": "eth_subscription", "params": {"subscription": "0x9ce", "result": {"number": "0x1b4", "hash": "0xabc"}}});
+        assert_eq!(
+            parse_head(Subscription::EthereumNewHeads, &eth),
+            Some((436, Some("0xabc".to_string())))
+        );
+        let dot = json!({"jsonrpc": "2.0", "method": "chain_newHead", "params": {"subscription": "abc", "result": {"number": "0x10", "parentHash": "0x01"}}});
+        assert_eq!(
+            parse_head(Subscription::PolkadotNewHeads, &dot),
+            Some((16, None))
+        );
+        let dot_finalized = json!({"jsonrpc": "2.0", "method": "chain_finalizedHead", "params": {"subscription": "def", "result": {"number": "0x0e", "parentHash": "0x02"}}});
+        assert_eq!(
+            parse_head(Subscription::PolkadotFinalizedHeads, &dot_finalized),
+            Some((14, None))
+        );
+        assert_eq!(
+            subscribe_request(Subscription::PolkadotFinalizedHeads)["method"],
+            "chain_subscribeFinalizedHeads"
+        );
+        let stark = json!({"jsonrpc": "2.0", "method": "starknet_subscriptionNewHeads", "params": {"subscription_id": 1, "result": {"block_number": 12, "block_hash": "0x12"}}});
+        assert_eq!(
+            parse_head(Subscription::StarknetNewHeads, &stark),
+            Some((12, Some("0x12".to_string())))
+        );
+        let tezos = json!({"hash": "BLxyz", "level": 42, "proto": 17});
+        assert_eq!(
+            parse_head(Subscription::TezosMonitorHeads, &tezos),
+            Some((42, Some("BLxyz".to_string())))
+        );
+    }
+
+    #[tokio::test]
+    async fn subscription_stream_http_invalid_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let body =
+                "{\"hash\": \"BLa\", \"level\": 1}\n{level}\n{\"hash\": \"BLb\", \"level\": 2}\n";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let r = stream_http(
+            Subscription::TezosMonitorHeads,
+            "tezos",
+            &url,
+            &EndpointOptions::test_new(&url, None, None),
+            &Protocol::Tezos,
+            &"mainnet".to_string(),
+            &tx,
+        )
+        .await;
+        // heads before invalid object are pushed, stream is dropped instead of buffering forever
+        assert!(r
+            .unwrap_err()
+            .to_string()
+            .starts_with("monitor heads invalid json"));
+        assert_eq!(rx.recv().await.unwrap().height, 1);
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use serde::Serialize;
+
+/**
+ * SimBlock is a block of the simulated chain, hashes are 32 bytes rendered by each api in its own format
+ * fork is the reorg counter when the block was produced, blocks replaced by a reorg get new hashes
+ */
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SimBlock {
+    pub height: u64,
+    pub hash: [u8; 32],
+    pub parent_hash: [u8; 32],
+    // time is unix time in seconds
+    pub time: u64,
+    pub txs: u64,
+    pub fork: u64,
+}
+impl SimBlock {
+    // hex return block hash as lowercase hex without prefix
+    pub fn hex(&self) -> String {
+        hex::encode(self.hash)
+    }
+    pub fn parent_hex(&self) -> String {
+        hex::encode(self.parent_hash)
+    }
+    // tx_hash return a deterministic hash of the transaction at index
+    pub fn tx_hash(&self, index: u64) -> [u8; 32] {
+        hash32(
+            u64::from_le_bytes(self.hash[..8].try_into().unwrap()),
+            index,
+            0,
+        )
+    }
+}
+
+/**
+ * SimChain is a deterministic chain, blocks only depend on seed, height and reorgs
+ * Two chains built with the same parameters produce the same blocks, tests can assert on them
+ */
+#[derive(Debug, Clone)]
+pub struct SimChain {
+    pub seed: u64,
+    // genesis_time is the unix time of block 0, block time is fixed
+    pub genesis_time: u64,
+    pub block_time: u64,
+    // finality_depth is the number of blocks between head and finalized head
+    pub finality_depth: u64,
+    pub max_txs: u64,
+    forks: u64,
+    blocks: Vec<SimBlock>,
+}
+impl SimChain {
+    pub fn new(seed: u64, height: u64) -> SimChain {
+        let mut chain = SimChain {
+            seed,
+            genesis_time: DEFAULT_GENESIS_TIME,
+            block_time: DEFAULT_BLOCK_TIME,
+            finality_depth: DEFAULT_FINALITY_DEPTH,
+            max_txs: DEFAULT_MAX_TXS,
+            forks: 0,
+            blocks: Vec::with_capacity(height as usize + 1),
+        };
+        chain.blocks.push(chain.new_block(0, [0; 32]));
+        chain.produce(height);
+        chain
+    }
+    pub fn with_block_time(mut self, block_time: u64) -> SimChain {
+        self.block_time = block_time;
+        self.rebuild(0);
+        self
+    }
+    // with_genesis_time move the chain in time, live simulations align head time with current time
+    pub fn with_genesis_time(mut self, genesis_time: u64) -> SimChain {
+        self.genesis_time = genesis_time;
+        self.rebuild(0);
+        self
+    }
+    pub fn with_finality_depth(mut self, finality_depth: u64) -> SimChain {
+        self.finality_depth = finality_depth;
+        self
+    }
+    pub fn head(&self) -> &SimBlock {
+        self.blocks.last().expect("chain has a genesis block")
+    }
+    pub fn height(&self) -> u64 {
+        self.head().height
+    }
+    pub fn forks(&self) -> u64 {
+        self.forks
+    }
+    pub fn block(&self, height: u64) -> Option<&SimBlock> {
+        self.blocks.get(height as usize)
+    }
+    pub fn block_by_hash(&self, hash: &[u8]) -> Option<&SimBlock> {
+        self.blocks.iter().rev().find(|b| b.hash == hash)
+    }
+    // finalized return the finalized block seen by a provider with head at height
+    pub fn finalized(&self, height: u64) -> &SimBlock {
+        &self.blocks[height.saturating_sub(self.finality_depth) as usize]
+    }
+    // produce append n blocks on head
+    pub fn produce(&mut self, n: u64) {
+        for _ in 0..n {
+            let head = self.head();
+            let block = self.new_block(head.height + 1, head.hash);
+            self.blocks.push(block);
+        }
+    }
+    // reorg replace the last depth blocks by a fork of the same height, genesis is never replaced
+    pub fn reorg(&mut self, depth: u64) {
+        let depth = depth.min(self.height());
+        if depth == 0 {
+            return;
+        }
+        self.forks += 1;
+        self.rebuild(self.height() + 1 - depth);
+    }
+    // rebuild produce again blocks from height, with current forks counter
+    fn rebuild(&mut self, from: u64) {
+        let height = self.height();
+        self.blocks.truncate(from.max(1) as usize);
+        if from == 0 {
+            self.blocks[0] = self.new_block(0, [0; 32]);
+        }
+        self.produce(height + 1 - self.blocks.len() as u64);
+    }
+    fn new_block(&self, height: u64, parent_hash: [u8; 32]) -> SimBlock {
+        let fork = if height == 0 { 0 } else { self.forks };
+        let hash = hash32(self.seed, height, fork);
+        SimBlock {
+            height,
+            hash,
+            parent_hash,
+            time: self.genesis_time + height * self.block_time,
+            txs: u64::from_le_bytes(hash[8..16].try_into().unwrap()) % (self.max_txs + 1),
+            fork,
+        }
+    }
+}
+
+const DEFAULT_GENESIS_TIME: u64 = 1_680_000_000;
+const DEFAULT_BLOCK_TIME: u64 = 12;
+const DEFAULT_FINALITY_DEPTH: u64 = 2;
+const DEFAULT_MAX_TXS: u64 = 20;
+
+// splitmix64 is a small deterministic mixer, enough to derive distinct fake hashes
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+fn hash32(seed: u64, height: u64, fork: u64) -> [u8; 32] {
+    let mut state = splitmix64(seed ^ splitmix64(height ^ splitmix64(fork)));
+    let mut hash = [0u8; 32];
+    for chunk in hash.chunks_mut(8) {
+        state = splitmix64(state);
+        chunk.copy_from_slice(&state.to_le_bytes());
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sim_chain_reorg() {
+        let mut chain = SimChain::new(42, 10);
+        assert_eq!(chain.height(), 10);
+        assert_eq!(SimChain::new(42, 10).head(), chain.head());
+        assert_ne!(SimChain::new(43, 10).head().hash, chain.head().hash);
+        for h in 1..=10 {
+            let block = chain.block(h).unwrap();
+            assert_eq!(block.parent_hash, chain.block(h - 1).unwrap().hash);
+            assert_eq!(block.time, chain.genesis_time + h * chain.block_time);
+        }
+
+        let before = chain.clone();
+        chain.reorg(3);
+        assert_eq!(chain.height(), 10);
+        assert_eq!(chain.block(7), before.block(7));
+        assert_ne!(chain.block(8).unwrap().hash, before.block(8).unwrap().hash);
+        assert_eq!(
+            chain.block(8).unwrap().parent_hash,
+            before.block(7).unwrap().hash
+        );
+        assert!(chain.block_by_hash(&before.head().hash).is_none());
+
+        chain.produce(2);
+        assert_eq!(chain.height(), 12);
+        assert_eq!(chain.finalized(12).height, 10);
+        assert_eq!(chain.finalized(1).height, 0);
+    }
+}
@@ -0,0 +1,12 @@
+/*
+ * Simulated chains served with provider apis (json rpc nodes and rest explorers)
+ * A chain is deterministic and controlled by callers: block production, reorgs
+ * Mock servers sharing a chain simulate providers of a network, each one with its own lag, errors and latency
+ */
+pub mod chain;
+mod rest;
+mod rpc;
+pub mod server;
+
+pub use chain::{SimBlock, SimChain};
+pub use server::{MockServer, SharedChain, SimApi, SimBehavior};
@@ -0,0 +1,509 @@
+use actix_web::http::StatusCode;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{SecondsFormat, TimeZone, Utc};
+use serde_json::{json, Map, Value};
+
+use super::{
+    chain::SimBlock,
+    server::{SimApi, SimView},
+};
+
+// BLOCKSTREAM_PAGE is the number of blocks returned by blockstream /blocks/{height}
+const BLOCKSTREAM_PAGE: u64 = 10;
+// TZKT_OPERATIONS are empty operation lists of tzkt full blocks, transactions are filled apart
+const TZKT_OPERATIONS: [&str; 37] = [
+    "endorsements",
+    "preendorsements",
+    "proposals",
+    "ballots",
+    "activations",
+    "doubleBaking",
+    "doubleEndorsing",
+    "doublePreendorsing",
+    "nonceRevelations",
+    "vdfRevelations",
+    "delegations",
+    "originations",
+    "reveals",
+    "registerConstants",
+    "setDepositsLimits",
+    "transferTicketOps",
+    "txRollupCommitOps",
+    "txRollupDispatchTicketsOps",
+    "txRollupFinalizeCommitmentOps",
+    "txRollupOriginationOps",
+    "txRollupRejectionOps",
+    "txRollupRemoveCommitmentOps",
+    "txRollupReturnBondOps",
+    "txRollupSubmitBatchOps",
+    "increasePaidStorageOps",
+    "updateConsensusKeyOps",
+    "drainDelegateOps",
+    "srAddMessagesOps",
+    "srCementOps",
+    "srExecuteOps",
+    "srOriginateOps",
+    "srPublishOps",
+    "srRecoverBondOps",
+    "srRefuteOps",
+    "migrations",
+    "revelationPenalties",
+    "endorsingRewards",
+];
+const TEZOS_PROTOCOL: &str = "PtMumbai2TmsJHNGRkD8v8YDbtao7BLUC3wjASn1inAKLFCjaH1";
+const TEZOS_BAKER: &str = "tz1irJKkXS2DBWkU1NnmFQx1c1L7pbGg4yhk";
+const TEZOS_CYCLE_BLOCKS: u64 = 8192;
+// COMET_MAX_BLOCK_METAS is the maximum number of block metas returned by cometbft /blockchain
+const COMET_MAX_BLOCK_METAS: u64 = 20;
+
+type RestResult = Result<Value, StatusCode>;
+
+// handle answer a rest request of explorer apis, tezos and cosmos nodes
+pub fn handle(view: &SimView, path: &str, query: &str, body: &Value) -> RestResult {
+    let segments = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    match (view.api, segments.as_slice()) {
+        (SimApi::TezosNode, ["chains", "main", "chain_id"]) => Ok(json!(view.chain_id())),
+        (SimApi::TezosNode, ["chains", "main", "is_bootstrapped"]) => Ok(json!({
+            "bootstrapped": !view.behavior.syncing,
+            "sync_state": if view.behavior.syncing { "unsynced" } else { "synced" },
+        })),
+        (SimApi::TezosNode, ["chains", "main", "blocks", id]) => {
+            tezos_block(view, tezos_block_id(view, id)?)
+        }
+        (SimApi::Blockstream, ["blocks", "tip"]) => {
+            Ok(json!([blockstream_block(view, view.head())]))
+        }
+        (SimApi::Blockstream, ["blocks", height]) => {
+            let height = parse_height(height)?.min(view.height);
+            Ok(json!((0..BLOCKSTREAM_PAGE.min(height + 1))
+                .filter_map(|i| view.block(height - i))
+                .map(|b| blockstream_block(view, b))
+                .collect::<Vec<_>>()))
+        }
+        (SimApi::Blockcypher, []) => blockcypher_chain(view),
+        (SimApi::Blockcypher, ["blocks", id]) => {
+            let block = match id.parse::<u64>() {
+                Ok(height) => view.block(height),
+                Err(_) => view.block_by_hash(id),
+            };
+            blockcypher_block(view, block.ok_or(StatusCode::NOT_FOUND)?)
+        }
+        (SimApi::Tzkt, ["v1", "blocks"]) => Ok(json!([{
+            "level": view.height,
+            "hash": view.hash(&view.head().hash),
+        }])),
+        (SimApi::Tzkt, ["v1", "blocks", level]) => {
+            let block = view
+                .block(parse_height(level)?)
+                .ok_or(StatusCode::NO_CONTENT)?;
+            Ok(tzkt_block(view, block))
+        }
+        (SimApi::TzStats, ["explorer", "block", id]) => {
+            let block = match *id {
+                "head" => Some(view.head()),
+                id => match id.parse::<u64>() {
+                    Ok(height) => view.block(height),
+                    Err(_) => view.block_by_hash(id),
+                },
+            };
+            Ok(tzstats_block(view, block.ok_or(StatusCode::NOT_FOUND)?))
+        }
+        (SimApi::Subscan, ["api", "v2", "scan", "blocks"]) => {
+            let row = body.get("row").and_then(Value::as_u64).unwrap_or(10);
+            let page = body.get("page").and_then(Value::as_u64).unwrap_or(0);
+            let from = view.height.saturating_sub(row * page);
+            let blocks = (0..row)
+                .filter_map(|i| from.checked_sub(i).and_then(|h| view.block(h)))
+                .filter(|b| b.height <= from)
+                .map(|b| subscan_block(view, b))
+                .collect::<Vec<_>>();
+            Ok(json!({
+                "code": 0,
+                "message": "Success",
+                "generated_at": view.head().time,
+                "data": {"blocks": blocks, "count": view.height + 1},
+            }))
+        }
+        (SimApi::Subscan, ["api", "v2", "scan", "block"]) => {
+            let height = body.get("block_num").and_then(Value::as_u64);
+            let block = height
+                .and_then(|h| view.block(h))
+                .ok_or(StatusCode::NOT_FOUND)?;
+            Ok(subscan_block(view, block))
+        }
+        (SimApi::CosmosNode, ["status"]) => Ok(comet_result(json!({
+            "node_info": {"network": view.chain_id(), "version": "0.37.2"},
+            "sync_info": {
+                "latest_block_hash": view.hash(&view.head().hash),
+                "latest_block_height": view.height.to_string(),
+                "latest_block_time": rfc3339(view.head().time),
+                "catching_up": view.behavior.syncing,
+            },
+        }))),
+        (SimApi::CosmosNode, ["block"]) => {
+            let block = match query_param(query, "height") {
+                Some(height) => view.block(parse_height(height)?),
+                None => Some(view.head()),
+            };
+            let block = block.ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+            Ok(comet_result(cosmos_block(view, block)))
+        }
+        (SimApi::CosmosNode, ["blockchain"]) => {
+            let max_height = match query_param(query, "maxHeight") {
+                Some(height) => parse_height(height)?.min(view.height),
+                None => view.height,
+            };
+            let min_height = match query_param(query, "minHeight") {
+                Some(height) => parse_height(height)?,
+                None => 1,
+            };
+            let min_height = min_height.max((max_height + 1).saturating_sub(COMET_MAX_BLOCK_METAS));
+            let metas = (min_height..=max_height)
+                .rev()
+                .filter_map(|h| view.block(h))
+                .map(|b| {
+                    let block = cosmos_block(view, b);
+                    json!({
+                        "block_id": block["block_id"],
+                        "block_size": "1000",
+                        "header": block["block"]["header"],
+                        "num_txs": b.txs.to_string(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            Ok(comet_result(json!({
+                "last_height": view.height.to_string(),
+                "block_metas": metas,
+            })))
+        }
+        (SimApi::CosmosLcd, ["cosmos", "base", "tendermint", "v1beta1", "node_info"]) => {
+            Ok(json!({
+                "default_node_info": {"network": view.chain_id(), "version": "0.37.2"},
+                "application_version": {"name": "gaia", "app_name": "gaiad"},
+            }))
+        }
+        (SimApi::CosmosLcd, ["cosmos", "base", "tendermint", "v1beta1", "syncing"]) => {
+            Ok(json!({"syncing": view.behavior.syncing}))
+        }
+        (SimApi::CosmosLcd, ["cosmos", "base", "tendermint", "v1beta1", "blocks", id]) => {
+            let block = match *id {
+                "latest" => Some(view.head()),
+                height => view.block(parse_height(height)?),
+            };
+            Ok(cosmos_block(view, block.ok_or(StatusCode::BAD_REQUEST)?))
+        }
+        _ => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+// query_param return the raw value of key in a query string
+fn query_param<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+fn parse_height(height: &str) -> Result<u64, StatusCode> {
+    height.parse::<u64>().map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+fn rfc3339(time: u64) -> String {
+    Utc.timestamp_opt(time as i64, 0)
+        .single()
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// tezos_block_id resolve head, level, hash and `id~n` predecessor paths
+fn tezos_block_id<'a>(view: &SimView<'a>, id: &str) -> Result<&'a SimBlock, StatusCode> {
+    let (id, depth) = match id.split_once('~') {
+        Some((id, depth)) => (id, parse_height(depth)?),
+        None => (id, 0),
+    };
+    let block = match id {
+        "head" => Some(view.head()),
+        id => match id.parse::<u64>() {
+            Ok(level) => view.block(level),
+            Err(_) => view.block_by_hash(id),
+        },
+    };
+    let block = block.ok_or(StatusCode::NOT_FOUND)?;
+    block
+        .height
+        .checked_sub(depth)
+        .and_then(|h| view.block(h))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+fn tezos_block(view: &SimView, block: &SimBlock) -> RestResult {
+    let chain_id = view.chain_id();
+    let contents = (0..block.txs)
+        .map(|i| json!({"kind": "transaction", "fee": (1000 + i).to_string()}))
+        .collect::<Vec<_>>();
+    let operations = vec![
+        vec![],
+        vec![],
+        vec![],
+        vec![json!({
+            "branch": view.hash(&block.parent_hash),
+            "chain_id": chain_id,
+            "contents": contents,
+            "hash": format!("o{}", hex::encode(block.tx_hash(0))),
+            "protocol": TEZOS_PROTOCOL,
+            "signature": "sigSimulated",
+        })],
+    ];
+    Ok(json!({
+        "chain_id": chain_id,
+        "hash": view.hash(&block.hash),
+        "header": {
+            "context": format!("Co{}", hex::encode(block.tx_hash(u64::MAX))),
+            "fitness": ["02", format!("{:08x}", block.height), "", "ffffffff", "00000000"],
+            "level": block.height,
+            "liquidity_baking_toggle_vote": "pass",
+            "operations_hash": format!("LLo{}", hex::encode(block.tx_hash(u64::MAX - 1))),
+            "payload_hash": format!("vh{}", hex::encode(block.tx_hash(u64::MAX - 2))),
+            "payload_round": 0,
+            "predecessor": view.hash(&block.parent_hash),
+            "proof_of_work_nonce": "0000000000000000",
+            "proto": 16,
+            "signature": "sigSimulated",
+            "timestamp": rfc3339(block.time),
+            "validation_pass": 4,
+        },
+        "metadata": {
+            "baker": TEZOS_BAKER,
+            "baker_consensus_key": TEZOS_BAKER,
+            "balance_updates": [],
+            "deactivated": [],
+            "consumed_milligas": "0",
+            "implicit_operations_results": [],
+            "level_info": {
+                "cycle": block.height / TEZOS_CYCLE_BLOCKS,
+                "cycle_position": block.height % TEZOS_CYCLE_BLOCKS,
+                "expected_commitment": false,
+                "level": block.height,
+                "level_position": block.height.saturating_sub(1),
+            },
+            "liquidity_baking_toggle_ema": 0,
+            "max_block_header_length": 289,
+            "max_operation_data_length": 32768,
+            "max_operation_list_length": [],
+            "max_operations_ttl": 120,
+            "next_protocol": TEZOS_PROTOCOL,
+            "nonce_hash": null,
+            "proposer": TEZOS_BAKER,
+            "proposer_consensus_key": TEZOS_BAKER,
+            "protocol": TEZOS_PROTOCOL,
+            "test_chain_status": {"status": "not_running"},
+            "voting_period_info": {},
+        },
+        "operations": operations,
+        "protocol": TEZOS_PROTOCOL,
+    }))
+}
+
+fn blockstream_block(view: &SimView, block: &SimBlock) -> Value {
+    json!({
+        "id": view.hash(&block.hash),
+        "height": block.height,
+        "version": 1,
+        "timestamp": block.time,
+        "tx_count": block.txs,
+        "size": 1000,
+        "weight": 4000,
+        "merkle_root": hex::encode(block.tx_hash(u64::MAX)),
+        "previousblockhash": view.hash(&block.parent_hash),
+        "mediantime": block.time.saturating_sub(view.chain.block_time * 6),
+        "nonce": 0,
+        "bits": 486604799,
+        "difficulty": 1,
+    })
+}
+
+fn blockcypher_chain(view: &SimView) -> RestResult {
+    let head = view.head();
+    Ok(json!({
+        "name": "BTC.main",
+        "height": head.height,
+        "hash": view.hash(&head.hash),
+        "time": rfc3339(head.time),
+        "latest_url": format!("/blocks/{}", view.hash(&head.hash)),
+        "previous_hash": view.hash(&head.parent_hash),
+        "previous_url": format!("/blocks/{}", view.hash(&head.parent_hash)),
+        "peer_count": 10,
+        "unconfirmed_count": 0,
+        "high_fee_per_kb": 30000,
+        "medium_fee_per_kb": 20000,
+        "low_fee_per_kb": 10000,
+        "last_fork_height": 0,
+        "last_fork_hash": view.hash(&view.chain.block(0).expect("genesis").hash),
+    }))
+}
+
+fn blockcypher_block(view: &SimView, block: &SimBlock) -> RestResult {
+    Ok(json!({
+        "hash": view.hash(&block.hash),
+        "height": block.height,
+        "chain": "BTC.main",
+        "total": 0,
+        "fees": 0,
+        "size": 1000,
+        "vsize": 1000,
+        "ver": 1,
+        "time": rfc3339(block.time),
+        "received_time": rfc3339(block.time),
+        "relayed_by": "127.0.0.1",
+        "bits": 486604799,
+        "nonce": 0,
+        "n_tx": block.txs,
+        "prev_block": view.hash(&block.parent_hash),
+        "mrkl_root": hex::encode(block.tx_hash(u64::MAX)),
+        "txids": (0..block.txs).map(|i| hex::encode(block.tx_hash(i))).collect::<Vec<_>>(),
+        "depth": view.height - block.height,
+        "prev_block_url": format!("/blocks/{}", view.hash(&block.parent_hash)),
+        "tx_url": "/txs/",
+    }))
+}
+
+fn tzkt_block(view: &SimView, block: &SimBlock) -> Value {
+    let mut res = Map::new();
+    for operations in TZKT_OPERATIONS {
+        res.insert(operations.to_string(), json!([]));
+    }
+    res.insert(
+        "transactions".to_string(),
+        json!((0..block.txs)
+            .map(|i| json!({"type": "transaction", "id": i, "level": block.height}))
+            .collect::<Vec<_>>()),
+    );
+    let baker = json!({"address": TEZOS_BAKER});
+    for (key, value) in [
+        ("cycle", json!(block.height / TEZOS_CYCLE_BLOCKS)),
+        ("level", json!(block.height)),
+        ("hash", json!(view.hash(&block.hash))),
+        ("timestamp", json!(rfc3339(block.time))),
+        ("proto", json!(16)),
+        ("payloadRound", json!(0)),
+        ("blockRound", json!(0)),
+        ("validations", json!(7000)),
+        ("deposit", json!(0)),
+        ("reward", json!(10000000)),
+        ("bonus", json!(0)),
+        ("fees", json!(block.txs * 1000)),
+        ("nonceRevealed", json!(false)),
+        ("proposer", baker.clone()),
+        ("producer", baker.clone()),
+        (
+            "software",
+            json!({"date": rfc3339(view.chain.genesis_time)}),
+        ),
+        ("lbToggleEma", json!(0)),
+        ("priority", json!(0)),
+        ("baker", baker),
+        ("lbEscapeVote", json!(false)),
+        ("lbEscapeEma", json!(0)),
+    ] {
+        res.insert(key.to_string(), value);
+    }
+    Value::Object(res)
+}
+
+fn tzstats_block(view: &SimView, block: &SimBlock) -> Value {
+    let mut res = json!({
+        "hash": view.hash(&block.hash),
+        "predecessor": view.hash(&block.parent_hash),
+        "successor": view.block(block.height + 1).map(|b| view.hash(&b.hash)),
+        "protocol": TEZOS_PROTOCOL,
+        "baker": TEZOS_BAKER,
+        "proposer": TEZOS_BAKER,
+        "baker_consensus_key": TEZOS_BAKER,
+        "proposer_consensus_key": TEZOS_BAKER,
+        "height": block.height,
+        "cycle": block.height / TEZOS_CYCLE_BLOCKS,
+        "is_cycle_snapshot": false,
+        "time": rfc3339(block.time),
+        "solvetime": view.chain.block_time,
+        "version": 16,
+        "round": 0,
+        "nonce": "0000000000000000",
+        "voting_period_kind": "proposal",
+    });
+    // block statistics are merged separately, a single json! literal would exceed macro recursion limit
+    let stats = json!({
+        "n_endorsed_slots": 7000,
+        "n_ops_applied": block.txs,
+        "n_ops_failed": 0,
+        "n_events": 0,
+        "n_calls": 0,
+        "n_rollup_calls": 0,
+        "n_tx": block.txs,
+        "volume": 0.0,
+        "fee": block.txs as f64 * 0.001,
+        "reward": 10.0,
+        "deposit": 0.0,
+        "activated_supply": 0.0,
+        "minted_supply": 10.0,
+        "burned_supply": 0.0,
+        "n_accounts": block.txs,
+        "n_new_accounts": 0,
+        "n_new_contracts": 0,
+        "n_cleared_accounts": 0,
+        "n_funded_accounts": 0,
+        "gas_limit": 1040000,
+        "gas_used": block.txs * 1000,
+        "storage_paid": 0,
+        "pct_account_reuse": 100.0,
+        "lb_esc_vote": "pass",
+        "lb_esc_ema": 0,
+    });
+    if let (Value::Object(res), Value::Object(stats)) = (&mut res, stats) {
+        res.extend(stats);
+    }
+    res
+}
+
+fn subscan_block(view: &SimView, block: &SimBlock) -> Value {
+    json!({
+        "block_num": block.height,
+        "block_timestamp": block.time,
+        "hash": view.hash(&block.hash),
+        "event_count": block.txs * 2,
+        "extrinsics_count": block.txs + 1,
+        "finalized": block.height <= view.finalized().height,
+        "account_display": {"address": ""},
+    })
+}
+
+// comet_result wrap a cometbft rpc result, its rest routes answer with json rpc envelopes
+fn comet_result(result: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": -1, "result": result})
+}
+
+// cosmos_block is a block of cometbft rpc and cosmos lcd, heights are strings and transactions base64
+fn cosmos_block(view: &SimView, block: &SimBlock) -> Value {
+    let txs = (0..block.txs)
+        .map(|i| STANDARD.encode(block.tx_hash(i)))
+        .collect::<Vec<_>>();
+    json!({
+        "block_id": {
+            "hash": view.hash(&block.hash),
+            "parts": {"total": 1, "hash": view.hash(&block.tx_hash(u64::MAX))},
+        },
+        "block": {
+            "header": {
+                "chain_id": view.chain_id(),
+                "height": block.height.to_string(),
+                "time": rfc3339(block.time),
+                "last_block_id": {"hash": view.hash(&block.parent_hash)},
+                "proposer_address": hex::encode_upper(&block.tx_hash(u64::MAX - 1)[..20]),
+            },
+            "data": {"txs": txs},
+        },
+    })
+}
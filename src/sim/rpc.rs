@@ -0,0 +1,344 @@
+use serde_json::{json, Value};
+
+use super::{
+    chain::SimBlock,
+    server::{SimApi, SimView},
+};
+
+// json rpc error codes returned by simulated nodes
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const BITCOIN_BLOCK_NOT_FOUND: i64 = -5;
+const STARKNET_BLOCK_NOT_FOUND: i64 = 24;
+const SOLANA_BLOCK_NOT_AVAILABLE: i64 = -32004;
+const SOLANA_NODE_UNHEALTHY: i64 = -32005;
+
+type RpcResult = Result<Value, (i64, String)>;
+
+// handle answer a single json rpc request
+pub fn handle(view: &SimView, req: &Value) -> Value {
+    let id = req.get("id").cloned().unwrap_or(Value::Null);
+    let method = req
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let params = req
+        .get("params")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let res = match view.api {
+        SimApi::BitcoinNode => bitcoin(view, method, &params),
+        SimApi::EthereumNode => ethereum(view, method, &params),
+        SimApi::PolkadotNode => polkadot(view, method, &params),
+        SimApi::StarknetNode => starknet(view, method, &params),
+        SimApi::SolanaNode => solana(view, method, &params),
+        _ => Err((
+            METHOD_NOT_FOUND,
+            format!("{:?} is not a json rpc api", view.api),
+        )),
+    };
+    match res {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": code, "message": message}
+        }),
+    }
+}
+
+fn method_not_found(method: &str) -> RpcResult {
+    Err((METHOD_NOT_FOUND, format!("Method {} not found", method)))
+}
+
+fn hex_u64(n: u64) -> String {
+    format!("0x{:x}", n)
+}
+
+fn bitcoin(view: &SimView, method: &str, params: &[Value]) -> RpcResult {
+    match method {
+        "getbestblockhash" => Ok(json!(view.hash(&view.head().hash))),
+        "getblockchaininfo" => Ok(json!({
+            "chain": view.chain_id(),
+            "blocks": view.height,
+            "headers": view.chain.height(),
+            "bestblockhash": view.hash(&view.head().hash),
+            "difficulty": 1.0,
+            "mediantime": view.head().time,
+            "pruned": false,
+            "initialblockdownload": view.behavior.syncing,
+            "verificationprogress": if view.behavior.syncing { 0.5 } else { 1.0 },
+        })),
+        "getblock" => {
+            let hash = params.first().and_then(Value::as_str).unwrap_or_default();
+            let block = view
+                .block_by_hash(hash)
+                .ok_or((BITCOIN_BLOCK_NOT_FOUND, "Block not found".to_string()))?;
+            let mut res = json!({
+                "hash": view.hash(&block.hash),
+                "confirmations": view.height - block.height + 1,
+                "strippedsize": 1000,
+                "size": 1000,
+                "weight": 4000,
+                "height": block.height,
+                "version": 1,
+                "versionHex": "00000001",
+                "merkleroot": hex::encode(block.tx_hash(u64::MAX)),
+                "tx": (0..block.txs).map(|i| hex::encode(block.tx_hash(i))).collect::<Vec<_>>(),
+                "time": block.time,
+                "nonce": 0,
+                "bits": "1d00ffff",
+                "difficulty": 1.0,
+                "previousblockhash": view.hash(&block.parent_hash),
+            });
+            if let Some(next) = view.block(block.height + 1) {
+                res["nextblockhash"] = json!(view.hash(&next.hash));
+            }
+            Ok(res)
+        }
+        _ => method_not_found(method),
+    }
+}
+
+fn eth_block(view: &SimView, block: &SimBlock, txs: bool) -> Value {
+    let transactions = (0..block.txs)
+        .map(|i| {
+            let hash = format!("0x{}", hex::encode(block.tx_hash(i)));
+            if txs {
+                json!({"hash": hash, "blockNumber": hex_u64(block.height), "transactionIndex": hex_u64(i)})
+            } else {
+                json!(hash)
+            }
+        })
+        .collect::<Vec<_>>();
+    let zero = format!("0x{}", "0".repeat(64));
+    json!({
+        "baseFeePerGas": "0x3b9aca00",
+        "difficulty": "0x0",
+        "extraData": "0x",
+        "gasLimit": "0x1c9c380",
+        "gasUsed": hex_u64(block.txs * 21000),
+        "hash": view.hash(&block.hash),
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "miner": format!("0x{}", "0".repeat(40)),
+        "mixHash": zero,
+        "nonce": "0x0000000000000000",
+        "number": hex_u64(block.height),
+        "parentHash": view.hash(&block.parent_hash),
+        "receiptsRoot": zero,
+        "sha3Uncles": zero,
+        "size": hex_u64(1000 + block.txs * 100),
+        "stateRoot": zero,
+        "timestamp": hex_u64(block.time),
+        "totalDifficulty": "0xc70d815d562d3cfa955",
+        "transactionsRoot": zero,
+        "uncles": [],
+        "transactions": transactions,
+    })
+}
+
+fn ethereum(view: &SimView, method: &str, params: &[Value]) -> RpcResult {
+    match method {
+        "eth_chainId" => Ok(json!(view.chain_id())),
+        "eth_blockNumber" => Ok(json!(hex_u64(view.height))),
+        "eth_syncing" if view.behavior.syncing => Ok(json!({
+            "startingBlock": "0x0",
+            "currentBlock": hex_u64(view.height),
+            "highestBlock": hex_u64(view.chain.height() + 100),
+        })),
+        "eth_syncing" => Ok(json!(false)),
+        "eth_getBlockByNumber" => {
+            let tag = params.first().and_then(Value::as_str).unwrap_or("latest");
+            let txs = params.get(1).and_then(Value::as_bool).unwrap_or(false);
+            let block = match tag {
+                "latest" | "pending" => Some(view.head()),
+                "safe" => Some(view.safe()),
+                "finalized" => Some(view.finalized()),
+                "earliest" => view.block(0),
+                n => {
+                    let n = u64::from_str_radix(n.trim_start_matches("0x"), 16)
+                        .map_err(|e| (INVALID_PARAMS, format!("Invalid block number: {}", e)))?;
+                    view.block(n)
+                }
+            };
+            Ok(block.map_or(Value::Null, |b| eth_block(view, b, txs)))
+        }
+        _ => method_not_found(method),
+    }
+}
+
+// polkadot_timestamp encode timestamp inherent extrinsic, the compact moment is stored in milliseconds
+fn polkadot_timestamp(time: u64) -> String {
+    let mut extrinsic = vec![0x28, 0x04, 0x03, 0x00, 0x0b];
+    extrinsic.extend_from_slice(&(time * 1000).to_le_bytes()[..6]);
+    format!("0x{}", hex::encode(extrinsic))
+}
+
+fn polkadot_header(view: &SimView, block: &SimBlock) -> Value {
+    json!({
+        "parentHash": view.hash(&block.parent_hash),
+        "number": hex_u64(block.height),
+        "stateRoot": view.hash(&block.tx_hash(u64::MAX)),
+        "extrinsicsRoot": view.hash(&block.tx_hash(u64::MAX - 1)),
+        "digest": {"logs": []},
+    })
+}
+
+// polkadot_block return block of hash param, head if there is no param
+fn polkadot_block<'a>(
+    view: &SimView<'a>,
+    params: &[Value],
+) -> Result<Option<&'a SimBlock>, (i64, String)> {
+    match params.first() {
+        None | Some(Value::Null) => Ok(Some(view.head())),
+        Some(Value::String(hash)) => Ok(view.block_by_hash(hash)),
+        Some(p) => Err((INVALID_PARAMS, format!("Invalid block hash {}", p))),
+    }
+}
+
+fn polkadot(view: &SimView, method: &str, params: &[Value]) -> RpcResult {
+    match method {
+        "chain_getBlockHash" => {
+            let block = match params.first() {
+                None | Some(Value::Null) => Some(view.head()),
+                Some(n) => n.as_u64().and_then(|n| view.block(n)),
+            };
+            Ok(block.map_or(Value::Null, |b| json!(view.hash(&b.hash))))
+        }
+        "chain_getFinalizedHead" => Ok(json!(view.hash(&view.finalized().hash))),
+        "chain_getHeader" => {
+            Ok(polkadot_block(view, params)?.map_or(Value::Null, |b| polkadot_header(view, b)))
+        }
+        "chain_getBlock" => Ok(polkadot_block(view, params)?.map_or(Value::Null, |b| {
+            let mut extrinsics = vec![polkadot_timestamp(b.time)];
+            extrinsics.extend((0..b.txs).map(|i| format!("0x{}", hex::encode(b.tx_hash(i)))));
+            json!({
+                "block": {"header": polkadot_header(view, b), "extrinsics": extrinsics},
+                "justifications": null,
+            })
+        })),
+        "system_health" => Ok(json!({
+            "isSyncing": view.behavior.syncing,
+            "peers": 10,
+            "shouldHavePeers": true,
+        })),
+        _ => method_not_found(method),
+    }
+}
+
+fn starknet_block(view: &SimView, block: &SimBlock, txs: bool) -> Value {
+    let status = if block.height <= view.finalized().height {
+        "ACCEPTED_ON_L1"
+    } else {
+        "ACCEPTED_ON_L2"
+    };
+    let transactions = (0..block.txs)
+        .map(|i| {
+            let hash = view.hash(&block.tx_hash(i));
+            if txs {
+                json!({"transaction_hash": hash, "type": "INVOKE", "version": "0x1"})
+            } else {
+                json!(hash)
+            }
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "status": status,
+        "block_hash": view.hash(&block.hash),
+        "parent_hash": view.hash(&block.parent_hash),
+        "block_number": block.height,
+        "new_root": view.hash(&block.tx_hash(u64::MAX)),
+        "timestamp": block.time,
+        "sequencer_address": "0x1176a1bd84444c89232ec27754698e5d2e7e1a7f1539f12027f28b23ec9f3d8",
+        "transactions": transactions,
+    })
+}
+
+fn starknet(view: &SimView, method: &str, params: &[Value]) -> RpcResult {
+    match method {
+        "starknet_chainId" => Ok(json!(view.chain_id())),
+        "starknet_blockHashAndNumber" => Ok(json!({
+            "block_hash": view.hash(&view.head().hash),
+            "block_number": view.height,
+        })),
+        "starknet_syncing" if view.behavior.syncing => Ok(json!({
+            "starting_block_num": 0,
+            "current_block_num": view.height,
+            "highest_block_num": view.chain.height() + 100,
+        })),
+        "starknet_syncing" => Ok(json!(false)),
+        "starknet_getBlockWithTxs" | "starknet_getBlockWithTxHashes" => {
+            let block = match params.first() {
+                Some(Value::String(tag)) if tag == "latest" || tag == "pending" => {
+                    Some(view.head())
+                }
+                Some(Value::Object(id)) => match (id.get("block_number"), id.get("block_hash")) {
+                    (Some(n), _) => n.as_u64().and_then(|n| view.block(n)),
+                    (_, Some(Value::String(hash))) => view.block_by_hash(hash),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let block = block.ok_or((STARKNET_BLOCK_NOT_FOUND, "Block not found".to_string()))?;
+            Ok(starknet_block(
+                view,
+                block,
+                method == "starknet_getBlockWithTxs",
+            ))
+        }
+        _ => method_not_found(method),
+    }
+}
+
+// solana_commitment return the block seen at commitment of the first object param, slots are chain heights
+fn solana_commitment<'a>(view: &SimView<'a>, params: &[Value]) -> &'a SimBlock {
+    let commitment = params
+        .iter()
+        .find_map(|p| p.get("commitment"))
+        .and_then(Value::as_str);
+    match commitment {
+        Some("finalized") => view.finalized(),
+        _ => view.head(),
+    }
+}
+
+fn solana(view: &SimView, method: &str, params: &[Value]) -> RpcResult {
+    match method {
+        "getGenesisHash" => Ok(json!(view.chain_id())),
+        "getHealth" if view.behavior.syncing => {
+            Err((SOLANA_NODE_UNHEALTHY, "Node is unhealthy".to_string()))
+        }
+        "getHealth" => Ok(json!("ok")),
+        "getSlot" => Ok(json!(solana_commitment(view, params).height)),
+        "getBlockHeight" => Ok(json!(solana_commitment(view, params).height)),
+        "getLatestBlockhash" => {
+            let block = solana_commitment(view, params);
+            Ok(json!({
+                "context": {"slot": block.height},
+                "value": {
+                    "blockhash": view.hash(&block.hash),
+                    "lastValidBlockHeight": block.height + 150,
+                },
+            }))
+        }
+        "getBlock" => {
+            let slot = params.first().and_then(Value::as_u64).unwrap_or_default();
+            let block = view.block(slot).ok_or((
+                SOLANA_BLOCK_NOT_AVAILABLE,
+                format!("Block not available for slot {}", slot),
+            ))?;
+            Ok(json!({
+                "blockhash": view.hash(&block.hash),
+                "previousBlockhash": view.hash(&block.parent_hash),
+                "parentSlot": block.height.saturating_sub(1),
+                "blockHeight": block.height,
+                "blockTime": block.time,
+                "signatures": (0..block.txs)
+                    .map(|i| hex::encode(block.tx_hash(i)))
+                    .collect::<Vec<_>>(),
+            }))
+        }
+        _ => method_not_found(method),
+    }
+}
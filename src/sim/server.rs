@@ -0,0 +1,322 @@
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+
+use actix_web::{
+    dev::ServerHandle,
+    http::{header, StatusCode},
+    web, App, HttpRequest, HttpResponse, HttpServer,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{
+    chain::{SimBlock, SimChain},
+    rest, rpc,
+};
+
+pub type SharedChain = Arc<Mutex<SimChain>>;
+
+/**
+ * SimApi is the provider api served by a mock server, json rpc nodes or rest explorers
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SimApi {
+    BitcoinNode,
+    EthereumNode,
+    TezosNode,
+    PolkadotNode,
+    StarknetNode,
+    Blockstream,
+    Blockcypher,
+    Tzkt,
+    #[serde(rename = "tzstats")]
+    TzStats,
+    Subscan,
+    SolanaNode,
+    CosmosNode,
+    CosmosLcd,
+}
+impl SimApi {
+    pub fn is_rpc(&self) -> bool {
+        matches!(
+            self,
+            SimApi::BitcoinNode
+                | SimApi::EthereumNode
+                | SimApi::PolkadotNode
+                | SimApi::StarknetNode
+                | SimApi::SolanaNode
+        )
+    }
+    // chain_id return the chain identity served by default, polkadot and solana identity is the genesis hash
+    pub fn chain_id(&self, chain: &SimChain) -> String {
+        match self {
+            SimApi::BitcoinNode | SimApi::Blockstream | SimApi::Blockcypher => "main".to_string(),
+            SimApi::EthereumNode => "0x1".to_string(),
+            SimApi::TezosNode | SimApi::Tzkt | SimApi::TzStats => "NetXdQprcVkpaWU".to_string(),
+            SimApi::PolkadotNode | SimApi::Subscan | SimApi::SolanaNode => {
+                format!("0x{}", chain.block(0).expect("genesis").hex())
+            }
+            SimApi::StarknetNode => "0x534e5f4d41494e".to_string(),
+            SimApi::CosmosNode | SimApi::CosmosLcd => "cosmoshub-4".to_string(),
+        }
+    }
+}
+
+/**
+ * SimBehavior control how a mock server answer, it can be changed while the server is running
+ * fail_next requests fail with fail_status, then error_rate and rate_limit_rate are random 500 and 429 ratios
+ */
+#[derive(Debug, Clone)]
+pub struct SimBehavior {
+    // lag is the number of blocks the provider is behind the chain head
+    pub lag: u64,
+    pub latency: Duration,
+    pub fail_next: u32,
+    pub fail_status: u16,
+    pub error_rate: f64,
+    pub rate_limit_rate: f64,
+    // retry_after is sent with 429 responses, in seconds
+    pub retry_after: Option<u64>,
+    pub basic_auth: Option<(String, String)>,
+    pub syncing: bool,
+    // chain_id override the chain identity served by default
+    pub chain_id: Option<String>,
+}
+impl Default for SimBehavior {
+    fn default() -> Self {
+        SimBehavior {
+            lag: 0,
+            latency: Duration::ZERO,
+            fail_next: 0,
+            fail_status: 500,
+            error_rate: 0.0,
+            rate_limit_rate: 0.0,
+            retry_after: None,
+            basic_auth: None,
+            syncing: false,
+            chain_id: None,
+        }
+    }
+}
+
+/**
+ * SimView is the chain as seen by a provider, blocks above its lagging head don't exist yet
+ */
+pub struct SimView<'a> {
+    pub api: SimApi,
+    pub chain: &'a SimChain,
+    pub behavior: &'a SimBehavior,
+    pub height: u64,
+}
+impl<'a> SimView<'a> {
+    pub fn head(&self) -> &'a SimBlock {
+        self.chain
+            .block(self.height)
+            .expect("view height is in chain")
+    }
+    pub fn block(&self, height: u64) -> Option<&'a SimBlock> {
+        if height > self.height {
+            return None;
+        }
+        self.chain.block(height)
+    }
+    // block_by_hash accept hashes with or without 0x or tezos B prefix
+    pub fn block_by_hash(&self, hash: &str) -> Option<&'a SimBlock> {
+        let hash = hash.trim_start_matches("0x").trim_start_matches('B');
+        let hash = hex::decode(hash).ok()?;
+        self.chain
+            .block_by_hash(&hash)
+            .filter(|b| b.height <= self.height)
+    }
+    pub fn finalized(&self) -> &'a SimBlock {
+        self.chain.finalized(self.height)
+    }
+    // safe head is half way between head and finalized head
+    pub fn safe(&self) -> &'a SimBlock {
+        let finalized = self.finalized().height;
+        self.block(finalized + (self.height - finalized) / 2)
+            .expect("safe head is below head")
+    }
+    pub fn chain_id(&self) -> String {
+        self.behavior
+            .chain_id
+            .clone()
+            .unwrap_or_else(|| self.api.chain_id(self.chain))
+    }
+    // hash render a block hash in api format, cosmos lcd encode hashes in base64
+    pub fn hash(&self, hash: &[u8; 32]) -> String {
+        match self.api {
+            SimApi::BitcoinNode | SimApi::Blockstream | SimApi::Blockcypher => hex::encode(hash),
+            SimApi::TezosNode | SimApi::Tzkt | SimApi::TzStats => format!("B{}", hex::encode(hash)),
+            SimApi::CosmosNode => hex::encode_upper(hash),
+            SimApi::CosmosLcd => STANDARD.encode(hash),
+            _ => format!("0x{}", hex::encode(hash)),
+        }
+    }
+}
+
+struct SimState {
+    api: SimApi,
+    chain: SharedChain,
+    behavior: Arc<Mutex<SimBehavior>>,
+    requests: Arc<AtomicU64>,
+    rng: Mutex<StdRng>,
+}
+
+/**
+ * MockServer serve a simulated provider api on a local port, servers sharing a chain simulate providers of the same network
+ * Server is stopped when dropped
+ */
+pub struct MockServer {
+    pub api: SimApi,
+    addr: SocketAddr,
+    chain: SharedChain,
+    behavior: Arc<Mutex<SimBehavior>>,
+    requests: Arc<AtomicU64>,
+    handle: ServerHandle,
+}
+impl MockServer {
+    // start serve api on a random local port, it must be called from a tokio runtime
+    pub fn start(api: SimApi, chain: SharedChain) -> std::io::Result<MockServer> {
+        Self::start_on(api, chain, SocketAddr::from(([127, 0, 0, 1], 0)))
+    }
+    pub fn start_on(
+        api: SimApi,
+        chain: SharedChain,
+        addr: SocketAddr,
+    ) -> std::io::Result<MockServer> {
+        let behavior = Arc::new(Mutex::new(SimBehavior::default()));
+        let requests = Arc::new(AtomicU64::new(0));
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let state = web::Data::new(SimState {
+            api,
+            chain: chain.clone(),
+            behavior: behavior.clone(),
+            requests: requests.clone(),
+            rng: Mutex::new(StdRng::seed_from_u64(addr.port() as u64)),
+        });
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(state.clone())
+                .default_service(web::to(handle))
+        })
+        .workers(1)
+        .disable_signals()
+        .shutdown_timeout(0)
+        .listen(listener)?
+        .run();
+        let handle = server.handle();
+        tokio::spawn(server);
+        Ok(MockServer {
+            api,
+            addr,
+            chain,
+            behavior,
+            requests,
+            handle,
+        })
+    }
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+    pub fn chain(&self) -> MutexGuard<'_, SimChain> {
+        self.chain.lock().unwrap()
+    }
+    pub fn behavior(&self) -> MutexGuard<'_, SimBehavior> {
+        self.behavior.lock().unwrap()
+    }
+    // requests return the number of requests received, failed ones included
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+}
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        // stop command is sent eagerly, there is no need to wait for the server
+        drop(self.handle.stop(false));
+    }
+}
+
+async fn handle(req: HttpRequest, body: web::Bytes, state: web::Data<SimState>) -> HttpResponse {
+    state.requests.fetch_add(1, Ordering::Relaxed);
+    let (behavior, status) = {
+        let mut behavior = state.behavior.lock().unwrap();
+        let status = if behavior.fail_next > 0 {
+            behavior.fail_next -= 1;
+            Some(behavior.fail_status)
+        } else {
+            let mut rng = state.rng.lock().unwrap();
+            if rng.gen_bool(behavior.rate_limit_rate.clamp(0.0, 1.0)) {
+                Some(429)
+            } else if rng.gen_bool(behavior.error_rate.clamp(0.0, 1.0)) {
+                Some(500)
+            } else {
+                None
+            }
+        };
+        (behavior.clone(), status)
+    };
+    if !behavior.latency.is_zero() {
+        tokio::time::sleep(behavior.latency).await;
+    }
+    if let Some((username, password)) = &behavior.basic_auth {
+        let expected = format!(
+            "Basic {}",
+            STANDARD.encode(format!("{}:{}", username, password))
+        );
+        let authorized = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .is_some_and(|v| v.as_bytes() == expected.as_bytes());
+        if !authorized {
+            return HttpResponse::Unauthorized().finish();
+        }
+    }
+    if let Some(status) = status {
+        let mut res = HttpResponse::build(
+            StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        );
+        if let (429, Some(retry_after)) = (status, behavior.retry_after) {
+            res.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+        }
+        return res.finish();
+    }
+    let chain = state.chain.lock().unwrap();
+    let view = SimView {
+        api: state.api,
+        chain: &chain,
+        behavior: &behavior,
+        height: chain.height().saturating_sub(behavior.lag),
+    };
+    if state.api.is_rpc() {
+        return match serde_json::from_slice::<Value>(&body) {
+            Ok(Value::Array(batch)) => HttpResponse::Ok().json(
+                batch
+                    .iter()
+                    .map(|r| rpc::handle(&view, r))
+                    .collect::<Vec<_>>(),
+            ),
+            Ok(r) => HttpResponse::Ok().json(rpc::handle(&view, &r)),
+            Err(e) => HttpResponse::Ok().json(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": {"code": -32700, "message": format!("Parse error: {}", e)}
+            })),
+        };
+    }
+    let body = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+    match rest::handle(&view, req.path(), req.query_string(), &body) {
+        Ok(res) => HttpResponse::Ok().json(res),
+        Err(status) => HttpResponse::build(status).finish(),
+    }
+}
@@ -1,3 +1,15 @@
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+use crate::sim::{MockServer, SimApi, SimChain};
+
+// MOCK_SEED and MOCK_HEIGHT define the simulated chain of mock servers, tests can rely on its blocks
+#[cfg(test)]
+pub const MOCK_SEED: u64 = 42;
+#[cfg(test)]
+pub const MOCK_HEIGHT: u64 = 100;
+
 #[cfg(test)]
 pub fn setup() {
     let _ = env_logger::builder()
@@ -5,3 +17,10 @@ pub fn setup() {
         .filter(Some("blockhead"), log::LevelFilter::Trace)
         .try_init();
 }
+
+// mock_server serve api on a new simulated chain, it must be called from a tokio runtime
+#[cfg(test)]
+pub fn mock_server(api: SimApi) -> MockServer {
+    let chain = SimChain::new(MOCK_SEED, MOCK_HEIGHT);
+    MockServer::start(api, Arc::new(Mutex::new(chain))).expect("mock server can start")
+}
@@ -38,6 +38,17 @@ where
         }
     }
 }
+// deserialize_from_str_to_u64 parse integers encoded as decimal strings (CometBFT, Cosmos LCD)
+pub fn deserialize_from_str_to_u64<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse::<u64>().map_err(|e| {
+        error!("deserialize_from_str_to_u64 error: {} {}", e, s);
+        serde::de::Error::custom(format!("deserialize_from_str_to_u64 error: {} {}", e, s))
+    })
+}
 #[cfg(test)]
 use crate::commons::blockchain;
 #[cfg(test)]